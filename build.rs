@@ -0,0 +1,43 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+// Embeds the endoflife.date responses in eol-snapshot/ for `--offline`
+fn main() {
+    let dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("eol-snapshot");
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.retain(|path| path.extension().is_some_and(|e| e == "json"));
+    files.sort();
+    // --offline then only works from the cache of earlier runs, which an
+    // air-gapped host doesn't have. Release builds need the snapshot.
+    if files.is_empty() {
+        if std::env::var("PROFILE").as_deref() == Ok("release") {
+            eprintln!(
+                "eol-snapshot/ holds no products, run `azindex eol snapshot eol-snapshot` with a debug build first"
+            );
+            std::process::exit(1);
+        }
+        println!(
+            "cargo:warning=eol-snapshot/ holds no products, run `azindex eol snapshot eol-snapshot` before a release"
+        );
+    }
+
+    let mut snapshot = String::from("&[\n");
+    for path in files {
+        let product = path.file_stem().unwrap().to_string_lossy();
+        println!("cargo:rerun-if-changed={}", path.display());
+        writeln!(snapshot, "    ({:?}, include_str!({:?})),", product, path).unwrap();
+    }
+    snapshot.push(']');
+
+    let out = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(out.join("eol_snapshot.rs"), snapshot).unwrap();
+
+    // Written by `azindex eol snapshot` next to the products
+    let date = std::fs::read_to_string(dir.join("DATE")).ok();
+    let date = format!("{:?}", date.as_deref().map(str::trim));
+    std::fs::write(out.join("eol_snapshot_date.rs"), date).unwrap();
+}
@@ -0,0 +1,679 @@
+use crate::http;
+use crate::inventory::arm::Cloud;
+use crate::logging::Logger;
+use async_trait::async_trait;
+use azure_core::auth::{AccessToken, TokenCredential, TokenResponse};
+use azure_core::error::ErrorKind;
+use azure_core::new_http_client;
+use azure_identity::device_code_flow;
+use azure_identity::{
+    AutoRefreshingTokenCredential, AzureCliCredential, CertificateCredentialOptions,
+    ClientCertificateCredential, ClientSecretCredential, EnvironmentCredential,
+    ImdsManagedIdentityCredential, TokenCredentialOptions, WorkloadIdentityCredential,
+};
+use chrono::{Local, NaiveDateTime, TimeZone};
+use futures::stream::StreamExt;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::PKey;
+use openssl::stack::Stack;
+use openssl::x509::X509;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+
+type AuthError = Box<dyn std::error::Error>;
+
+/// How azindex signs in to Azure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Auth {
+    // Service principal from AZURE_TENANT_ID, AZURE_CLIENT_ID and a secret,
+    // certificate or federated token
+    Environment,
+    // The Azure CLI's login (`az login`)
+    AzureCli,
+    // System- or user-assigned identity of the VM/container running azindex
+    ManagedIdentity,
+    // Service principal with a certificate instead of a secret
+    ClientCertificate,
+    // Interactive sign-in with a code entered on another device
+    DeviceCode,
+    // Federated token of an AKS pod (AZURE_FEDERATED_TOKEN_FILE) or a GitHub
+    // Actions job exchanged for a token, no secret involved
+    WorkloadIdentity,
+}
+
+impl FromStr for Auth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "environment" | "env" => Ok(Auth::Environment),
+            "azure-cli" | "cli" => Ok(Auth::AzureCli),
+            "managed-identity" | "msi" => Ok(Auth::ManagedIdentity),
+            "client-certificate" | "certificate" => Ok(Auth::ClientCertificate),
+            "device-code" => Ok(Auth::DeviceCode),
+            "workload-identity" | "federated" => Ok(Auth::WorkloadIdentity),
+            _ => Err(format!(
+                "invalid auth '{}', expected environment, azure-cli, managed-identity, client-certificate, device-code or workload-identity",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthOptions {
+    // Picked from the other options when not given
+    pub auth: Option<Auth>,
+    pub tenant_id: Option<String>,
+    // Service principal, or the user-assigned identity to use
+    pub client_id: Option<String>,
+    // PKCS#12 (.pfx) or PEM with the private key and certificate chain
+    pub client_cert: Option<PathBuf>,
+    pub password: Option<String>,
+}
+
+/// The credential every client uses and the tenant it signs in to, as far
+/// as it is known up front
+pub async fn credential(
+    options: &AuthOptions,
+    cloud: Cloud,
+) -> Result<(Arc<dyn TokenCredential>, Option<String>), AuthError> {
+    let auth = match (options.auth, &options.client_cert) {
+        (Some(auth), _) => auth,
+        (None, Some(_)) => Auth::ClientCertificate,
+        (None, None) => detect(options, cloud).await,
+    };
+    match auth {
+        Auth::Environment => environment_credential(options, cloud),
+        Auth::AzureCli => match &options.tenant_id {
+            Some(tenant) => {
+                let credential = AzureCliTenantCredential {
+                    tenant: tenant.clone(),
+                };
+                Ok((Arc::new(credential), Some(tenant.clone())))
+            }
+            None => {
+                let tenant = AzureCliCredential::get_tenant()?;
+                Ok((Arc::new(AzureCliCredential::new()), Some(tenant)))
+            }
+        },
+        Auth::ManagedIdentity => Ok((managed_identity(options), options.tenant_id.clone())),
+        Auth::ClientCertificate => certificate_credential(options, cloud),
+        Auth::DeviceCode => device_code_credential(options, cloud).await,
+        Auth::WorkloadIdentity => workload_identity(options, cloud),
+    }
+}
+
+// How long the managed identity endpoint gets to answer before assuming
+// there is none, it is link-local and answers quickly where it exists
+const MANAGED_IDENTITY_PROBE: Duration = Duration::from_secs(2);
+
+/// Picks the first method that is available, like DefaultAzureCredential:
+/// a service principal in the environment, then a managed identity, then
+/// the Azure CLI. Decided once instead of per token.
+async fn detect(options: &AuthOptions, cloud: Cloud) -> Auth {
+    let mut log = Logger::new();
+    if federated_token_source().is_some()
+        && std::env::var("AZURE_TENANT_ID").is_ok()
+        && std::env::var("AZURE_CLIENT_ID").is_ok()
+    {
+        log.info("Signing in with workload identity federation");
+        return Auth::WorkloadIdentity;
+    }
+    if environment_configured() {
+        log.info("Signing in with the service principal from the environment");
+        return Auth::Environment;
+    }
+    let probe = managed_identity(options);
+    let resource = cloud.resource();
+    if let Ok(Ok(_)) =
+        tokio::time::timeout(MANAGED_IDENTITY_PROBE, probe.get_token(&resource)).await
+    {
+        log.info("Signing in with the managed identity");
+        return Auth::ManagedIdentity;
+    }
+    log.info("Signing in with the Azure CLI");
+    Auth::AzureCli
+}
+
+fn environment_configured() -> bool {
+    let set = |name: &str| std::env::var(name).is_ok_and(|v| !v.is_empty());
+    set("AZURE_TENANT_ID")
+        && set("AZURE_CLIENT_ID")
+        && (set("AZURE_CLIENT_SECRET")
+            || set("AZURE_CLIENT_CERTIFICATE_PATH")
+            || set("AZURE_FEDERATED_TOKEN")
+            || set("AZURE_FEDERATED_TOKEN_FILE"))
+}
+
+fn environment_credential(
+    options: &AuthOptions,
+    cloud: Cloud,
+) -> Result<(Arc<dyn TokenCredential>, Option<String>), AuthError> {
+    if !environment_configured() {
+        return Err("--auth environment needs AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET, AZURE_CLIENT_CERTIFICATE_PATH or AZURE_FEDERATED_TOKEN_FILE".into());
+    }
+    // azure_identity's environment credential doesn't do certificates
+    if let Ok(path) = std::env::var("AZURE_CLIENT_CERTIFICATE_PATH") {
+        let options = AuthOptions {
+            client_cert: Some(PathBuf::from(path)),
+            ..options.clone()
+        };
+        return certificate_credential(&options, cloud);
+    }
+    // Another tenant the service principal's app is registered in
+    if let (Some(tenant_id), Ok(client_id), Ok(secret)) = (
+        &options.tenant_id,
+        std::env::var("AZURE_CLIENT_ID"),
+        std::env::var("AZURE_CLIENT_SECRET"),
+    ) {
+        let credential = ClientSecretCredential::new(
+            new_http_client(),
+            tenant_id.clone(),
+            client_id,
+            secret,
+            TokenCredentialOptions::new(cloud.authority_host().to_string()),
+        );
+        let credential = AutoRefreshingTokenCredential::new(Arc::new(credential));
+        return Ok((Arc::new(credential), Some(tenant_id.clone())));
+    }
+    let credential = EnvironmentCredential::new(
+        new_http_client(),
+        TokenCredentialOptions::new(cloud.authority_host().to_string()),
+    );
+    let credential = AutoRefreshingTokenCredential::new(Arc::new(credential));
+    Ok((Arc::new(credential), std::env::var("AZURE_TENANT_ID").ok()))
+}
+
+/// Where the token proving the workload's identity comes from
+#[derive(Debug, Clone)]
+enum FederatedToken {
+    // Projected into AKS pods and rotated, read again for every exchange
+    File(PathBuf),
+    // GitHub Actions job with `permissions: id-token: write`
+    GitHubActions { url: String, token: String },
+}
+
+fn federated_token_source() -> Option<FederatedToken> {
+    if let Ok(path) = std::env::var("AZURE_FEDERATED_TOKEN_FILE") {
+        return Some(FederatedToken::File(PathBuf::from(path)));
+    }
+    let url = std::env::var("ACTIONS_ID_TOKEN_REQUEST_URL").ok()?;
+    let token = std::env::var("ACTIONS_ID_TOKEN_REQUEST_TOKEN").ok()?;
+    Some(FederatedToken::GitHubActions { url, token })
+}
+
+impl FederatedToken {
+    async fn read(&self) -> Result<String, AuthError> {
+        match self {
+            FederatedToken::File(path) => Ok(std::fs::read_to_string(path)?.trim().to_string()),
+            FederatedToken::GitHubActions { url, token } => {
+                #[derive(serde::Deserialize)]
+                struct IdToken {
+                    value: String,
+                }
+                let request = http::client()
+                    .get(url.as_str())
+                    .query(&[("audience", "api://AzureADTokenExchange")])
+                    .bearer_auth(token);
+                let response = http::send(request).await?.error_for_status()?;
+                Ok(response.json::<IdToken>().await?.value)
+            }
+        }
+    }
+}
+
+fn workload_identity(
+    options: &AuthOptions,
+    cloud: Cloud,
+) -> Result<(Arc<dyn TokenCredential>, Option<String>), AuthError> {
+    let source = federated_token_source().ok_or(
+        "--auth workload-identity needs AZURE_FEDERATED_TOKEN_FILE or a GitHub Actions job with id-token: write",
+    )?;
+    let tenant_id = setting(
+        &options.tenant_id,
+        "AZURE_TENANT_ID",
+        "--tenant-id",
+        "workload-identity",
+    )?;
+    let client_id = setting(
+        &options.client_id,
+        "AZURE_CLIENT_ID",
+        "--client-id",
+        "workload-identity",
+    )?;
+    // Set by the AKS webhook, e.g. for sovereign clouds
+    let authority_host = std::env::var("AZURE_AUTHORITY_HOST")
+        .unwrap_or_else(|_| cloud.authority_host().to_string());
+    let credential = FederatedCredential {
+        tenant_id: tenant_id.clone(),
+        client_id,
+        authority_host,
+        source,
+    };
+    let credential = AutoRefreshingTokenCredential::new(Arc::new(credential));
+    Ok((Arc::new(credential), Some(tenant_id)))
+}
+
+/// Exchanges a freshly read federated token for an access token every time
+/// one is needed, the federated tokens are short-lived
+struct FederatedCredential {
+    tenant_id: String,
+    client_id: String,
+    authority_host: String,
+    source: FederatedToken,
+}
+
+#[async_trait]
+impl TokenCredential for FederatedCredential {
+    async fn get_token(&self, resource: &str) -> azure_core::Result<TokenResponse> {
+        let assertion = self.source.read().await.map_err(|e| {
+            azure_core::Error::message(
+                ErrorKind::Credential,
+                format!("reading the federated token failed: {}", e),
+            )
+        })?;
+        let mut credential = WorkloadIdentityCredential::new(
+            new_http_client(),
+            self.tenant_id.clone(),
+            self.client_id.clone(),
+            assertion,
+        );
+        credential.set_options(TokenCredentialOptions::new(self.authority_host.clone()));
+        credential.get_token(resource).await
+    }
+}
+
+/// IMDS on VMs, IDENTITY_ENDPOINT in App Service and Container Apps. A
+/// client ID picks a user-assigned identity.
+fn managed_identity(options: &AuthOptions) -> Arc<dyn TokenCredential> {
+    let mut credential = ImdsManagedIdentityCredential::default();
+    let client_id = options
+        .client_id
+        .clone()
+        .or_else(|| std::env::var("AZURE_CLIENT_ID").ok());
+    if let Some(client_id) = client_id {
+        credential = credential.with_client_id(client_id);
+    }
+    Arc::new(AutoRefreshingTokenCredential::new(Arc::new(credential)))
+}
+
+fn certificate_credential(
+    options: &AuthOptions,
+    cloud: Cloud,
+) -> Result<(Arc<dyn TokenCredential>, Option<String>), AuthError> {
+    let path = options
+        .client_cert
+        .as_ref()
+        .ok_or("--client-cert is required with --auth client-certificate")?;
+    let tenant_id = setting(
+        &options.tenant_id,
+        "AZURE_TENANT_ID",
+        "--tenant-id",
+        "client-certificate",
+    )?;
+    let client_id = setting(
+        &options.client_id,
+        "AZURE_CLIENT_ID",
+        "--client-id",
+        "client-certificate",
+    )?;
+    let password = options
+        .password
+        .clone()
+        .or_else(|| std::env::var("AZURE_CLIENT_CERTIFICATE_PASSWORD").ok())
+        .unwrap_or_default();
+    let pkcs12 = load_pkcs12(path, &password)?;
+    // The chain is sent along so subject name/issuer registrations work too
+    let options = CertificateCredentialOptions::new(cloud.authority_host().to_string(), true);
+    let credential = ClientCertificateCredential::new(
+        tenant_id.clone(),
+        client_id,
+        azure_core::base64::encode(pkcs12),
+        password,
+        options,
+    );
+    // Tokens are signed for and fetched once, not per request
+    let credential = AutoRefreshingTokenCredential::new(Arc::new(credential));
+    Ok((Arc::new(credential), Some(tenant_id)))
+}
+
+// Public client ID of the Azure CLI, users of any tenant can sign in to it
+// without an app registration of their own
+const AZURE_CLI_CLIENT_ID: &str = "04b07795-8ddb-461a-bbee-02f9e1bf7b46";
+
+/// Prints the code and URL to sign in with and waits until the user did
+async fn device_code_credential(
+    options: &AuthOptions,
+    cloud: Cloud,
+) -> Result<(Arc<dyn TokenCredential>, Option<String>), AuthError> {
+    // azure_identity only implements the flow against the public cloud
+    if cloud != Cloud::Public {
+        return Err("--auth device-code only supports --cloud azure-public".into());
+    }
+    let tenant_id = options
+        .tenant_id
+        .clone()
+        .unwrap_or_else(|| String::from("organizations"));
+    let client_id = options.client_id.as_deref().unwrap_or(AZURE_CLI_CLIENT_ID);
+    let scope = format!("{}.default", cloud.resource());
+    let flow = device_code_flow::start(
+        new_http_client(),
+        tenant_id.clone(),
+        client_id,
+        &[&scope, "offline_access"],
+    )
+    .await?;
+    Logger::new().info(flow.message());
+    let mut polls = flow.stream();
+    let mut failure = None;
+    let authorization = loop {
+        match polls.next().await {
+            Some(Ok(authorization)) => break authorization,
+            // authorization_pending until the user signed in, polling stops
+            // after any other error
+            Some(Err(e)) => failure = Some(e),
+            None => {
+                return Err(failure
+                    .map(|e| format!("device code sign-in failed: {}", e))
+                    .unwrap_or_else(|| String::from("device code sign-in timed out"))
+                    .into())
+            }
+        }
+    };
+    let arm_token = TokenResponse::new(
+        authorization.access_token().clone(),
+        OffsetDateTime::now_utc() + Duration::from_secs(authorization.expires_in),
+    );
+    let credential = DeviceCodeCredential {
+        tenant_id,
+        client_id: client_id.to_string(),
+        tokens: Mutex::new(DeviceCodeTokens {
+            access: HashMap::from([(audience(&cloud.resource()), arm_token)]),
+            refresh: authorization.refresh_token().cloned(),
+        }),
+    };
+    Ok((Arc::new(credential), options.tenant_id.clone()))
+}
+
+/// Tokens of a device code sign-in, one per resource, all renewed with its
+/// refresh token
+struct DeviceCodeCredential {
+    tenant_id: String,
+    client_id: String,
+    tokens: Mutex<DeviceCodeTokens>,
+}
+
+struct DeviceCodeTokens {
+    access: HashMap<String, TokenResponse>,
+    refresh: Option<AccessToken>,
+}
+
+#[derive(serde::Deserialize)]
+struct RefreshedToken {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: u64,
+}
+
+/// `https://management.azure.com/` and `https://management.azure.com/.default`
+/// ask for the same token
+fn audience(resource: &str) -> String {
+    resource
+        .trim_end_matches("/.default")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn credential_error(message: String) -> azure_core::Error {
+    azure_core::Error::message(ErrorKind::Credential, message)
+}
+
+#[async_trait]
+impl TokenCredential for DeviceCodeCredential {
+    async fn get_token(&self, resource: &str) -> azure_core::Result<TokenResponse> {
+        let audience = audience(resource);
+        let mut tokens = self.tokens.lock().await;
+        // Renewed early so requests in flight don't fail
+        if let Some(token) = tokens.access.get(&audience) {
+            if token.expires_on > OffsetDateTime::now_utc() + Duration::from_secs(5 * 60) {
+                return Ok(token.clone());
+            }
+        }
+        let refresh = tokens.refresh.clone().ok_or_else(|| {
+            credential_error(String::from(
+                "the device code sign-in expired, no refresh token was issued",
+            ))
+        })?;
+        // The refresh token is exchanged for a token of any resource the
+        // user consented to, e.g. Databricks workspaces next to ARM
+        let url = format!(
+            "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+            self.tenant_id
+        );
+        let scope = format!("{}/.default offline_access", audience);
+        let request = http::client().post(url).form(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", self.client_id.as_str()),
+            ("refresh_token", refresh.secret()),
+            ("scope", scope.as_str()),
+        ]);
+        let refreshed: RefreshedToken = async {
+            let response = http::send(request).await?.error_for_status()?;
+            response.json().await
+        }
+        .await
+        .map_err(|e| {
+            credential_error(format!(
+                "renewing the device code sign-in for {} failed: {}",
+                audience, e
+            ))
+        })?;
+        let token = TokenResponse::new(
+            AccessToken::new(refreshed.access_token),
+            OffsetDateTime::now_utc() + Duration::from_secs(refreshed.expires_in),
+        );
+        if let Some(refresh) = refreshed.refresh_token {
+            tokens.refresh = Some(AccessToken::new(refresh));
+        }
+        tokens.access.insert(audience, token.clone());
+        Ok(token)
+    }
+}
+
+/// Tenant IDs listed in `--tenants-file`, one per line. Blank lines and
+/// `#` comments are ignored.
+pub fn read_tenants(path: &Path) -> std::io::Result<Vec<String>> {
+    Ok(parse_tenants(&std::fs::read_to_string(path)?))
+}
+
+fn parse_tenants(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Azure CLI tokens for a tenant other than the one it defaults to, for
+/// users logged in to several. azure_identity's credential can't pass
+/// `--tenant`.
+struct AzureCliTenantCredential {
+    tenant: String,
+}
+
+#[derive(serde::Deserialize)]
+struct CliToken {
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    // Unix timestamp, Azure CLI 2.54 and later
+    expires_on: Option<i64>,
+    // Local time, e.g. 2023-10-31 16:43:15.000000
+    #[serde(rename = "expiresOn", default)]
+    expires_on_local: String,
+}
+
+impl CliToken {
+    fn expires_on(&self) -> Option<OffsetDateTime> {
+        let timestamp = match self.expires_on {
+            Some(timestamp) => timestamp,
+            None => {
+                let local =
+                    NaiveDateTime::parse_from_str(&self.expires_on_local, "%Y-%m-%d %H:%M:%S%.f")
+                        .ok()?;
+                Local.from_local_datetime(&local).earliest()?.timestamp()
+            }
+        };
+        OffsetDateTime::from_unix_timestamp(timestamp).ok()
+    }
+}
+
+#[async_trait]
+impl TokenCredential for AzureCliTenantCredential {
+    async fn get_token(&self, resource: &str) -> azure_core::Result<TokenResponse> {
+        // az is a cmd script on Windows
+        let mut command = if cfg!(target_os = "windows") {
+            let mut command = tokio::process::Command::new("cmd");
+            command.args(["/C", "az"]);
+            command
+        } else {
+            tokio::process::Command::new("az")
+        };
+        command.args(["account", "get-access-token", "--output", "json"]);
+        command.args(["--resource", resource, "--tenant", &self.tenant]);
+        let output = command.output().await.map_err(|e| {
+            azure_core::Error::message(ErrorKind::Credential, format!("running az failed: {}", e))
+        })?;
+        if !output.status.success() {
+            return Err(azure_core::Error::message(
+                ErrorKind::Credential,
+                format!(
+                    "'az account get-access-token --tenant {}' failed: {}",
+                    self.tenant,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        let token: CliToken = serde_json::from_slice(&output.stdout)?;
+        let expires_on = token.expires_on().ok_or_else(|| {
+            azure_core::Error::message(ErrorKind::Credential, "unreadable token expiry from az")
+        })?;
+        Ok(TokenResponse::new(
+            AccessToken::new(token.access_token),
+            expires_on,
+        ))
+    }
+}
+
+/// `value` or else the environment variable, `auth` names the `--auth`
+/// method needing it
+fn setting(value: &Option<String>, env: &str, flag: &str, auth: &str) -> Result<String, AuthError> {
+    value
+        .clone()
+        .or_else(|| std::env::var(env).ok())
+        .ok_or_else(|| format!("{} or {} is required with --auth {}", flag, env, auth).into())
+}
+
+/// DER encoded PKCS#12 as the credential expects it, PEM files are
+/// converted protecting the bundle with the same password
+fn load_pkcs12(path: &Path, password: &str) -> Result<Vec<u8>, AuthError> {
+    let contents = std::fs::read(path)?;
+    if !contents.windows(10).any(|w| w == b"-----BEGIN") {
+        return Ok(contents);
+    }
+    let key = if password.is_empty() {
+        PKey::private_key_from_pem(&contents)?
+    } else {
+        PKey::private_key_from_pem_passphrase(&contents, password.as_bytes())?
+    };
+    let mut certificates = X509::stack_from_pem(&contents)?.into_iter();
+    let certificate = certificates
+        .next()
+        .ok_or_else(|| format!("no certificate found in {}", path.display()))?;
+    let mut chain = Stack::new()?;
+    for ca in certificates {
+        chain.push(ca)?;
+    }
+    let pkcs12 = Pkcs12::builder()
+        .name("azindex")
+        .pkey(&key)
+        .cert(&certificate)
+        .ca(chain)
+        .build2(password)?;
+    Ok(pkcs12.to_der()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{audience, parse_tenants, setting, CliToken};
+
+    #[test]
+    fn test_parse_tenants() {
+        let text = "# Contoso\n72f988bf-86f1-41af-91ab-2d7cd011db47\n\n  fabrikam.onmicrosoft.com  # guest\n";
+        assert_eq!(
+            parse_tenants(text),
+            vec![
+                "72f988bf-86f1-41af-91ab-2d7cd011db47",
+                "fabrikam.onmicrosoft.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cli_token_expiry() {
+        let token: CliToken = serde_json::from_str(
+            r#"{"accessToken": "x", "expiresOn": "2023-10-31 16:43:15.000000", "expires_on": 1698767000}"#,
+        )
+        .unwrap();
+        assert_eq!(token.expires_on().unwrap().unix_timestamp(), 1698767000);
+        let old: CliToken = serde_json::from_str(
+            r#"{"accessToken": "x", "expiresOn": "2023-10-31 16:43:15.000000"}"#,
+        )
+        .unwrap();
+        assert!(old.expires_on().is_some());
+        let broken: CliToken = serde_json::from_str(r#"{"accessToken": "x"}"#).unwrap();
+        assert!(broken.expires_on().is_none());
+    }
+
+    #[test]
+    fn test_audience() {
+        assert_eq!(
+            audience("https://management.azure.com/"),
+            audience("https://management.azure.com/.default")
+        );
+        assert_eq!(
+            audience("2ff814a6-3304-4ab8-85cb-cd0e6f879c1d"),
+            "2ff814a6-3304-4ab8-85cb-cd0e6f879c1d"
+        );
+    }
+
+    #[test]
+    fn test_setting() {
+        let given = Some(String::from("00000000-0000-0000-0000-000000000001"));
+        assert_eq!(
+            setting(
+                &given,
+                "AZINDEX_TEST_UNSET",
+                "--client-id",
+                "workload-identity"
+            )
+            .unwrap(),
+            "00000000-0000-0000-0000-000000000001"
+        );
+        let missing = setting(
+            &None,
+            "AZINDEX_TEST_UNSET",
+            "--client-id",
+            "workload-identity",
+        );
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            "--client-id or AZINDEX_TEST_UNSET is required with --auth workload-identity"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use crate::VMResult;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Raw inventory written by `azindex scan --cache`, reported later with
+/// `azindex report --from-cache` without scanning again
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cache {
+    // The scan queried the guest OS of every VM
+    pub deep: bool,
+    // Ctrl+C stopped the scan, resources are missing
+    pub interrupted: bool,
+    // Subscription ID and reason, see `subscriptions::Skipped`
+    pub skipped: Vec<(String, String)>,
+    pub results: Vec<VMResult>,
+}
+
+impl Cache {
+    pub fn load(path: &Path) -> Result<Cache, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
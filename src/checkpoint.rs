@@ -0,0 +1,91 @@
+use crate::logging::warn;
+use crate::VMResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A line of the state file, appended as the scan goes so an interrupted run
+/// loses at most the line being written
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Entry {
+    // Every resource of the subscription has been reported
+    Subscription(String),
+    Result(Box<VMResult>),
+}
+
+/// What an earlier, interrupted run got through
+#[derive(Default)]
+pub struct Resumed {
+    pub subscriptions: HashSet<String>,
+    pub results: Vec<VMResult>,
+}
+
+/// Reads the state file of an earlier run. A torn last line is ignored, the
+/// resource it held is simply scanned again.
+pub fn load(path: &Path) -> std::io::Result<Resumed> {
+    let mut resumed = Resumed::default();
+    for line in BufReader::new(File::open(path)?).lines() {
+        match serde_json::from_str(&line?) {
+            Ok(Entry::Subscription(id)) => {
+                resumed.subscriptions.insert(id);
+            }
+            Ok(Entry::Result(result)) => resumed.results.push(*result),
+            Err(e) => warn!("Skipping unreadable line in {}: {}", path.display(), e),
+        }
+    }
+    Ok(resumed)
+}
+
+/// Records progress to `<out>.state.jsonl` next to the report, removed once
+/// the report is written
+pub struct Checkpoint {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Checkpoint {
+    pub fn path(out: &Path) -> PathBuf {
+        out.with_extension("state.jsonl")
+    }
+
+    /// Starts a new state file, or continues the one being resumed from
+    pub fn create(path: PathBuf, resume: bool) -> std::io::Result<Checkpoint> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(&path)?;
+        Ok(Checkpoint {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn result(&self, result: &VMResult) {
+        self.append(&Entry::Result(Box::new(result.clone())));
+    }
+
+    pub fn subscription_done(&self, subscription_id: &str) {
+        self.append(&Entry::Subscription(subscription_id.to_string()));
+    }
+
+    fn append(&self, entry: &Entry) {
+        let mut line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Recording scan progress failed: {}", e);
+                return;
+            }
+        };
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            warn!("Writing {} failed: {}", self.path.display(), e);
+        }
+    }
+}
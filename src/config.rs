@@ -0,0 +1,188 @@
+use crate::eol_detection::eol::EOLEntity;
+use crate::eol_detection::mapping::ImageMapping;
+use crate::logging::warn;
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer};
+use std::path::Path;
+
+/// Optional settings read from the file passed with `--config`
+///
+/// ```toml
+/// gallery_tag = "baseOs"
+///
+/// [[mappings]]
+/// publisher = "contoso"
+/// offer = "hardened-ubuntu-*"
+/// product = "ubuntu"
+/// version_from = "sku"
+/// version_format = "major_minor"
+///
+/// [[overrides]]
+/// product = "redhat"
+/// cycle = "7"
+/// eol = 2028-06-30
+/// source = "Vendor contract 2023-114"
+/// ```
+#[derive(Deserialize, Debug, Default)]
+pub struct Config {
+    /// Evaluated before the built-in detectors so they can reclassify images
+    #[serde(default)]
+    pub mappings: Vec<ImageMapping>,
+    #[serde(default)]
+    pub overrides: Vec<CycleOverride>,
+    /// Tag on Compute Gallery image definitions holding the marketplace image
+    /// (`publisher:offer:sku`) they are built from, `baseOs` by default
+    pub gallery_tag: Option<String>,
+}
+
+/// Replaces the dates of a single fetched cycle, the source ends up in the
+/// report next to every verdict based on it.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CycleOverride {
+    pub product: String,
+    pub cycle: String,
+    #[serde(default, deserialize_with = "config_date")]
+    pub eol: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "config_date")]
+    pub support: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "config_date")]
+    pub extended_support: Option<NaiveDate>,
+    pub source: String,
+}
+
+/// Accepts both TOML dates and quoted `YYYY-MM-DD` strings
+fn config_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawDate {
+        Toml(toml::value::Datetime),
+        Text(String),
+    }
+    let raw = match Option::<RawDate>::deserialize(deserializer)? {
+        Some(RawDate::Toml(date)) => date.to_string(),
+        Some(RawDate::Text(text)) => text,
+        None => return Ok(None),
+    };
+    NaiveDate::parse_from_str(&raw, "%Y-%m-%d")
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+impl Config {
+    pub fn gallery_tag(&self) -> &str {
+        self.gallery_tag.as_deref().unwrap_or("baseOs")
+    }
+
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&contents)?;
+        Ok(config)
+    }
+
+    /// Patches the cycles of a product as it is fetched, cycles
+    /// endoflife.date doesn't list are added when the override carries an
+    /// EOL date.
+    pub fn apply_product_overrides(&self, product: &str, cycles: &mut Vec<EOLEntity>) {
+        for item in self.overrides.iter().filter(|i| i.product == product) {
+            apply_override(item, cycles);
+        }
+    }
+}
+
+fn apply_override(item: &CycleOverride, cycles: &mut Vec<EOLEntity>) {
+    let entity = match cycles.iter_mut().find(|c| c.cycle == item.cycle) {
+        Some(entity) => entity,
+        None => match item.eol {
+            Some(eol) => {
+                cycles.push(EOLEntity {
+                    cycle: item.cycle.clone(),
+                    lts: false,
+                    release_date: NaiveDate::default(),
+                    latest: String::new(),
+                    support: None,
+                    eol,
+                    latest_release_date: None,
+                    extended_support: None,
+                    discontinued: None,
+                    override_source: None,
+                });
+                cycles.last_mut().unwrap()
+            }
+            None => {
+                warn!(
+                    "Override for {} {} has no EOL date and matches no cycle, ignoring",
+                    item.product, item.cycle
+                );
+                return;
+            }
+        },
+    };
+    if let Some(eol) = item.eol {
+        entity.eol = eol;
+    }
+    if item.support.is_some() {
+        entity.support = item.support;
+    }
+    if item.extended_support.is_some() {
+        entity.extended_support = item.extended_support;
+    }
+    entity.override_source = Some(item.source.clone());
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+    use crate::eol_detection::eol::EolData;
+
+    #[test]
+    fn test_mappings() {
+        let config: Config = toml::from_str(
+            r#"
+            [[mappings]]
+            publisher = "contoso"
+            offer = "hardened-*"
+            product = "ubuntu"
+            version_format = "major_minor"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.mappings.len(), 1);
+        assert_eq!(config.mappings[0].product, "ubuntu");
+        assert!(config.mappings[0].sku.is_none());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let config: Config = toml::from_str(
+            r#"
+            [[overrides]]
+            product = "redhat"
+            cycle = "7"
+            eol = 2028-06-30
+            source = "Vendor contract"
+
+            [[overrides]]
+            product = "redhat"
+            cycle = "6"
+            extended_support = "2029-06-30"
+            source = "Vendor contract"
+            "#,
+        )
+        .unwrap();
+        let mut data: EolData = serde_json::from_str(
+            r#"{"redhat": [{"cycle": "7", "releaseDate": "2014-06-10", "eol": "2024-06-30"}]}"#,
+        )
+        .unwrap();
+        let cycles = data.get_mut("redhat").unwrap();
+        config.apply_product_overrides("redhat", cycles);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].eol.to_string(), "2028-06-30");
+        assert_eq!(
+            cycles[0].override_source.as_deref(),
+            Some("Vendor contract")
+        );
+    }
+}
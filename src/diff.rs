@@ -0,0 +1,102 @@
+use crate::logging::Logger;
+use crate::VMResult;
+use std::collections::BTreeMap;
+
+/// What changed between two inventories, matched by resource ID
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    // Resource ID with the image before and after
+    pub redeployed: Vec<(String, String, String)>,
+}
+
+/// The image a resource runs, the gallery image version when there is one
+fn image(vm: &VMResult) -> String {
+    if !vm.gallery_image.is_empty() {
+        return vm.gallery_image.clone();
+    }
+    let version = match vm.exact_version.is_empty() {
+        true => &vm.version,
+        false => &vm.exact_version,
+    };
+    format!("{}:{}:{}:{}", vm.publisher, vm.offer, vm.sku, version)
+}
+
+fn by_id(results: &[VMResult]) -> BTreeMap<String, &VMResult> {
+    results
+        .iter()
+        .map(|vm| (vm.id.trim_end_matches('/').to_lowercase(), vm))
+        .collect()
+}
+
+pub fn diff(old: &[VMResult], new: &[VMResult]) -> Diff {
+    let (old, new) = (by_id(old), by_id(new));
+    let mut diff = Diff::default();
+    for (key, vm) in &new {
+        match old.get(key) {
+            None => diff.added.push(vm.id.clone()),
+            Some(before) if image(before) != image(vm) => {
+                diff.redeployed
+                    .push((vm.id.clone(), image(before), image(vm)))
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, vm) in &old {
+        if !new.contains_key(key) {
+            diff.removed.push(vm.id.clone());
+        }
+    }
+    diff
+}
+
+impl Diff {
+    pub fn print(&self) {
+        let mut log = Logger::new();
+        log.info(format!(
+            "{} added, {} removed, {} on another image",
+            self.added.len(),
+            self.removed.len(),
+            self.redeployed.len()
+        ));
+        for id in &self.added {
+            log.indent(1).log(format!("+ {}", id));
+        }
+        for id in &self.removed {
+            log.indent(1).log(format!("- {}", id));
+        }
+        for (id, before, after) in &self.redeployed {
+            log.indent(1)
+                .log(format!("~ {}: {} -> {}", id, before, after));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::diff;
+    use crate::VMResult;
+
+    fn vm(id: &str, sku: &str) -> VMResult {
+        VMResult {
+            id: id.to_string(),
+            publisher: String::from("canonical"),
+            offer: String::from("ubuntu-24_04-lts"),
+            sku: sku.to_string(),
+            version: String::from("latest"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = vec![vm("/vms/a", "server"), vm("/vms/b", "server")];
+        let new = vec![vm("/VMs/A", "server-gen1"), vm("/vms/c", "server")];
+        let diff = diff(&old, &new);
+        assert_eq!(diff.added, vec!["/vms/c"]);
+        assert_eq!(diff.removed, vec!["/vms/b"]);
+        assert_eq!(diff.redeployed.len(), 1);
+        assert_eq!(diff.redeployed[0].0, "/VMs/A");
+    }
+}
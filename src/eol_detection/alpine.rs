@@ -0,0 +1,53 @@
+use crate::eol_detection::detector::OsDetector;
+use crate::eol_detection::sku::normalize_sku;
+use crate::VMResult;
+
+pub struct Alpine;
+
+impl OsDetector for Alpine {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.offer.to_lowercase().contains("alpine")
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("alpine")
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // 3-18, 3_19, alpine-3-18-gen2
+    let parts: Vec<&str> = az_version
+        .split(['-', '_', '.'])
+        .skip_while(|p| !p.chars().all(|c| c.is_ascii_digit()) || p.is_empty())
+        .take(2)
+        .collect();
+    if parts.len() < 2 || !parts[1].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}.{}", parts[0], parts[1]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_separators() {
+        assert_eq!(parse_azure_version("3-18"), Some(String::from("3.18")));
+        assert_eq!(parse_azure_version("3_19"), Some(String::from("3.19")));
+    }
+
+    #[test]
+    fn test_prefixed() {
+        assert_eq!(
+            parse_azure_version("alpine-3-18-gen2"),
+            Some(String::from("3.18"))
+        );
+        assert_eq!(parse_azure_version("latest"), None);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::eol_detection::detector::OsDetector;
+use crate::eol_detection::sku::normalize_sku;
+use crate::VMResult;
+
+pub struct AzureLinux;
+
+impl OsDetector for AzureLinux {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher.to_lowercase() == "microsoftcblmariner"
+            || vm.offer.to_lowercase().contains("cbl-mariner")
+            || vm.offer.to_lowercase().contains("azure-linux")
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("azure-linux")
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // cbl-mariner-1, cbl-mariner-2-gen2, cbl-mariner-2-arm64, azure-linux-3-gen2
+    // endoflife.date lists the cycles as 1.0, 2.0, 3.0
+    let major = az_version
+        .split(['-', '_'])
+        .find(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))?;
+    Some(format!("{}.0", major))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_mariner() {
+        assert_eq!(
+            parse_azure_version("cbl-mariner-1"),
+            Some(String::from("1.0"))
+        );
+        assert_eq!(
+            parse_azure_version("cbl-mariner-2-gen2"),
+            Some(String::from("2.0"))
+        );
+        assert_eq!(
+            parse_azure_version("cbl-mariner-2-arm64"),
+            Some(String::from("2.0"))
+        );
+    }
+
+    #[test]
+    fn test_azure_linux() {
+        assert_eq!(
+            parse_azure_version("azure-linux-3-gen2"),
+            Some(String::from("3.0"))
+        );
+        assert_eq!(parse_azure_version("azure-linux"), None);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::eol_detection::detector::OsDetector;
+use crate::eol_detection::sku::normalize_sku;
+use crate::VMResult;
+
+pub struct CentOSStream;
+
+impl OsDetector for CentOSStream {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.offer.to_lowercase().contains("centos")
+            && (vm.offer.to_lowercase().contains("stream")
+                || vm.sku.to_lowercase().contains("stream"))
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("centos-stream")
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // 9, 8-gen2, centos-stream-9-gen2, stream-9-arm64
+    let major = az_version
+        .split(['-', '_', '.'])
+        .find(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))?;
+    Some(major.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_stream() {
+        assert_eq!(parse_azure_version("9"), Some(String::from("9")));
+        assert_eq!(parse_azure_version("8-gen2"), Some(String::from("8")));
+        assert_eq!(
+            parse_azure_version("centos-stream-9-gen2"),
+            Some(String::from("9"))
+        );
+        assert_eq!(parse_azure_version("stream"), None);
+    }
+}
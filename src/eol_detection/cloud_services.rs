@@ -0,0 +1,28 @@
+use crate::eol_detection::detector::{eol_status, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::inventory::cloud_services;
+use crate::VMResult;
+use chrono::NaiveDate;
+
+// Cloud Services (classic) were retired in favour of extended support
+const RETIREMENT: &str = "2024-08-31";
+
+pub struct ClassicCloudService;
+
+impl OsDetector for ClassicCloudService {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher == cloud_services::CLASSIC_PUBLISHER
+    }
+
+    fn product(&self) -> Option<&str> {
+        None
+    }
+
+    fn parse_azure_version(&self, _vm: &VMResult) -> Option<String> {
+        Some(String::from("classic"))
+    }
+
+    fn is_outdated(&self, _vm: &VMResult, _eol_data: &EolData) -> String {
+        eol_status(NaiveDate::parse_from_str(RETIREMENT, "%Y-%m-%d").unwrap())
+    }
+}
@@ -0,0 +1,136 @@
+use crate::eol_detection::detector::{eol_status, find_cycle, Detection, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::VMResult;
+use chrono::NaiveDate;
+
+/// Engine version of a managed database server, the verdict also accounts for
+/// Azure retiring the service itself.
+pub struct ManagedDatabase {
+    pub publisher: &'static str,
+    pub offer: &'static str,
+    pub product: &'static str,
+    pub name: &'static str,
+    /// Date the whole service is retired by Azure
+    pub retirement: Option<&'static str>,
+}
+
+pub fn databases() -> Vec<ManagedDatabase> {
+    vec![
+        ManagedDatabase {
+            publisher: "Microsoft.DBforPostgreSQL",
+            offer: "postgresql-flexible",
+            product: "postgresql",
+            name: "PostgreSQL",
+            retirement: None,
+        },
+        ManagedDatabase {
+            publisher: "Microsoft.DBforPostgreSQL",
+            offer: "postgresql-single",
+            product: "postgresql",
+            name: "PostgreSQL",
+            retirement: Some("2025-03-28"),
+        },
+        ManagedDatabase {
+            publisher: "Microsoft.DBforMySQL",
+            offer: "mysql-flexible",
+            product: "mysql",
+            name: "MySQL",
+            retirement: None,
+        },
+        ManagedDatabase {
+            publisher: "Microsoft.DBforMySQL",
+            offer: "mysql-single",
+            product: "mysql",
+            name: "MySQL",
+            retirement: Some("2024-09-16"),
+        },
+        ManagedDatabase {
+            publisher: "Microsoft.DBforMariaDB",
+            offer: "mariadb",
+            product: "mariadb",
+            name: "MariaDB",
+            retirement: Some("2025-09-19"),
+        },
+    ]
+}
+
+impl ManagedDatabase {
+    fn retirement(&self) -> Option<NaiveDate> {
+        self.retirement
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap())
+    }
+}
+
+impl OsDetector for ManagedDatabase {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher.eq_ignore_ascii_case(self.publisher) && vm.offer == self.offer
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some(self.product)
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(&vm.sku)
+    }
+
+    /// The verdict follows whichever comes first, the engine's EOL or the
+    /// service retirement, and the component names it.
+    fn detect(&self, vm: &VMResult, eol_data: &EolData) -> Detection {
+        let version = self.parse_azure_version(vm).unwrap_or_default();
+        let item = find_cycle(self.product(), &version, eol_data);
+        let engine = item.map(|item| (item.eol, format!("{} {}", self.name, version)));
+        let service = self
+            .retirement()
+            .map(|date| (date, format!("{} retirement", vm.resource_type)));
+        let (eol, component) = match (engine, service) {
+            (Some(engine), Some(service)) if service.0 < engine.0 => service,
+            (Some(engine), _) => engine,
+            (None, Some(service)) => service,
+            (None, None) => {
+                return Detection {
+                    version,
+                    status: "--".to_string(),
+                    classified: true,
+                    ..Default::default()
+                }
+            }
+        };
+        Detection {
+            version,
+            status: eol_status(eol),
+            component,
+            support_tier: String::new(),
+            source: item
+                .and_then(|item| item.override_source.clone())
+                .unwrap_or_default(),
+            classified: true,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // 16, 9.6, 11, 8.0.21, 5.7, 10.3
+    // endoflife.date lists PostgreSQL 10+ by major, everything else by minor
+    let parts: Vec<&str> = az_version.split('.').collect();
+    if parts.iter().any(|p| p.parse::<u32>().is_err()) {
+        return None;
+    }
+    Some(parts[..parts.len().min(2)].join("."))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(parse_azure_version("16"), Some(String::from("16")));
+        assert_eq!(parse_azure_version("9.6"), Some(String::from("9.6")));
+        assert_eq!(parse_azure_version("8.0.21"), Some(String::from("8.0")));
+        assert_eq!(parse_azure_version("10.3"), Some(String::from("10.3")));
+        assert_eq!(parse_azure_version(""), None);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::eol_detection::eol::{EOLEntity, EolData};
+use crate::logging::{debug, trace, Logger};
+use crate::VMResult;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+/// Outcome of running a detector against a single VM
+#[derive(Debug, Clone, Default)]
+pub struct Detection {
+    pub version: String,
+    pub status: String,
+    pub component: String,
+    pub support_tier: String,
+    /// Lifecycle phase of the cycle, see `support_phase`
+    pub phase: String,
+    /// Where overridden EOL dates came from, empty for endoflife.date data
+    pub source: String,
+    /// Whether any detector matched the VM
+    pub classified: bool,
+    /// SQL Server registered on the VM and its verdict, next to the OS one
+    pub sql_version: String,
+    pub sql_status: String,
+    /// endoflife.date product of the detector that matched, empty for
+    /// detectors carrying their own lifecycle data
+    pub product: String,
+    /// Date of the cached or bundled data the verdict is based on when
+    /// endoflife.date couldn't be reached
+    pub data_as_of: Option<NaiveDate>,
+}
+
+/// A verdict without the date of an ending cycle, what `--only` selects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    Eol,
+    Ending,
+    Supported,
+    // No detector matched or no cycle was found
+    Unknown,
+    Custom,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Eol => "EOL",
+            Verdict::Ending => "Ending",
+            Verdict::Supported => "Supported",
+            Verdict::Unknown => "Unknown",
+            Verdict::Custom => "Custom image",
+        }
+    }
+}
+
+impl FromStr for Verdict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "eol" => Ok(Verdict::Eol),
+            "ending" => Ok(Verdict::Ending),
+            "supported" => Ok(Verdict::Supported),
+            "unknown" => Ok(Verdict::Unknown),
+            "custom" => Ok(Verdict::Custom),
+            _ => Err(format!(
+                "{} isn't eol, ending, supported, unknown or custom",
+                s
+            )),
+        }
+    }
+}
+
+impl Detection {
+    pub fn verdict(&self) -> Verdict {
+        match self.status.as_str() {
+            "EOL" => Verdict::Eol,
+            "Supported" => Verdict::Supported,
+            status if status.starts_with("Ending") => Verdict::Ending,
+            status if status.starts_with("Custom image") => Verdict::Custom,
+            _ => Verdict::Unknown,
+        }
+    }
+}
+
+/// A distro or OS family azindex knows how to classify.
+///
+/// Only `matches`, `product` and `parse_azure_version` are required, the
+/// default `is_outdated` compares the parsed version against the cycles of
+/// `product`.
+pub trait OsDetector: Send + Sync {
+    fn matches(&self, vm: &VMResult) -> bool;
+
+    /// endoflife.date product the verdict is based on, `None` for detectors
+    /// that carry their own lifecycle data.
+    fn product(&self) -> Option<&str>;
+
+    /// Every endoflife.date product this detector needs fetched
+    fn products(&self) -> Vec<&str> {
+        self.product().into_iter().collect()
+    }
+
+    /// The products needed to classify `vm`, fetched as resources turn up
+    fn products_for(&self, _vm: &VMResult) -> Vec<&str> {
+        self.products()
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String>;
+
+    fn is_outdated(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        let version = match image_version(vm, self.parse_azure_version(vm)) {
+            Some(v) => v,
+            None => return "--".to_string(),
+        };
+        match find_cycle(self.product(), &version, eol_data) {
+            Some(item) => eol_status(item.eol),
+            None => "--".to_string(),
+        }
+    }
+
+    fn support_tier(&self, _vm: &VMResult, _eol_data: &EolData) -> String {
+        String::new()
+    }
+
+    /// Lifecycle phase of the cycle the verdict is based on
+    fn phase(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        self.parse_azure_version(vm)
+            .and_then(|version| find_cycle(self.product(), &version, eol_data))
+            .map(|item| support_phase(item, chrono::Utc::now().date_naive()))
+            .unwrap_or_default()
+    }
+
+    /// Override source of the cycle the verdict is based on
+    fn eol_source(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        self.parse_azure_version(vm)
+            .and_then(|version| find_cycle(self.product(), &version, eol_data))
+            .and_then(|item| item.override_source.clone())
+            .unwrap_or_default()
+    }
+
+    fn detect(&self, vm: &VMResult, eol_data: &EolData) -> Detection {
+        Detection {
+            version: self.parse_azure_version(vm).unwrap_or_default(),
+            status: self.is_outdated(vm, eol_data),
+            component: String::new(),
+            support_tier: self.support_tier(vm, eol_data),
+            phase: self.phase(vm, eol_data),
+            source: self.eol_source(vm, eol_data),
+            classified: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Passes the version a detector parsed from the image of `vm` through,
+/// logging the VM when there was none. Its verdict is then "--".
+pub fn image_version<T>(vm: &VMResult, version: Option<T>) -> Option<T> {
+    if version.is_none() {
+        debug!(
+            "No version in the image of {} ({}:{})",
+            vm.id, vm.offer, vm.sku
+        );
+        trace!("{:#?}", vm);
+    }
+    version
+}
+
+pub fn cycles<'a>(product: Option<&str>, eol_data: &'a EolData) -> &'a [EOLEntity] {
+    product
+        .and_then(|p| eol_data.get(p))
+        .map(|list| list.as_slice())
+        .unwrap_or_default()
+}
+
+/// The lifecycle phase of a cycle on `today`: Active support, Security-only
+/// once active support ended, Extended while paid extended support lasts,
+/// then EOL. Discontinued cycles can no longer be deployed anew.
+pub fn support_phase(item: &EOLEntity, today: NaiveDate) -> String {
+    let phase = if today >= item.eol {
+        match item.extended_support {
+            Some(extended) if today < extended => "Extended",
+            _ => "EOL",
+        }
+    } else if item.support.is_some_and(|support| today >= support) {
+        "Security-only"
+    } else {
+        "Active support"
+    };
+    match item.discontinued {
+        Some(discontinued) if today >= discontinued && phase != "EOL" => {
+            format!("{}, discontinued", phase)
+        }
+        _ => phase.to_string(),
+    }
+}
+
+/// Looks up the cycle for `version`. Without an exact match the newest minor
+/// of a bare major (`8` -> `8.10`) or the major of a minor release (`8.6` ->
+/// `8`) is used, both are recorded as near-misses, see `report_mismatches`.
+pub fn find_cycle<'a>(
+    product: Option<&str>,
+    version: &str,
+    eol_data: &'a EolData,
+) -> Option<&'a EOLEntity> {
+    let list = cycles(product, eol_data);
+    if let Some(item) = list
+        .iter()
+        .find(|item| item.cycle.eq_ignore_ascii_case(version))
+    {
+        return Some(item);
+    }
+    let product = product?;
+    let minor_prefix = format!("{}.", version);
+    let major = version.split('.').next().unwrap_or(version);
+    let near = list
+        .iter()
+        .filter(|item| item.cycle.starts_with(&minor_prefix))
+        .max_by_key(|item| item.eol)
+        .or_else(|| {
+            list.iter()
+                .find(|item| major != version && item.cycle == major)
+        });
+    let mut mismatches = MISMATCHES.lock().unwrap();
+    let entry = mismatches.entry(product.to_string()).or_default();
+    match near {
+        Some(item) => entry.insert(format!("{} -> {}", version, item.cycle)),
+        None => entry.insert(version.to_string()),
+    };
+    near
+}
+
+// Versions per product that had no exact cycle, with the cycle used instead
+static MISMATCHES: Mutex<BTreeMap<String, BTreeSet<String>>> = Mutex::new(BTreeMap::new());
+
+/// Warns about every version that didn't match a cycle exactly
+pub fn report_mismatches() {
+    let mismatches = MISMATCHES.lock().unwrap();
+    if mismatches.is_empty() {
+        return;
+    }
+    let mut log = Logger::new();
+    log.warn("Versions without an exact EOL cycle:");
+    for (product, versions) in mismatches.iter() {
+        let versions: Vec<&str> = versions.iter().map(String::as_str).collect();
+        log.indent(1)
+            .log(format!("{}: {}", product, versions.join(", ")));
+    }
+}
+
+/// How far ahead an upcoming EOL date is reported as "Ending"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WarnWindow {
+    Days(u32),
+    Months(u32),
+}
+
+impl Default for WarnWindow {
+    fn default() -> Self {
+        WarnWindow::Months(12)
+    }
+}
+
+impl FromStr for WarnWindow {
+    type Err = String;
+
+    /// Accepts `90d`, `90days`, `6m`, `6months`, a bare number is taken as days
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_lowercase();
+        let digits: String = lower.chars().take_while(|c| c.is_ascii_digit()).collect();
+        let value: u32 = digits
+            .parse()
+            .map_err(|_| format!("invalid window '{}', expected e.g. 90d or 6m", s))?;
+        match lower[digits.len()..].trim() {
+            "" | "d" | "day" | "days" => Ok(WarnWindow::Days(value)),
+            "m" | "month" | "months" => Ok(WarnWindow::Months(value)),
+            _ => Err(format!("invalid window '{}', expected e.g. 90d or 6m", s)),
+        }
+    }
+}
+
+impl WarnWindow {
+    pub fn end(&self, from: NaiveDate) -> NaiveDate {
+        match self {
+            WarnWindow::Days(days) => from + chrono::Days::new(*days as u64),
+            WarnWindow::Months(months) => from + chrono::Months::new(*months),
+        }
+    }
+}
+
+static WARN_WITHIN: OnceLock<WarnWindow> = OnceLock::new();
+
+/// Sets the window used by `eol_status`, only the first call has an effect
+pub fn set_warn_within(window: WarnWindow) {
+    _ = WARN_WITHIN.set(window);
+}
+
+/// Turns an EOL date into the verdict shown in the reports, dates within the
+/// warn window (12 months unless `--warn-within` is given) are reported as
+/// ending.
+pub fn eol_status(eol: NaiveDate) -> String {
+    let now = chrono::Utc::now().date_naive();
+    let future_eol = WARN_WITHIN.get().copied().unwrap_or_default().end(now);
+    if eol < now {
+        "EOL".to_string()
+    } else if eol < future_eol {
+        format!("Ending {}", eol)
+    } else {
+        "Supported".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_cycle, support_phase, Detection, Verdict, WarnWindow};
+    use crate::eol_detection::eol::{EOLEntity, EolData};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_verdict() {
+        let verdict = |status: &str| {
+            Detection {
+                status: status.to_string(),
+                ..Default::default()
+            }
+            .verdict()
+        };
+        assert_eq!(verdict("Ending 2026-05-31"), Verdict::Ending);
+        assert_eq!(verdict("--"), Verdict::Unknown);
+        assert_eq!(
+            verdict("Custom image - needs manual review"),
+            Verdict::Custom
+        );
+        assert_eq!("EOL".parse::<Verdict>(), Ok(Verdict::Eol));
+    }
+
+    #[test]
+    fn test_support_phase() {
+        let cycles: Vec<EOLEntity> = serde_json::from_str(
+            r#"[{"cycle": "2019", "support": "2024-01-09", "eol": "2029-01-09", "extendedSupport": "2032-01-13", "discontinued": "2025-06-01"}]"#,
+        )
+        .unwrap();
+        let phase = |date: &str| support_phase(&cycles[0], date.parse::<NaiveDate>().unwrap());
+        assert_eq!(phase("2023-05-01"), "Active support");
+        assert_eq!(phase("2025-01-01"), "Security-only");
+        assert_eq!(phase("2026-01-01"), "Security-only, discontinued");
+        assert_eq!(phase("2030-01-01"), "Extended, discontinued");
+        assert_eq!(phase("2033-01-01"), "EOL");
+    }
+
+    #[test]
+    fn test_near_miss() {
+        let data: EolData = serde_json::from_str(
+            r#"{"redhat": [
+                {"cycle": "8.6", "eol": "2024-05-31"},
+                {"cycle": "8.8", "eol": "2025-05-31"},
+                {"cycle": "9", "eol": "2032-05-31"}
+            ]}"#,
+        )
+        .unwrap();
+        let find = |v| find_cycle(Some("redhat"), v, &data).map(|item| item.cycle.as_str());
+        assert_eq!(find("8.6"), Some("8.6"));
+        assert_eq!(find("8"), Some("8.8"));
+        assert_eq!(find("9.2"), Some("9"));
+        assert_eq!(find("7"), None);
+    }
+
+    #[test]
+    fn test_warn_window() {
+        assert_eq!("90d".parse(), Ok(WarnWindow::Days(90)));
+        assert_eq!("30".parse(), Ok(WarnWindow::Days(30)));
+        assert_eq!("6months".parse(), Ok(WarnWindow::Months(6)));
+        assert_eq!("6 M".parse(), Ok(WarnWindow::Months(6)));
+        assert!("soon".parse::<WarnWindow>().is_err());
+        assert!("6y".parse::<WarnWindow>().is_err());
+    }
+}
@@ -1,26 +1,525 @@
-use reqwest::{Client, Error};
-use serde::Deserialize;
-use chrono::NaiveDate;
+use crate::http;
+use crate::logging::{debug, warn};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use futures::future::join_all;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
 
+/// Fetched cycles keyed by endoflife.date product name
+pub type EolData = HashMap<String, Vec<EOLEntity>>;
+
+#[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 pub struct EOLEntity {
     pub cycle: String,
-    // pub lts: bool,
-    #[serde(rename(deserialize = "releaseDate"))]
+    // Either a boolean or the date the cycle became LTS
+    #[serde(default, deserialize_with = "lts_flag")]
+    pub lts: bool,
+    #[serde(rename(deserialize = "releaseDate"), default)]
     pub release_date: NaiveDate,
+    #[serde(default)]
     pub latest: String,
-    pub support: NaiveDate,
+    // Not every product publishes a separate active support date (e.g. alpine),
+    // a boolean means active support is ongoing (true) or over (false).
+    #[serde(default, deserialize_with = "support_date")]
+    pub support: Option<NaiveDate>,
+    // Products without a fixed date publish a boolean, `true` meaning the
+    // cycle already reached EOL. These are mapped to the earliest or latest
+    // representable date so comparisons keep working.
+    #[serde(deserialize_with = "eol_date")]
     pub eol: NaiveDate,
     #[serde(rename(deserialize = "latestReleaseDate"))]
     pub latest_release_date: Option<NaiveDate>,
+    // End of paid extended support (e.g. Ubuntu ESM), `true` means extended
+    // support without a published end date.
+    #[serde(
+        rename(deserialize = "extendedSupport"),
+        default,
+        deserialize_with = "optional_date"
+    )]
+    pub extended_support: Option<NaiveDate>,
+    // No longer offered for new deployments, `true` means it already is
+    // without a published date
+    #[serde(default, deserialize_with = "discontinued_date")]
+    pub discontinued: Option<NaiveDate>,
+    // Set when the cycle was patched from the config file
+    #[serde(skip)]
+    pub override_source: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DateOrBool {
+    Date(NaiveDate),
+    Bool(bool),
+    Other(serde_json::Value),
+}
+
+fn eol_date<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match DateOrBool::deserialize(deserializer)? {
+        DateOrBool::Date(date) => Ok(date),
+        DateOrBool::Bool(true) => Ok(NaiveDate::MIN),
+        DateOrBool::Bool(false) => Ok(NaiveDate::MAX),
+        DateOrBool::Other(v) => Err(serde::de::Error::custom(format!(
+            "expected a date or boolean for eol, got {}",
+            v
+        ))),
+    }
+}
+
+fn support_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<DateOrBool>::deserialize(deserializer)? {
+        Some(DateOrBool::Date(date)) => Some(date),
+        Some(DateOrBool::Bool(true)) => Some(NaiveDate::MAX),
+        Some(DateOrBool::Bool(false)) => Some(NaiveDate::MIN),
+        _ => None,
+    })
+}
+
+fn optional_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<DateOrBool>::deserialize(deserializer)? {
+        Some(DateOrBool::Date(date)) => Some(date),
+        Some(DateOrBool::Bool(true)) => Some(NaiveDate::MAX),
+        _ => None,
+    })
+}
+
+fn discontinued_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<DateOrBool>::deserialize(deserializer)? {
+        Some(DateOrBool::Date(date)) => Some(date),
+        Some(DateOrBool::Bool(true)) => Some(NaiveDate::MIN),
+        _ => None,
+    })
+}
+
+fn lts_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<DateOrBool>::deserialize(deserializer)? {
+        Some(DateOrBool::Bool(lts)) => lts,
+        Some(DateOrBool::Date(_)) => true,
+        _ => false,
+    })
+}
+
+/// How endoflife.date responses are kept between runs
+#[derive(Debug, Clone)]
+pub struct CacheOptions {
+    // None when no cache directory could be determined
+    pub dir: Option<PathBuf>,
+    // Cached responses younger than this are used without fetching
+    pub ttl: Duration,
+    // `--refresh-eol`, fetch everything again
+    pub refresh: bool,
+    // `--offline`, never contact endoflife.date
+    pub offline: bool,
+}
+
+/// endoflife.date responses bundled at build time from `eol-snapshot/`,
+/// see build.rs
+static SNAPSHOT: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/eol_snapshot.rs"));
+
+// When the snapshot was taken, from eol-snapshot/DATE
+static SNAPSHOT_DATE: Option<&str> = include!(concat!(env!("OUT_DIR"), "/eol_snapshot_date.rs"));
+
+/// Where the data of a product came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    Fetched,
+    Cache,
+    // Past the TTL, used because endoflife.date couldn't be reached or
+    // with --offline
+    ExpiredCache,
+    Snapshot,
+    Pinned,
+}
+
+impl Origin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Origin::Fetched => "endoflife.date",
+            Origin::Cache => "Cache",
+            Origin::ExpiredCache => "Expired cache",
+            Origin::Snapshot => "Bundled snapshot",
+            Origin::Pinned => "Pinned dataset",
+        }
+    }
+
+    /// Data that isn't as current as the TTL asks, rows based on it are dated
+    fn is_dated(&self) -> bool {
+        matches!(
+            self,
+            Origin::ExpiredCache | Origin::Snapshot | Origin::Pinned
+        )
+    }
+}
+
+/// Where a product's data came from and when it was fetched
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    pub origin: Origin,
+    pub fetched: DateTime<Local>,
+}
+
+static PROVENANCE: Mutex<BTreeMap<String, Provenance>> = Mutex::new(BTreeMap::new());
+
+fn record(product_name: &str, origin: Origin, fetched: DateTime<Local>) {
+    PROVENANCE
+        .lock()
+        .unwrap()
+        .insert(product_name.to_string(), Provenance { origin, fetched });
+}
+
+/// Every product used so far, for the summary of the report
+pub fn provenance() -> Vec<(String, Provenance)> {
+    PROVENANCE
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(product, provenance)| (product.clone(), provenance.clone()))
+        .collect()
+}
+
+/// Date of the data used for a product that was pinned or couldn't be
+/// refreshed, `None` when it is current
+pub fn data_as_of(product_name: &str) -> Option<NaiveDate> {
+    PROVENANCE
+        .lock()
+        .unwrap()
+        .get(product_name)
+        .filter(|p| p.origin.is_dated())
+        .map(|p| p.fetched.date_naive())
+}
+
+static CACHE: OnceLock<CacheOptions> = OnceLock::new();
+
+pub const DEFAULT_ENDPOINT: &str = "https://endoflife.date/api";
+
+// `--eol-endpoint`, an internal mirror of the endoflife.date API
+static ENDPOINT: OnceLock<String> = OnceLock::new();
+
+pub fn set_endpoint(endpoint: &str) {
+    _ = ENDPOINT.set(endpoint.trim_end_matches('/').to_string());
+}
+
+fn endpoint() -> &'static str {
+    ENDPOINT.get().map_or(DEFAULT_ENDPOINT, |e| e.as_str())
+}
+
+fn product_url(product_name: &str) -> String {
+    format!("{}/{}.json", endpoint(), product_name)
 }
 
-pub async fn fetch_eol(product_name: &str) -> Result<Vec<EOLEntity>, Error> {
-    let items = Client::new()
-        .get(format!("https://endoflife.date/api/{}.json", product_name))
-        .send()
-        .await?
-        .json::<Vec<EOLEntity>>()
-        .await?;
-    Ok(items)
+pub fn set_cache(options: CacheOptions) {
+    _ = CACHE.set(options);
+}
+
+/// `~/.cache/azindex/eol`, `%LOCALAPPDATA%\azindex\eol` on Windows
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(target_os = "windows") => PathBuf::from(std::env::var_os("LOCALAPPDATA")?),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("azindex").join("eol"))
+}
+
+fn is_fresh(path: &Path, ttl: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < ttl)
+}
+
+fn modified(path: &Path) -> std::io::Result<DateTime<Local>> {
+    Ok(DateTime::from(std::fs::metadata(path)?.modified()?))
+}
+
+fn read_cached<T: DeserializeOwned>(path: &Path) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+fn write_cached(path: &Path, body: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Written aside and renamed so a concurrent run never reads half a file
+    let partial = path.with_extension("json.partial");
+    std::fs::write(&partial, body)?;
+    std::fs::rename(&partial, path)
+}
+
+/// Cycles of a product, from the cache while it is younger than the TTL.
+/// When endoflife.date can't be reached an expired copy is used instead.
+pub async fn fetch_eol(product_name: &str) -> Result<Vec<EOLEntity>, Box<dyn Error>> {
+    let (cycles, provenance) = fetch_json(product_name).await?;
+    if let Some(provenance) = provenance {
+        record(product_name, provenance.origin, provenance.fetched);
+    }
+    Ok(cycles)
+}
+
+/// Names of every product endoflife.date tracks, cached like the products.
+/// The list isn't EOL data, it is left out of `provenance`.
+pub async fn all_products() -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(fetch_json("all").await?.0)
+}
+
+/// A response of the API and where it came from, `None` for a snapshot
+/// without a date
+type Loaded<T> = (T, Option<Provenance>);
+
+fn loaded<T>(items: T, origin: Origin, fetched: DateTime<Local>) -> Loaded<T> {
+    (items, Some(Provenance { origin, fetched }))
+}
+
+async fn fetch_json<T: DeserializeOwned>(product_name: &str) -> Result<Loaded<T>, Box<dyn Error>> {
+    let options = CACHE.get();
+    let cached = options
+        .and_then(|o| o.dir.as_ref())
+        .map(|dir| dir.join(format!("{}.json", product_name)));
+    if let (Some(path), Some(options)) = (&cached, options) {
+        if !options.refresh && is_fresh(path, options.ttl) {
+            if let (Ok(items), Ok(modified)) = (read_cached(path), modified(path)) {
+                debug!("Using {} cached in {}", product_name, path.display());
+                return Ok(loaded(items, Origin::Cache, modified));
+            }
+        }
+    }
+    if options.is_some_and(|o| o.offline) {
+        return offline_eol(product_name, cached.as_deref());
+    }
+    let body = match download(product_name).await {
+        Ok(body) => body,
+        Err(e) => {
+            let fallback = offline_eol(product_name, cached.as_deref());
+            if let Ok((_, provenance)) = &fallback {
+                let as_of = provenance
+                    .as_ref()
+                    .filter(|p| p.origin.is_dated())
+                    .map_or(String::new(), |p| {
+                        format!(" from {}", p.fetched.date_naive())
+                    });
+                warn!(
+                    "Fetching {} from endoflife.date failed, using older data{}: {}",
+                    product_name, as_of, e
+                );
+                return fallback;
+            }
+            return Err(e);
+        }
+    };
+    let items = serde_json::from_str(&body)?;
+    debug!(
+        "Fetched {} from {}",
+        product_name,
+        product_url(product_name)
+    );
+    if let Some(path) = &cached {
+        if let Err(e) = write_cached(path, &body) {
+            warn!("Caching {} failed: {}", path.display(), e);
+        }
+    }
+    Ok(loaded(items, Origin::Fetched, Local::now()))
+}
+
+/// The endoflife.date API response for a product
+async fn download(product_name: &str) -> Result<String, Box<dyn Error>> {
+    let request = http::client().get(product_url(product_name));
+    let response = http::send(request).await?.error_for_status()?;
+    Ok(response.text().await?)
+}
+
+/// A dataset written by `azindex eol update`, used with `--eol-data` so
+/// reports of the same dataset are reproducible
+#[derive(Serialize, Deserialize)]
+pub struct PinnedData {
+    pub fetched: DateTime<Local>,
+    pub endpoint: String,
+    // API responses as returned, keyed by product
+    pub products: BTreeMap<String, serde_json::Value>,
+}
+
+impl PinnedData {
+    /// Fetches every product, failing if any of them can't be fetched
+    pub async fn fetch(products: &[&str]) -> Result<PinnedData, Box<dyn Error>> {
+        let bodies = join_all(products.iter().map(|product| download(product))).await;
+        let mut pinned = PinnedData {
+            fetched: Local::now(),
+            endpoint: endpoint().to_string(),
+            products: BTreeMap::new(),
+        };
+        for (product, body) in products.iter().zip(bodies) {
+            let body = body.map_err(|e| format!("Fetching {} failed: {}", product, e))?;
+            let cycles: serde_json::Value = serde_json::from_str(&body)?;
+            // Caught now rather than when a report needs the product
+            Vec::<EOLEntity>::deserialize(&cycles)
+                .map_err(|e| format!("Unreadable cycles of {}: {}", product, e))?;
+            pinned.products.insert(product.to_string(), cycles);
+        }
+        Ok(pinned)
+    }
+
+    pub fn load(path: &Path) -> Result<PinnedData, Box<dyn Error>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The cycles of every product, each recorded as of the dataset's date
+    pub fn eol_data(&self) -> Result<EolData, Box<dyn Error>> {
+        let mut data = EolData::new();
+        for (product, cycles) in &self.products {
+            data.insert(product.clone(), Vec::<EOLEntity>::deserialize(cycles)?);
+            record(product, Origin::Pinned, self.fetched);
+        }
+        Ok(data)
+    }
+}
+
+/// A cached copy of any age, else the bundled snapshot. Data past the TTL
+/// comes back as an expired cache, see `data_as_of`.
+fn offline_eol<T: DeserializeOwned>(
+    product_name: &str,
+    cached: Option<&Path>,
+) -> Result<Loaded<T>, Box<dyn Error>> {
+    if let Some(path) = cached.filter(|path| path.exists()) {
+        let cycles = read_cached(path)?;
+        let ttl = CACHE.get().map_or(Duration::ZERO, |o| o.ttl);
+        let origin = match is_fresh(path, ttl) {
+            true => Origin::Cache,
+            false => Origin::ExpiredCache,
+        };
+        return Ok(loaded(cycles, origin, modified(path)?));
+    }
+    match SNAPSHOT
+        .iter()
+        .find(|(product, _)| *product == product_name)
+    {
+        Some((_, body)) => {
+            let cycles = serde_json::from_str(body)?;
+            let taken = SNAPSHOT_DATE
+                .and_then(|d| d.parse::<NaiveDate>().ok())
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .and_then(|d| Local.from_local_datetime(&d).earliest());
+            // Builds without eol-snapshot/DATE don't know when it was taken
+            Ok(match taken {
+                Some(taken) => loaded(cycles, Origin::Snapshot, taken),
+                None => (cycles, None),
+            })
+        }
+        None => Err(format!(
+            "{} is neither cached nor bundled, it can't be fetched with --offline",
+            product_name
+        )
+        .into()),
+    }
+}
+
+/// Fetches every product not already present in `data`, all at once
+pub async fn fetch_products(
+    products: &[&str],
+    mut data: EolData,
+) -> Result<EolData, Box<dyn Error>> {
+    let missing: Vec<&str> = products
+        .iter()
+        .copied()
+        .filter(|product| !data.contains_key(*product))
+        .collect();
+    let fetched = join_all(missing.iter().map(|product| fetch_eol(product))).await;
+    for (product, cycles) in missing.into_iter().zip(fetched) {
+        data.insert(product.to_string(), cycles?);
+    }
+    Ok(data)
+}
+
+/// Reads custom products from a JSON or TOML file (picked by extension), using
+/// the same layout as the endoflife.date API keyed by product name:
+///
+/// ```json
+/// { "contoso-golden": [{ "cycle": "2023.1", "eol": "2025-06-30" }] }
+/// ```
+///
+/// Dates have to be quoted strings in TOML as well.
+pub fn load_eol_file(path: &Path) -> Result<EolData, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let data = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&contents)?,
+        _ => serde_json::from_str(&contents)?,
+    };
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EOLEntity, EolData, SNAPSHOT};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_boolean_fields() {
+        let cycles: Vec<EOLEntity> = serde_json::from_str(
+            r#"[
+                {"cycle": "11-23h2-e", "releaseDate": "2023-10-31", "support": true, "eol": "2026-11-10", "lts": false},
+                {"cycle": "10-21h2-e-lts", "releaseDate": "2021-11-16", "support": false, "eol": false, "lts": "2021-11-16"},
+                {"cycle": "7", "releaseDate": "2013-12-11", "support": "2018-09-18", "eol": true, "extendedSupport": true, "discontinued": true}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(cycles[0].support, Some(NaiveDate::MAX));
+        assert!(!cycles[0].lts);
+        assert_eq!(cycles[1].eol, NaiveDate::MAX);
+        assert_eq!(cycles[1].support, Some(NaiveDate::MIN));
+        assert!(cycles[1].lts);
+        assert_eq!(cycles[2].eol, NaiveDate::MIN);
+        assert_eq!(cycles[2].extended_support, Some(NaiveDate::MAX));
+        assert!(cycles[0].discontinued.is_none());
+        assert_eq!(cycles[2].discontinued, Some(NaiveDate::MIN));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        for (product, body) in SNAPSHOT {
+            let cycles: Result<Vec<EOLEntity>, _> = serde_json::from_str(body);
+            assert!(cycles.is_ok(), "unreadable snapshot of {}", product);
+        }
+    }
+
+    #[test]
+    fn test_custom_products() {
+        let data: EolData = toml::from_str(
+            r#"
+            [[contoso-golden]]
+            cycle = "2023.1"
+            eol = "2025-06-30"
+            extendedSupport = false
+            "#,
+        )
+        .unwrap();
+        let cycles = &data["contoso-golden"];
+        assert_eq!(cycles[0].cycle, "2023.1");
+        assert_eq!(cycles[0].eol.to_string(), "2025-06-30");
+        assert!(cycles[0].extended_support.is_none());
+    }
 }
@@ -0,0 +1,86 @@
+use crate::eol_detection::detector::{image_version, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::eol_detection::sku::normalize_sku;
+use crate::VMResult;
+
+// Flatcar is not versioned per image on Azure, the SKU selects a release
+// channel instead. endoflife.date doesn't track these, so the channels are
+// listed here.
+const ACTIVE_CHANNELS: [&str; 4] = ["alpha", "beta", "stable", "lts"];
+const DISCONTINUED_CHANNELS: [&str; 3] = ["edge", "lts2021", "lts2022"];
+
+pub struct Flatcar;
+
+impl OsDetector for Flatcar {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher.to_lowercase() == "kinvolk" || vm.offer.to_lowercase().contains("flatcar")
+    }
+
+    fn product(&self) -> Option<&str> {
+        None
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+
+    fn is_outdated(&self, vm: &VMResult, _eol_data: &EolData) -> String {
+        let channel = match image_version(vm, self.parse_azure_version(vm)) {
+            Some(v) => v,
+            None => return "--".to_string(),
+        };
+        if DISCONTINUED_CHANNELS.contains(&channel.as_str()) {
+            return "EOL".to_string();
+        }
+        if ACTIVE_CHANNELS.contains(&channel.as_str()) {
+            return "Supported".to_string();
+        }
+
+        "--".to_string()
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // stable, stable-gen2, lts2022, lts-2022-gen2
+    let lower = az_version.to_lowercase();
+    let parts: Vec<&str> = lower.split(['-', '_']).collect();
+    if parts[0].is_empty() {
+        return None;
+    }
+    if parts[0] == "lts"
+        && parts.len() > 1
+        && parts[1].len() == 4
+        && parts[1].chars().all(|c| c.is_ascii_digit())
+    {
+        return Some(format!("lts{}", parts[1]));
+    }
+    Some(parts[0].to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_channels() {
+        assert_eq!(parse_azure_version("stable"), Some(String::from("stable")));
+        assert_eq!(
+            parse_azure_version("stable-gen2"),
+            Some(String::from("stable"))
+        );
+        assert_eq!(parse_azure_version("lts-gen2"), Some(String::from("lts")));
+    }
+
+    #[test]
+    fn test_pinned_lts() {
+        assert_eq!(
+            parse_azure_version("lts2022"),
+            Some(String::from("lts2022"))
+        );
+        assert_eq!(
+            parse_azure_version("lts-2021-gen2"),
+            Some(String::from("lts2021"))
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use crate::eol_detection::detector::{cycles, eol_status, image_version, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::eol_detection::sku::normalize_sku;
+use crate::VMResult;
+
+pub struct FreeBSD;
+
+impl OsDetector for FreeBSD {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher.to_lowercase() == "thefreebsdfoundation"
+            || vm.offer.to_lowercase().contains("freebsd")
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("freebsd")
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+
+    fn is_outdated(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        let version = match image_version(vm, self.parse_azure_version(vm)) {
+            Some(v) => v,
+            None => return "--".to_string(),
+        };
+        // Stable branches are published either as the bare major or as `stable/<major>`
+        let stable_cycle = format!("stable/{}", version);
+        cycles(self.product(), eol_data)
+            .iter()
+            .find(|item| item.cycle == version || item.cycle == stable_cycle)
+            .map(|item| eol_status(item.eol))
+            .unwrap_or_else(|| "--".to_string())
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // 13_2-release, 14_0-release-amd64-gen2-zfs, 13-stable
+    // A RELEASE image is bound to its minor release, a STABLE image
+    // follows the major branch.
+    let lower = az_version.to_lowercase();
+    let parts: Vec<&str> = lower.split('-').collect();
+    let version: Vec<&str> = parts[0].split(['_', '.']).collect();
+    if version[0].is_empty() || !version[0].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if parts.contains(&"stable") || version.len() < 2 {
+        return Some(version[0].to_string());
+    }
+    Some(format!("{}.{}", version[0], version[1]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_release() {
+        assert_eq!(
+            parse_azure_version("13_2-release"),
+            Some(String::from("13.2"))
+        );
+        assert_eq!(
+            parse_azure_version("14_0-RELEASE-amd64-gen2-zfs"),
+            Some(String::from("14.0"))
+        );
+    }
+
+    #[test]
+    fn test_stable() {
+        assert_eq!(parse_azure_version("13_2-stable"), Some(String::from("13")));
+        assert_eq!(parse_azure_version("14-stable"), Some(String::from("14")));
+        assert_eq!(parse_azure_version("current"), None);
+    }
+}
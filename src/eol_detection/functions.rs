@@ -0,0 +1,133 @@
+use crate::eol_detection::detector::{eol_status, find_cycle, Detection, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::eol_detection::runtime;
+use crate::inventory::app_service;
+use crate::VMResult;
+use chrono::NaiveDate;
+
+// endoflife.date doesn't track the Functions host. 1.x is supported for .NET
+// Framework apps only, 4.x has no announced end yet.
+const HOST_RUNTIMES: [(&str, Option<&str>); 4] = [
+    ("1", Some("2026-09-14")),
+    ("2", Some("2022-12-13")),
+    ("3", Some("2022-12-13")),
+    ("4", None),
+];
+
+/// Function Apps, the SKU holds `FUNCTIONS_EXTENSION_VERSION` and the version
+/// holds the language runtime as `offer|version` (`python|3.11`).
+pub struct FunctionApp;
+
+impl OsDetector for FunctionApp {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher == app_service::PUBLISHER && vm.offer == "functions"
+    }
+
+    fn product(&self) -> Option<&str> {
+        None
+    }
+
+    fn products(&self) -> Vec<&str> {
+        runtime::runtimes().into_iter().map(|r| r.product).collect()
+    }
+
+    fn products_for(&self, vm: &VMResult) -> Vec<&str> {
+        let offer = vm.version.split_once('|').map(|(offer, _)| offer);
+        runtime::runtimes()
+            .into_iter()
+            .filter(|r| Some(r.offer) == offer)
+            .map(|r| r.product)
+            .collect()
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(&vm.sku)
+    }
+
+    /// Evaluates the Functions host and the language runtime, the verdict
+    /// follows whichever reaches EOL first and the component names it.
+    fn detect(&self, vm: &VMResult, eol_data: &EolData) -> Detection {
+        let version = self.parse_azure_version(vm).unwrap_or_default();
+        let host = host_eol(&version).map(|eol| (eol, format!("Functions {}.x", version), None));
+        let language = vm
+            .version
+            .split_once('|')
+            .and_then(|(offer, language_version)| {
+                let runtime = runtime::runtimes().into_iter().find(|r| r.offer == offer)?;
+                let cycle = runtime::parse_version(language_version, runtime.parts)?;
+                let item = find_cycle(Some(runtime.product), &cycle, eol_data)?;
+                Some((
+                    item.eol,
+                    format!("{} {}", runtime.offer, cycle),
+                    item.override_source.clone(),
+                ))
+            });
+        let (eol, component, source) = match (host, language) {
+            (Some(host), Some(language)) if language.0 < host.0 => language,
+            (Some(host), _) => host,
+            (None, Some(language)) => language,
+            (None, None) => {
+                return Detection {
+                    version,
+                    status: "--".to_string(),
+                    classified: true,
+                    ..Default::default()
+                }
+            }
+        };
+        Detection {
+            version,
+            status: eol_status(eol),
+            component,
+            support_tier: String::new(),
+            source: source.unwrap_or_default(),
+            classified: true,
+            ..Default::default()
+        }
+    }
+}
+
+fn host_eol(version: &str) -> Option<NaiveDate> {
+    HOST_RUNTIMES
+        .iter()
+        .find(|(major, _)| *major == version)
+        .map(|(_, eol)| match eol {
+            Some(eol) => NaiveDate::parse_from_str(eol, "%Y-%m-%d").unwrap(),
+            None => NaiveDate::MAX,
+        })
+}
+
+pub fn parse_azure_version(extension_version: &str) -> Option<String> {
+    // Examples:
+    // ~4, ~1, 4.0, 3.0.15828
+    let major = extension_version
+        .trim_start_matches('~')
+        .split('.')
+        .next()
+        .unwrap_or_default();
+    if major.is_empty() || !major.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(major.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{host_eol, parse_azure_version};
+
+    #[test]
+    fn test_extension_versions() {
+        assert_eq!(parse_azure_version("~4"), Some(String::from("4")));
+        assert_eq!(parse_azure_version("~1"), Some(String::from("1")));
+        assert_eq!(parse_azure_version("3.0.15828"), Some(String::from("3")));
+        assert_eq!(parse_azure_version("beta"), None);
+        assert_eq!(parse_azure_version(""), None);
+    }
+
+    #[test]
+    fn test_host_eol() {
+        assert_eq!(host_eol("3").unwrap().to_string(), "2022-12-13");
+        assert_eq!(host_eol("4"), Some(chrono::NaiveDate::MAX));
+        assert_eq!(host_eol("5"), None);
+    }
+}
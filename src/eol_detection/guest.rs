@@ -0,0 +1,152 @@
+use crate::eol_detection::detector::{eol_status, Detection, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::eol_detection::mapping::{parse_version, VersionFormat};
+use crate::VMResult;
+
+// osName as reported by the VM agent, the endoflife.date product and how its
+// cycles are formatted.
+const GUEST_PRODUCTS: [(&str, &str, VersionFormat); 11] = [
+    ("ubuntu", "ubuntu", VersionFormat::MajorMinor),
+    ("centos", "centos", VersionFormat::Major),
+    ("rhel", "redhat", VersionFormat::Major),
+    ("redhat", "redhat", VersionFormat::Major),
+    ("debian", "debian", VersionFormat::Major),
+    ("almalinux", "almalinux", VersionFormat::Major),
+    ("rocky", "rocky-linux", VersionFormat::Major),
+    ("opensuse-leap", "opensuse", VersionFormat::MajorMinor),
+    ("sles", "sles", VersionFormat::MajorMinor),
+    ("alpine", "alpine", VersionFormat::MajorMinor),
+    ("freebsd", "freebsd", VersionFormat::MajorMinor),
+];
+
+/// Classifies machines by the OS the VM agent reports (`--deep`), takes
+/// precedence over the image reference so in-place upgrades and custom
+/// images are evaluated by what is actually running. Arc-enabled servers
+/// report it without `--deep`.
+pub struct GuestOs;
+
+impl OsDetector for GuestOs {
+    fn matches(&self, vm: &VMResult) -> bool {
+        parse_guest_os(&vm.guest_os_name, &vm.guest_os_version).is_some()
+    }
+
+    fn product(&self) -> Option<&str> {
+        None
+    }
+
+    fn products(&self) -> Vec<&str> {
+        let mut products: Vec<&str> = GUEST_PRODUCTS.iter().map(|(_, p, _)| *p).collect();
+        products.push("windowsserver");
+        products
+    }
+
+    fn products_for(&self, vm: &VMResult) -> Vec<&str> {
+        parse_guest_os(&vm.guest_os_name, &vm.guest_os_version)
+            .map(|(product, _)| product)
+            .into_iter()
+            .collect()
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_guest_os(&vm.guest_os_name, &vm.guest_os_version).map(|(_, version)| version)
+    }
+
+    fn detect(&self, vm: &VMResult, eol_data: &EolData) -> Detection {
+        // Only reached for machines `matches` accepted
+        let (product, version) =
+            parse_guest_os(&vm.guest_os_name, &vm.guest_os_version).unwrap_or_default();
+        let item = eol_data
+            .get(product)
+            .and_then(|cycles| cycles.iter().find(|item| item.cycle == version));
+        Detection {
+            status: item
+                .map(|item| eol_status(item.eol))
+                .unwrap_or_else(|| "--".to_string()),
+            component: format!("{} {}", vm.guest_os_name, vm.guest_os_version),
+            support_tier: String::new(),
+            source: item
+                .and_then(|item| item.override_source.clone())
+                .unwrap_or_default(),
+            classified: true,
+            version,
+            ..Default::default()
+        }
+    }
+}
+
+/// Maps the agent reported osName/osVersion to an endoflife.date product and
+/// cycle.
+pub fn parse_guest_os(os_name: &str, os_version: &str) -> Option<(&'static str, String)> {
+    // Examples:
+    // ubuntu 22.04, centos 7.9.2009, rhel 8.6, debian 11,
+    // Windows Server 2019 Datacenter 10.0.17763.4252,
+    // Windows Server 2012 R2 Datacenter 6.3.9600
+    let name = os_name.trim().to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+    if let Some(rest) = name.strip_prefix("windows server") {
+        let mut words = rest.split_whitespace();
+        let year = words.next().filter(|y| y.len() == 4)?;
+        if !year.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        return match words.next() {
+            Some("r2") => Some(("windowsserver", format!("{}-r2", year))),
+            _ => Some(("windowsserver", year.to_string())),
+        };
+    }
+    let (_, product, format) = GUEST_PRODUCTS
+        .iter()
+        .find(|(guest, _, _)| name == *guest || name.starts_with(&format!("{} ", guest)))?;
+    Some((product, parse_version(os_version, *format)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_guest_os, GuestOs};
+    use crate::eol_detection::detector::OsDetector;
+    use crate::VMResult;
+
+    #[test]
+    fn test_products_for() {
+        let vm = VMResult {
+            guest_os_name: String::from("Windows Server 2016 Datacenter"),
+            guest_os_version: String::from("10.0.14393.6351"),
+            ..Default::default()
+        };
+        // Only the product of the running OS is fetched
+        assert_eq!(GuestOs.products_for(&vm), vec!["windowsserver"]);
+        assert_eq!(
+            GuestOs.products_for(&VMResult::default()),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn test_linux() {
+        assert_eq!(
+            parse_guest_os("ubuntu", "22.04"),
+            Some(("ubuntu", String::from("22.04")))
+        );
+        assert_eq!(
+            parse_guest_os("centos", "7.9.2009"),
+            Some(("centos", String::from("7")))
+        );
+        assert_eq!(parse_guest_os("ubuntu", ""), None);
+        assert_eq!(parse_guest_os("", "22.04"), None);
+    }
+
+    #[test]
+    fn test_windows() {
+        assert_eq!(
+            parse_guest_os("Windows Server 2019 Datacenter", "10.0.17763.4252"),
+            Some(("windowsserver", String::from("2019")))
+        );
+        assert_eq!(
+            parse_guest_os("Windows Server 2012 R2 Datacenter", "6.3.9600"),
+            Some(("windowsserver", String::from("2012-r2")))
+        );
+        assert_eq!(parse_guest_os("Windows 10 Enterprise", "10.0.19045"), None);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::eol_detection::detector::{eol_status, Detection, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::inventory::hdinsight;
+use crate::VMResult;
+use chrono::NaiveDate;
+
+// endoflife.date doesn't track HDInsight, these are the ends of basic
+// support. Versions without a date have none announced.
+const RELEASES: [(&str, Option<&str>); 6] = [
+    ("3.5", Some("2020-12-31")),
+    ("3.6", Some("2022-10-01")),
+    ("4.0", Some("2025-03-31")),
+    ("5.0", None),
+    ("5.1", None),
+    ("5.2", None),
+];
+
+pub struct HDInsight;
+
+impl OsDetector for HDInsight {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher == hdinsight::PUBLISHER
+    }
+
+    fn product(&self) -> Option<&str> {
+        None
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(&vm.sku)
+    }
+
+    fn is_outdated(&self, vm: &VMResult, _eol_data: &EolData) -> String {
+        match self
+            .parse_azure_version(vm)
+            .as_deref()
+            .and_then(release_eol)
+        {
+            Some(eol) => eol_status(eol),
+            None => "--".to_string(),
+        }
+    }
+
+    fn detect(&self, vm: &VMResult, eol_data: &EolData) -> Detection {
+        Detection {
+            version: self.parse_azure_version(vm).unwrap_or_default(),
+            status: self.is_outdated(vm, eol_data),
+            component: vm.version.clone(),
+            support_tier: String::new(),
+            source: String::new(),
+            classified: true,
+            ..Default::default()
+        }
+    }
+}
+
+fn release_eol(version: &str) -> Option<NaiveDate> {
+    RELEASES
+        .iter()
+        .find(|(release, _)| *release == version)
+        .map(|(_, eol)| match eol {
+            Some(eol) => NaiveDate::parse_from_str(eol, "%Y-%m-%d").unwrap(),
+            None => NaiveDate::MAX,
+        })
+}
+
+pub fn parse_azure_version(cluster_version: &str) -> Option<String> {
+    // Examples:
+    // 4.0.3000.1, 3.6.1000.67, 5.1
+    let parts: Vec<&str> = cluster_version.split('.').take(2).collect();
+    if parts.len() < 2 || parts.iter().any(|p| p.parse::<u32>().is_err()) {
+        return None;
+    }
+    Some(parts.join("."))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(parse_azure_version("4.0.3000.1"), Some(String::from("4.0")));
+        assert_eq!(
+            parse_azure_version("3.6.1000.67"),
+            Some(String::from("3.6"))
+        );
+        assert_eq!(parse_azure_version("5.1"), Some(String::from("5.1")));
+        assert_eq!(parse_azure_version("4"), None);
+    }
+}
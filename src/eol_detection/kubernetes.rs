@@ -0,0 +1,92 @@
+use crate::eol_detection::detector::{eol_status, find_cycle, OsDetector};
+use crate::eol_detection::eol::{EOLEntity, EolData};
+use crate::inventory::aks;
+use crate::VMResult;
+
+/// Kubernetes version of an AKS cluster, the SKU holds the oldest version the
+/// control plane or any node pool runs.
+pub struct Kubernetes;
+
+// Support plan of clusters paying for AKS long term support
+const LONG_TERM_SUPPORT: &str = "AKSLongTermSupport";
+
+impl Kubernetes {
+    /// The AKS support window, upstream Kubernetes for versions AKS no longer
+    /// lists at all.
+    fn cycle<'a>(&self, vm: &VMResult, eol_data: &'a EolData) -> Option<&'a EOLEntity> {
+        let version = self.parse_azure_version(vm)?;
+        find_cycle(self.product(), &version, eol_data)
+            .or_else(|| find_cycle(Some("kubernetes"), &version, eol_data))
+    }
+}
+
+impl OsDetector for Kubernetes {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher == aks::PUBLISHER && vm.offer == "kubernetes"
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("azure-kubernetes-service")
+    }
+
+    fn products(&self) -> Vec<&str> {
+        vec!["azure-kubernetes-service", "kubernetes"]
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(&vm.sku)
+    }
+
+    fn is_outdated(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        let item = match self.cycle(vm, eol_data) {
+            Some(item) => item,
+            None => return "--".to_string(),
+        };
+        match item.extended_support {
+            Some(lts) if vm.support_plan == LONG_TERM_SUPPORT => eol_status(lts),
+            _ => eol_status(item.eol),
+        }
+    }
+
+    fn support_tier(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        if self.cycle(vm, eol_data).is_none() {
+            return String::new();
+        }
+        if vm.support_plan == LONG_TERM_SUPPORT {
+            String::from("Long term support")
+        } else {
+            String::from("Community support")
+        }
+    }
+
+    fn eol_source(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        self.cycle(vm, eol_data)
+            .and_then(|item| item.override_source.clone())
+            .unwrap_or_default()
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // 1.27.7, 1.28, v1.29.2
+    // endoflife.date lists the minor releases (1.27, 1.28)
+    let parts: Vec<&str> = az_version.trim_start_matches('v').split('.').collect();
+    if parts.len() < 2 || parts[..2].iter().any(|p| p.parse::<u32>().is_err()) {
+        return None;
+    }
+    Some(format!("{}.{}", parts[0], parts[1]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(parse_azure_version("1.27.7"), Some(String::from("1.27")));
+        assert_eq!(parse_azure_version("1.28"), Some(String::from("1.28")));
+        assert_eq!(parse_azure_version("v1.29.2"), Some(String::from("1.29")));
+        assert_eq!(parse_azure_version("1"), None);
+        assert_eq!(parse_azure_version(""), None);
+    }
+}
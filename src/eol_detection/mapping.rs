@@ -0,0 +1,210 @@
+use crate::eol_detection::detector::OsDetector;
+use crate::eol_detection::sku::normalize_sku;
+use crate::inventory::containers;
+use crate::VMResult;
+use serde::Deserialize;
+
+/// Image field the version is read from
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionSource {
+    #[default]
+    Sku,
+    Offer,
+    Version,
+}
+
+/// How the numbers found in the image field are turned into a cycle
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionFormat {
+    /// `8_5-gen2` -> `8`
+    #[default]
+    Major,
+    /// `8_5-gen2` -> `8.5`, falls back to the major when there is no minor
+    MajorMinor,
+    /// The field as-is, lowercased
+    Raw,
+}
+
+/// Maps images to an endoflife.date product without a dedicated detector.
+///
+/// Patterns are matched case-insensitively against the image reference and
+/// may contain `*` wildcards, a missing pattern matches anything.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImageMapping {
+    pub publisher: Option<String>,
+    pub offer: Option<String>,
+    pub sku: Option<String>,
+    pub product: String,
+    #[serde(default)]
+    pub version_from: VersionSource,
+    #[serde(default)]
+    pub version_format: VersionFormat,
+}
+
+impl ImageMapping {
+    fn new(publisher: &str, offer: &str, product: &str, version_from: VersionSource) -> Self {
+        ImageMapping {
+            publisher: Some(publisher.to_string()),
+            offer: Some(offer.to_string()),
+            sku: None,
+            product: product.to_string(),
+            version_from,
+            version_format: VersionFormat::Major,
+        }
+    }
+}
+
+/// Community and partner images that are common enough to know about out of
+/// the box, they are evaluated after the built-in detectors.
+pub fn defaults() -> Vec<ImageMapping> {
+    let container = |sku: &str, product: &str, version_format| ImageMapping {
+        publisher: Some(containers::PUBLISHER.to_string()),
+        offer: None,
+        sku: Some(sku.to_string()),
+        product: product.to_string(),
+        version_from: VersionSource::Sku,
+        version_format,
+    };
+    vec![
+        // debian-11 / 11-gen2
+        ImageMapping::new("debian", "debian-*", "debian", VersionSource::Sku),
+        ImageMapping::new("credativ", "debian", "debian", VersionSource::Sku),
+        ImageMapping::new("almalinux", "almalinux*", "almalinux", VersionSource::Sku),
+        ImageMapping::new("resf", "rockylinux*", "rocky-linux", VersionSource::Sku),
+        // procomputers publishes one offer per release: rocky-linux-8-5, almalinux-9
+        ImageMapping::new(
+            "procomputers",
+            "rocky-linux*",
+            "rocky-linux",
+            VersionSource::Offer,
+        ),
+        ImageMapping::new(
+            "procomputers",
+            "almalinux*",
+            "almalinux",
+            VersionSource::Offer,
+        ),
+        ImageMapping::new(
+            "procomputers",
+            "alma-linux*",
+            "almalinux",
+            VersionSource::Offer,
+        ),
+        // Base OS in container image tags: centos7, 1.2-ubuntu18.04
+        container("centos*", "centos", VersionFormat::Major),
+        container("ubuntu*", "ubuntu", VersionFormat::MajorMinor),
+        container("alpine*", "alpine", VersionFormat::MajorMinor),
+        container("debian*", "debian", VersionFormat::Major),
+    ]
+}
+
+impl OsDetector for ImageMapping {
+    fn matches(&self, vm: &VMResult) -> bool {
+        pattern_matches(self.publisher.as_deref(), &vm.publisher)
+            && pattern_matches(self.offer.as_deref(), &vm.offer)
+            && pattern_matches(self.sku.as_deref(), &vm.sku)
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some(&self.product)
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        let field = match self.version_from {
+            VersionSource::Sku => normalize_sku(&vm.sku),
+            VersionSource::Offer => &vm.offer,
+            VersionSource::Version => &vm.version,
+        };
+        parse_version(field, self.version_format)
+    }
+}
+
+fn pattern_matches(pattern: Option<&str>, value: &str) -> bool {
+    match pattern {
+        Some(pattern) => glob_match(&pattern.to_lowercase(), &value.to_lowercase()),
+        None => true,
+    }
+}
+
+/// Minimal glob, only `*` is special
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+    let first = parts[0];
+    let last = parts[parts.len() - 1];
+    if value.len() < first.len() + last.len() || !value.starts_with(first) || !value.ends_with(last)
+    {
+        return false;
+    }
+    let mut rest = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+pub fn parse_version(field: &str, format: VersionFormat) -> Option<String> {
+    // Examples:
+    // 7_9, 8_5-gen2, 11-gen2, rocky-linux-8-5, almalinux-9, ubuntu18.04
+    if format == VersionFormat::Raw {
+        return if field.is_empty() {
+            None
+        } else {
+            Some(field.to_lowercase())
+        };
+    }
+    let is_number = |p: &&str| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit());
+    // The major may be glued to a name, as in container tags (centos7)
+    let mut parts = field
+        .split(['-', '_', '.'])
+        .skip_while(|p| !is_number(&p.trim_start_matches(|c: char| c.is_ascii_alphabetic())));
+    let major = parts
+        .next()?
+        .trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    match (format, parts.next().filter(is_number)) {
+        (VersionFormat::MajorMinor, Some(minor)) => Some(format!("{}.{}", major, minor)),
+        _ => Some(major.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glob_match, parse_version, VersionFormat};
+
+    #[test]
+    fn test_glob() {
+        assert!(glob_match("debian-*", "debian-11"));
+        assert!(glob_match("*linux*", "rocky-linux-8"));
+        assert!(glob_match("credativ", "credativ"));
+        assert!(!glob_match("debian-*", "debian"));
+        assert!(!glob_match("alma*", "rocky-linux"));
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(
+            parse_version("8_5-gen2", VersionFormat::MajorMinor),
+            Some(String::from("8.5"))
+        );
+        assert_eq!(
+            parse_version("11-gen2", VersionFormat::MajorMinor),
+            Some(String::from("11"))
+        );
+        assert_eq!(
+            parse_version("rocky-linux-8-5", VersionFormat::Major),
+            Some(String::from("8"))
+        );
+        assert_eq!(
+            parse_version("ubuntu18.04", VersionFormat::MajorMinor),
+            Some(String::from("18.04"))
+        );
+        assert_eq!(parse_version("latest", VersionFormat::Major), None);
+    }
+}
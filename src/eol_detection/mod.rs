@@ -1,5 +1,193 @@
+pub mod alpine;
+pub mod azure_linux;
 pub mod centos;
+pub mod centos_stream;
+pub mod cloud_services;
+pub mod database;
+pub mod detector;
+pub mod flatcar;
+pub mod freebsd;
+pub mod functions;
+pub mod guest;
+pub mod hdinsight;
+pub mod kubernetes;
+pub mod mapping;
+pub mod opensuse;
+pub mod published;
 pub mod redhat;
+pub mod runtime;
+pub mod sku;
+pub mod sles;
+pub mod sqlserver;
 pub mod ubuntu;
 pub mod windows;
-pub mod eol;
\ No newline at end of file
+pub mod windows_client;
+pub mod eol;
+
+use crate::config::Config;
+use crate::logging::{error, trace};
+use crate::VMResult;
+use detector::{Detection, OsDetector};
+use eol::EolData;
+use futures::future::join_all;
+
+/// Switches changing which detectors are used and how they evaluate a VM
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    /// Classify by the OS reported by the VM agent first
+    pub deep: bool,
+    /// Use the exact image version to evaluate minor releases / builds
+    pub minor_versions: bool,
+}
+
+/// Every known detector, the first one matching a VM classifies it so more
+/// specific detectors have to come before the generic ones (e.g. SQL Server
+/// and Windows client images before Windows Server).
+///
+/// Mappings from the config file go first, the built-in mappings last. With
+/// `deep` the OS reported by the VM agent beats everything else, without it
+/// the reported OS is only known for Arc-enabled servers and checked last.
+pub fn registry(config: &Config, options: Options) -> Vec<Box<dyn OsDetector>> {
+    let mut detectors: Vec<Box<dyn OsDetector>> = Vec::new();
+    if options.deep {
+        detectors.push(Box::new(guest::GuestOs));
+    }
+    for mapping in &config.mappings {
+        detectors.push(Box::new(mapping.clone()));
+    }
+    let builtin: Vec<Box<dyn OsDetector>> = vec![
+        Box::new(kubernetes::Kubernetes),
+        Box::new(functions::FunctionApp),
+        Box::new(hdinsight::HDInsight),
+        Box::new(cloud_services::ClassicCloudService),
+        Box::new(ubuntu::Ubuntu),
+        Box::new(centos_stream::CentOSStream),
+        Box::new(centos::CentOS),
+        Box::new(sqlserver::SQLServer),
+        Box::new(windows_client::WindowsClient),
+        Box::new(windows::WindowsServer {
+            minor_versions: options.minor_versions,
+        }),
+        Box::new(redhat::RedHat {
+            minor_versions: options.minor_versions,
+        }),
+        Box::new(alpine::Alpine),
+        Box::new(freebsd::FreeBSD),
+        Box::new(azure_linux::AzureLinux),
+        Box::new(flatcar::Flatcar),
+        Box::new(opensuse::OpenSuse),
+        Box::new(sles::Sles),
+    ];
+    detectors.extend(builtin);
+    for runtime in runtime::runtimes() {
+        detectors.push(Box::new(runtime));
+    }
+    for database in database::databases() {
+        detectors.push(Box::new(database));
+    }
+    for service in published::services() {
+        detectors.push(Box::new(service));
+    }
+    for mapping in mapping::defaults() {
+        detectors.push(Box::new(mapping));
+    }
+    if !options.deep {
+        detectors.push(Box::new(guest::GuestOs));
+    }
+    detectors
+}
+
+/// endoflife.date products needed by the given detectors
+pub fn products(detectors: &[Box<dyn OsDetector>]) -> Vec<&str> {
+    let mut products: Vec<&str> = detectors.iter().flat_map(|d| d.products()).collect();
+    products.sort();
+    products.dedup();
+    products
+}
+
+/// endoflife.date data fetched as products turn up in the report, so a run
+/// only requests the products of the resources it found
+pub struct LazyEolData<'a> {
+    data: EolData,
+    config: &'a Config,
+}
+
+impl<'a> LazyEolData<'a> {
+    /// Starts from the `--eol-file` products, which are never fetched
+    pub fn new(mut custom: EolData, config: &'a Config) -> Self {
+        for (product, cycles) in custom.iter_mut() {
+            config.apply_product_overrides(product, cycles);
+        }
+        LazyEolData {
+            data: custom,
+            config,
+        }
+    }
+
+    /// Fetches the products needed to classify `vm` that aren't known yet
+    pub async fn prepare(&mut self, vm: &VMResult, detectors: &[Box<dyn OsDetector>]) {
+        let mut needed: Vec<&str> = detectors
+            .iter()
+            .find(|d| d.matches(vm))
+            .map(|d| d.products_for(vm))
+            .unwrap_or_default();
+        if !vm.sql_server.is_empty() {
+            needed.push("mssqlserver");
+        }
+        needed.retain(|product| !self.data.contains_key(*product));
+        if needed.is_empty() {
+            return;
+        }
+        let fetched = join_all(needed.iter().map(|product| eol::fetch_eol(product))).await;
+        for (product, cycles) in needed.into_iter().zip(fetched) {
+            // Kept empty so the product isn't requested again for every VM,
+            // its resources are reported as unknown
+            let mut cycles = cycles.unwrap_or_else(|e| {
+                error!("Fetching {} from endoflife.date failed: {}", product, e);
+                Vec::new()
+            });
+            self.config.apply_product_overrides(product, &mut cycles);
+            self.data.insert(product.to_string(), cycles);
+        }
+    }
+
+    pub fn data(&self) -> &EolData {
+        &self.data
+    }
+}
+
+pub fn detect(vm: &VMResult, detectors: &[Box<dyn OsDetector>], eol_data: &EolData) -> Detection {
+    let detector = detectors.iter().find(|d| d.matches(vm));
+    let mut products = detector.map(|d| d.products_for(vm)).unwrap_or_default();
+    let mut detection = match detector {
+        Some(detector) => detector.detect(vm, eol_data),
+        None if vm.custom_image => Detection {
+            status: String::from("Custom image - needs manual review"),
+            ..Default::default()
+        },
+        None => Detection {
+            status: String::from("--"),
+            ..Default::default()
+        },
+    };
+    if !vm.sql_server.is_empty() {
+        (detection.sql_version, detection.sql_status) =
+            sqlserver::sql_server_status(&vm.sql_server, eol_data);
+        products.push("mssqlserver");
+    }
+    if let Some(product) = detector.and_then(|d| d.product()) {
+        detection.product = product.to_string();
+    }
+    detection.data_as_of = products.into_iter().filter_map(eol::data_as_of).min();
+    trace!(
+        "{}: {}:{}:{}:{} is {} {}",
+        vm.id,
+        vm.publisher,
+        vm.offer,
+        vm.sku,
+        vm.version,
+        detection.version,
+        detection.status
+    );
+    detection
+}
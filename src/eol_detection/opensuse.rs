@@ -0,0 +1,46 @@
+use crate::eol_detection::detector::OsDetector;
+use crate::eol_detection::sku::normalize_sku;
+use crate::VMResult;
+
+pub struct OpenSuse;
+
+impl OsDetector for OpenSuse {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.offer.to_lowercase().contains("opensuse")
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("opensuse")
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // 15-5-gen2, 15_4, leap-15-6
+    let parts: Vec<&str> = az_version
+        .split(['-', '_', '.'])
+        .skip_while(|p| !p.chars().all(|c| c.is_ascii_digit()) || p.is_empty())
+        .take(2)
+        .collect();
+    if parts.len() < 2 || !parts[1].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}.{}", parts[0], parts[1]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_leap() {
+        assert_eq!(parse_azure_version("15-5-gen2"), Some(String::from("15.5")));
+        assert_eq!(parse_azure_version("15_4"), Some(String::from("15.4")));
+        assert_eq!(parse_azure_version("leap-15-6"), Some(String::from("15.6")));
+        assert_eq!(parse_azure_version("15"), None);
+    }
+}
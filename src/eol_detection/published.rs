@@ -0,0 +1,70 @@
+use crate::eol_detection::detector::{eol_status, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::inventory::{databricks, service_fabric};
+use crate::VMResult;
+
+/// Services whose provider publishes the supported versions itself, the
+/// scanner looks the version up and fills in `support_end`.
+pub struct PublishedSupport {
+    pub publisher: &'static str,
+}
+
+pub fn services() -> Vec<PublishedSupport> {
+    vec![
+        PublishedSupport {
+            publisher: service_fabric::PUBLISHER,
+        },
+        PublishedSupport {
+            publisher: databricks::PUBLISHER,
+        },
+    ]
+}
+
+impl OsDetector for PublishedSupport {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher == self.publisher
+    }
+
+    fn product(&self) -> Option<&str> {
+        None
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(&vm.sku)
+    }
+
+    fn is_outdated(&self, vm: &VMResult, _eol_data: &EolData) -> String {
+        match vm.support_end {
+            Some(end) => eol_status(end),
+            None => "--".to_string(),
+        }
+    }
+}
+
+pub fn parse_azure_version(version: &str) -> Option<String> {
+    // Examples:
+    // 9.1.1436.9590, 10.0.1816.9590, 11.3.x-scala2.12, 13.3.x-gpu-ml-scala2.12
+    let parts: Vec<&str> = version.split(['.', '-']).take(2).collect();
+    if parts.len() < 2 || parts.iter().any(|p| p.parse::<u32>().is_err()) {
+        return None;
+    }
+    Some(parts.join("."))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(
+            parse_azure_version("9.1.1436.9590"),
+            Some(String::from("9.1"))
+        );
+        assert_eq!(
+            parse_azure_version("11.3.x-scala2.12"),
+            Some(String::from("11.3"))
+        );
+        assert_eq!(parse_azure_version("custom:latest"), None);
+    }
+}
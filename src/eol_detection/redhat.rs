@@ -1,50 +1,194 @@
-use crate::eol_detection::eol::{fetch_eol, EOLEntity};
+use crate::eol_detection::detector::{eol_status, find_cycle, image_version, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::eol_detection::sku::normalize_sku;
 use crate::VMResult;
-use chrono::Months;
-use reqwest::Error;
+use chrono::NaiveDate;
 
-pub async fn list() -> Result<Vec<EOLEntity>, Error> {
-    fetch_eol("redhat").await
+// endoflife.date only tracks the major releases. Minor releases stop
+// receiving updates once the next minor ships, unless they are an
+// Extended Update Support (EUS) release. The last minor of a major is
+// covered by the major's own dates and is therefore not listed.
+const MINOR_RELEASES: [(&str, &str, bool); 19] = [
+    ("7.6", "2020-10-31", true),
+    ("7.7", "2021-08-30", true),
+    ("7.8", "2020-09-29", false),
+    ("8.1", "2021-11-30", true),
+    ("8.2", "2022-04-30", true),
+    ("8.3", "2021-05-18", false),
+    ("8.4", "2023-05-31", true),
+    ("8.5", "2022-05-10", false),
+    ("8.6", "2024-05-31", true),
+    ("8.7", "2023-05-16", false),
+    ("8.8", "2025-05-31", true),
+    ("8.9", "2024-05-22", false),
+    ("9.0", "2024-05-31", true),
+    ("9.1", "2023-05-09", false),
+    ("9.2", "2025-05-31", true),
+    ("9.3", "2024-04-30", false),
+    ("9.4", "2026-04-30", true),
+    ("9.5", "2025-05-13", false),
+    ("9.6", "2027-05-31", true),
+];
+
+pub struct RedHat {
+    /// Take the minor release from the exact image version when the SKU only
+    /// names the major (8-lvm-gen2 deployed as 8.4.2021053108)
+    pub minor_versions: bool,
+}
+
+impl RedHat {
+    fn versions(&self, vm: &VMResult) -> Option<(String, Option<String>)> {
+        let (major, minor) = parse_minor_version(normalize_sku(&vm.sku))?;
+        let exact = parse_exact_version(&vm.exact_version)
+            .filter(|v| self.minor_versions && v.split('.').next() == Some(major.as_str()));
+        Some((major, minor.or(exact)))
+    }
 }
 
-pub fn is_outdated(vm: &VMResult, eol_list: &Vec<EOLEntity>) -> String {
-    let version = match parse_azure_version(&vm.sku) {
-        Some(v) => v,
-        None => {
-            eprintln!("[ ERROR ] Parsing azure version falied for {:#?}", vm);
-            return "--".to_string();
+impl OsDetector for RedHat {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.offer.to_lowercase().contains("rhel")
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("redhat")
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+
+    fn is_outdated(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        let (version, minor) = match image_version(vm, self.versions(vm)) {
+            Some(v) => v,
+            None => return "--".to_string(),
+        };
+        let item = match find_cycle(self.product(), &version, eol_data) {
+            Some(item) => item,
+            None => return "--".to_string(),
+        };
+        match minor.as_deref().and_then(minor_release) {
+            Some((end, _)) => eol_status(end.min(item.eol)),
+            None => eol_status(item.eol),
         }
-    };
-    for item in eol_list {
-        if item.cycle == version {
-            let now = chrono::Utc::now().date_naive();
-            let future_eol = chrono::Utc::now()
-                .checked_add_months(Months::new(12))
-                .unwrap()
-                .date_naive();
-            if item.eol < now {
-                return "EOL".to_string();
-            } else if item.eol > now {
-                if item.eol < future_eol {
-                    return format!("Ending {}", item.eol);
-                }
-                return "Supported".to_string();
+    }
+
+    /// The lifecycle phase the machine is in: Full support, Maintenance, ELS
+    /// (Extended Life cycle Support) or EUS for pinned minor releases.
+    fn support_tier(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        let (version, minor) = match self.versions(vm) {
+            Some(v) => v,
+            None => return String::new(),
+        };
+        let now = chrono::Utc::now().date_naive();
+        if let Some((end, eus)) = minor.as_deref().and_then(minor_release) {
+            if eus && end >= now {
+                return String::from("EUS");
+            } else if end < now {
+                return String::from("EOL");
             }
-            println!("Item matches: {:#?}", item);
+        }
+        let item = match find_cycle(self.product(), &version, eol_data) {
+            Some(item) => item,
+            None => return String::new(),
+        };
+        if item.support.is_some_and(|support| now < support) {
+            String::from("Full support")
+        } else if now < item.eol {
+            String::from("Maintenance")
+        } else if item.extended_support.is_some_and(|els| now < els) {
+            String::from("ELS")
+        } else {
+            String::from("EOL")
         }
     }
+}
 
-    "--".to_string()
+fn minor_release(version: &str) -> Option<(NaiveDate, bool)> {
+    MINOR_RELEASES
+        .iter()
+        .find(|(minor, _, _)| *minor == version)
+        .map(|(_, end, eus)| (NaiveDate::parse_from_str(end, "%Y-%m-%d").unwrap(), *eus))
 }
 
-pub fn parse_azure_version(az_version: &String) -> Option<String> {
-    let parts: Vec<&str> = az_version.split(".").collect();
-    if parts.len() < 2 {
-        let parts: Vec<&str> = az_version.split("-").collect();
-        if parts.is_empty() {
-            return None;
-        }
-        return Some(parts[0].to_string());
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    parse_minor_version(az_version).map(|(major, _)| major)
+}
+
+/// Returns the major release and, when the SKU pins one, the minor release.
+pub fn parse_minor_version(az_version: &str) -> Option<(String, Option<String>)> {
+    // Examples:
+    // 7.6, 8_6, 86-gen2, 810-gen2, rhel-lvm84, 8-lvm-gen2, 7-RAW
+    let lower = az_version.to_lowercase();
+    let token = lower
+        .split('-')
+        .map(|p| p.trim_start_matches(|c: char| c.is_ascii_alphabetic()))
+        .find(|p| p.starts_with(|c: char| c.is_ascii_digit()))?;
+    let token = token.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let parts: Vec<&str> = token.split(['.', '_']).collect();
+    if parts.len() >= 2 {
+        return Some((
+            parts[0].to_string(),
+            Some(format!("{}.{}", parts[0], parts[1])),
+        ));
+    }
+    if !token.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Squashed SKUs (86, 810) carry the major as their first digit
+    if token.len() > 1 {
+        let (major, minor) = token.split_at(1);
+        return Some((major.to_string(), Some(format!("{}.{}", major, minor))));
+    }
+    Some((token.to_string(), None))
+}
+
+/// Minor release of an exact image version (8.6.2022052413 -> 8.6)
+pub fn parse_exact_version(exact_version: &str) -> Option<String> {
+    let mut parts = exact_version.split('.');
+    let major = parts.next().filter(|p| !p.is_empty())?;
+    let minor = parts.next().filter(|p| !p.is_empty())?;
+    if !(major.chars().all(|c| c.is_ascii_digit()) && minor.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    Some(format!("{}.{}", major, minor))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_exact_version, parse_minor_version};
+
+    fn version(major: &str, minor: Option<&str>) -> Option<(String, Option<String>)> {
+        Some((major.to_string(), minor.map(str::to_string)))
+    }
+
+    #[test]
+    fn test_major() {
+        assert_eq!(parse_minor_version("8-lvm-gen2"), version("8", None));
+        assert_eq!(parse_minor_version("7-RAW"), version("7", None));
+        assert_eq!(parse_minor_version("9"), version("9", None));
+    }
+
+    #[test]
+    fn test_eus() {
+        assert_eq!(parse_minor_version("8_6"), version("8", Some("8.6")));
+        assert_eq!(parse_minor_version("7.9"), version("7", Some("7.9")));
+        assert_eq!(parse_minor_version("86-gen2"), version("8", Some("8.6")));
+        assert_eq!(parse_minor_version("810-gen2"), version("8", Some("8.10")));
+        assert_eq!(parse_minor_version("rhel-lvm84"), version("8", Some("8.4")));
+    }
+
+    #[test]
+    fn test_exact_version() {
+        assert_eq!(
+            parse_exact_version("8.6.2022052413"),
+            Some(String::from("8.6"))
+        );
+        assert_eq!(
+            parse_exact_version("9.2.2023060509"),
+            Some(String::from("9.2"))
+        );
+        assert_eq!(parse_exact_version("latest"), None);
+        assert_eq!(parse_exact_version(""), None);
     }
-    return Some(parts[0].to_string());
 }
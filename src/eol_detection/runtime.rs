@@ -0,0 +1,101 @@
+use crate::eol_detection::detector::OsDetector;
+use crate::inventory::{app_service, spring_apps};
+use crate::VMResult;
+
+/// Language runtime of an App Service app or Spring Apps deployment, the
+/// offer names the runtime and the SKU its version.
+pub struct Runtime {
+    pub offer: &'static str,
+    pub product: &'static str,
+    /// Version components the endoflife.date cycles use (18 vs 3.11)
+    pub parts: usize,
+}
+
+/// Runtimes App Service, Functions and Spring Apps offer that endoflife.date
+/// tracks
+pub fn runtimes() -> Vec<Runtime> {
+    vec![
+        Runtime {
+            offer: "php",
+            product: "php",
+            parts: 2,
+        },
+        Runtime {
+            offer: "node",
+            product: "nodejs",
+            parts: 1,
+        },
+        Runtime {
+            offer: "python",
+            product: "python",
+            parts: 2,
+        },
+        Runtime {
+            offer: "dotnet",
+            product: "dotnet",
+            parts: 2,
+        },
+        Runtime {
+            offer: "dotnetfx",
+            product: "dotnetfx",
+            parts: 2,
+        },
+        Runtime {
+            offer: "powershell",
+            product: "powershell",
+            parts: 2,
+        },
+        // The JDK Spring Apps runs Java 11 and later on
+        Runtime {
+            offer: "java",
+            product: "microsoft-build-of-openjdk",
+            parts: 1,
+        },
+    ]
+}
+
+impl OsDetector for Runtime {
+    fn matches(&self, vm: &VMResult) -> bool {
+        (vm.publisher == app_service::PUBLISHER || vm.publisher == spring_apps::PUBLISHER)
+            && vm.offer == self.offer
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some(self.product)
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_version(&vm.sku, self.parts)
+    }
+}
+
+pub fn parse_version(version: &str, parts: usize) -> Option<String> {
+    // Examples:
+    // 8.2, 18-lts, ~20, v6.0, 3.11
+    let version = version
+        .trim_start_matches(['~', 'v', 'V'])
+        .split('-')
+        .next()
+        .unwrap_or_default();
+    let split: Vec<&str> = version.split('.').take(parts).collect();
+    if split.len() < parts || split.iter().any(|p| p.parse::<u32>().is_err()) {
+        return None;
+    }
+    Some(split.join("."))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_version;
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(parse_version("8.2", 2), Some(String::from("8.2")));
+        assert_eq!(parse_version("18-lts", 1), Some(String::from("18")));
+        assert_eq!(parse_version("~20", 1), Some(String::from("20")));
+        assert_eq!(parse_version("v6.0", 2), Some(String::from("6.0")));
+        assert_eq!(parse_version("3.11.4", 2), Some(String::from("3.11")));
+        assert_eq!(parse_version("8", 2), None);
+        assert_eq!(parse_version("latest", 1), None);
+    }
+}
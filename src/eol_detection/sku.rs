@@ -0,0 +1,77 @@
+// Marketplace SKUs carry the hardware generation, CPU architecture and disk
+// layout as suffixes. None of these affect the OS release, so they are
+// stripped before the per-distro parsing.
+const SUFFIXES: [&str; 14] = [
+    "gen1",
+    "gen2",
+    "g2",
+    "gensecond",
+    "arm64",
+    "aarch64",
+    "amd64",
+    "x64",
+    "lvm",
+    "raw",
+    "smalldisk",
+    "zfs",
+    "ufs",
+    "cvm",
+];
+
+/// Strips generation/architecture/disk layout suffixes from a SKU, keeping
+/// its original case (`20_04-lts-gen2` -> `20_04-lts`, `7_9_gen2` -> `7_9`)
+pub fn normalize_sku(sku: &str) -> &str {
+    let mut sku = sku.trim();
+    loop {
+        let idx = match sku.rfind(['-', '_']) {
+            Some(idx) => idx,
+            None => return sku,
+        };
+        let suffix = &sku[idx + 1..];
+        if !SUFFIXES.iter().any(|s| s.eq_ignore_ascii_case(suffix)) {
+            return sku;
+        }
+        sku = &sku[..idx];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::normalize_sku;
+
+    // Real marketplace SKUs and what is left of them
+    const CORPUS: [(&str, &str); 22] = [
+        ("22_04-lts-gen2", "22_04-lts"),
+        ("22_04-lts-arm64", "22_04-lts"),
+        ("20_04-lts-cvm", "20_04-lts"),
+        ("18.04-LTS", "18.04-LTS"),
+        ("pro-18_04-lts-gen2", "pro-18_04-lts"),
+        ("7_9-gen2", "7_9"),
+        ("7_9_gen2", "7_9"),
+        ("7-LVM", "7"),
+        ("7-RAW", "7"),
+        ("8-lvm-gen2", "8"),
+        ("86-gen2", "86"),
+        ("rhel-lvm84", "rhel-lvm84"),
+        ("2019-datacenter-core-smalldisk", "2019-datacenter-core"),
+        ("2019-Datacenter-smalldisk-g2", "2019-Datacenter"),
+        ("2016-datacenter-gensecond", "2016-datacenter"),
+        (
+            "2022-datacenter-azure-edition",
+            "2022-datacenter-azure-edition",
+        ),
+        ("14_0-release-amd64-gen2-zfs", "14_0-release"),
+        ("cbl-mariner-2-arm64", "cbl-mariner-2"),
+        ("azure-linux-3-gen2", "azure-linux-3"),
+        ("stable-gen2", "stable"),
+        ("15-sp5-gen2", "15-sp5"),
+        ("gen2", "gen2"),
+    ];
+
+    #[test]
+    fn test_corpus() {
+        for (sku, expected) in CORPUS {
+            assert_eq!(normalize_sku(sku), expected, "normalizing {}", sku);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::eol_detection::detector::OsDetector;
+use crate::eol_detection::sku::normalize_sku;
+use crate::VMResult;
+
+pub struct Sles;
+
+impl OsDetector for Sles {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.offer.to_lowercase().starts_with("sles")
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("sles")
+    }
+
+    /// The service pack is part of the offer for recent images and part of
+    /// the SKU for the older ones, so both are considered together.
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(&format!("{}-{}", vm.offer, normalize_sku(&vm.sku)))
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // sles-15-sp5-gen2, sles-byos-12-sp4, sles-sap-15-sp4-byos-gen2, sles-15
+    let lower = az_version.to_lowercase();
+    let mut parts = lower
+        .split(['-', '_'])
+        .skip_while(|p| p.is_empty() || !p.chars().all(|c| c.is_ascii_digit()));
+    let major = parts.next()?;
+    match parts.next().and_then(|p| p.strip_prefix("sp")) {
+        Some(sp) if !sp.is_empty() && sp.chars().all(|c| c.is_ascii_digit()) => {
+            Some(format!("{}.{}", major, sp))
+        }
+        _ => Some(major.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_azure_version;
+
+    #[test]
+    fn test_service_pack() {
+        assert_eq!(
+            parse_azure_version("sles-15-sp5-gen2"),
+            Some(String::from("15.5"))
+        );
+        assert_eq!(
+            parse_azure_version("sles-byos-12-SP4"),
+            Some(String::from("12.4"))
+        );
+        assert_eq!(
+            parse_azure_version("sles-sap-15-sp4-byos-gen2"),
+            Some(String::from("15.4"))
+        );
+    }
+
+    #[test]
+    fn test_major() {
+        assert_eq!(parse_azure_version("sles-15"), Some(String::from("15")));
+        assert_eq!(parse_azure_version("sles-byos-gen2"), None);
+    }
+}
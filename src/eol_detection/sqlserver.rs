@@ -0,0 +1,152 @@
+use crate::eol_detection::detector::{cycles, eol_status, image_version, Detection, OsDetector};
+use crate::eol_detection::eol::{EOLEntity, EolData};
+use crate::eol_detection::windows;
+use crate::VMResult;
+
+pub struct SQLServer;
+
+impl OsDetector for SQLServer {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher.to_lowercase() == "microsoftsqlserver"
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("mssqlserver")
+    }
+
+    fn products(&self) -> Vec<&str> {
+        vec!["mssqlserver", "windowsserver"]
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(&vm.offer)
+    }
+
+    /// Evaluates both the SQL Server release and the Windows Server it ships
+    /// on, the verdict follows whichever reaches EOL first and the component
+    /// names it.
+    fn detect(&self, vm: &VMResult, eol_data: &EolData) -> Detection {
+        let version = match image_version(vm, self.parse_azure_version(vm)) {
+            Some(v) => v,
+            None => {
+                return Detection {
+                    status: "--".to_string(),
+                    classified: true,
+                    ..Default::default()
+                };
+            }
+        };
+        let sql_eol = sql_cycle(&version, eol_data);
+        let windows_version = parse_windows_version(&vm.offer);
+        let windows_eol = windows_version.as_ref().and_then(|v| {
+            cycles(Some("windowsserver"), eol_data)
+                .iter()
+                .find(|item| item.cycle.to_lowercase() == *v)
+        });
+
+        let (item, component): (&EOLEntity, String) = match (sql_eol, windows_eol) {
+            (Some(sql), Some(win)) if win.eol < sql.eol => (
+                win,
+                format!("Windows Server {}", windows_version.unwrap_or_default()),
+            ),
+            (Some(sql), _) => (sql, format!("SQL Server {}", version)),
+            (None, Some(win)) => (
+                win,
+                format!("Windows Server {}", windows_version.unwrap_or_default()),
+            ),
+            (None, None) => {
+                return Detection {
+                    version,
+                    status: "--".to_string(),
+                    classified: true,
+                    ..Default::default()
+                }
+            }
+        };
+        Detection {
+            version,
+            status: eol_status(item.eol),
+            component,
+            support_tier: String::new(),
+            source: item.override_source.clone().unwrap_or_default(),
+            classified: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Service packs are listed as separate cycles (2016-sp3), the latest one
+/// determines when the release as a whole runs out.
+fn sql_cycle<'a>(version: &str, eol_data: &'a EolData) -> Option<&'a EOLEntity> {
+    cycles(Some("mssqlserver"), eol_data)
+        .iter()
+        .filter(|item| {
+            item.cycle == version
+                || item.cycle.starts_with(&format!("{}-", version))
+                || item.cycle.starts_with(&format!("{} ", version))
+        })
+        .max_by_key(|item| item.eol)
+}
+
+/// Release and verdict of a SQL Server registered on a VM, given its SQL
+/// image offer (SQL2019-WS2022). The OS is judged by the VM's own detector.
+pub fn sql_server_status(sql_offer: &str, eol_data: &EolData) -> (String, String) {
+    let version = match parse_azure_version(sql_offer) {
+        Some(v) => v,
+        None => return (sql_offer.to_string(), "--".to_string()),
+    };
+    let status = match sql_cycle(&version, eol_data) {
+        Some(item) => eol_status(item.eol),
+        None => "--".to_string(),
+    };
+    (format!("SQL Server {}", version), status)
+}
+
+pub fn parse_azure_version(az_offer: &str) -> Option<String> {
+    // Examples:
+    // sql2019-ws2019, SQL2016SP2-WS2016, sql2014sp3-ws2012r2
+    let lower = az_offer.to_lowercase();
+    let sql = lower.split('-').next()?.strip_prefix("sql")?;
+    let year: String = sql.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if year.len() != 4 {
+        return None;
+    }
+    Some(year)
+}
+
+pub fn parse_windows_version(az_offer: &str) -> Option<String> {
+    let lower = az_offer.to_lowercase();
+    let ws = lower.split('-').find_map(|p| p.strip_prefix("ws"))?;
+    let sku = match ws.strip_suffix("r2") {
+        Some(year) => format!("{}-r2", year),
+        None => ws.to_string(),
+    };
+    windows::parse_azure_version(&sku)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_azure_version, parse_windows_version};
+
+    #[test]
+    fn test_sql_version() {
+        assert_eq!(
+            parse_azure_version("sql2019-ws2019"),
+            Some(String::from("2019"))
+        );
+        assert_eq!(
+            parse_azure_version("SQL2016SP2-WS2016"),
+            Some(String::from("2016"))
+        );
+        assert_eq!(parse_azure_version("WindowsServer"), None);
+    }
+
+    #[test]
+    fn test_windows_version() {
+        assert_eq!(
+            parse_windows_version("sql2022-ws2022"),
+            Some(String::from("2022"))
+        );
+        assert_eq!(parse_windows_version("sql2019"), None);
+    }
+}
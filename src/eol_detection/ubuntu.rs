@@ -1,44 +1,66 @@
-use crate::eol_detection::eol::{fetch_eol, EOLEntity};
+use crate::eol_detection::detector::{eol_status, find_cycle, image_version, OsDetector};
+use crate::eol_detection::eol::EolData;
+use crate::eol_detection::sku::normalize_sku;
 use crate::VMResult;
-use reqwest::Error;
 
-pub async fn list() -> Result<Vec<EOLEntity>, Error> {
-    fetch_eol("ubuntu").await
-}
+pub struct Ubuntu;
+
+impl OsDetector for Ubuntu {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.offer.to_lowercase().contains("ubuntu")
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("ubuntu")
+    }
 
-pub fn is_outdated(vm: &VMResult, eol_list: &Vec<EOLEntity>) -> String {
-    let version = match parse_azure_version(&vm.sku) {
-        Some(v) => v,
-        None => {
-            eprintln!("[ ERROR ] Parsing azure version falied for {:#?}", vm);
-            return "--".to_string();
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+
+    fn is_outdated(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        let version = match image_version(vm, self.parse_azure_version(vm)) {
+            Some(v) => v,
+            None => return "--".to_string(),
+        };
+        let item = match find_cycle(self.product(), &version, eol_data) {
+            Some(item) => item,
+            None => return "--".to_string(),
+        };
+        // Pro attached machines keep receiving security updates until ESM ends
+        match item.extended_support {
+            Some(esm) if is_pro(vm) => eol_status(esm),
+            _ => eol_status(item.eol),
         }
-    };
-    for item in eol_list {
-        if item.cycle == version {
-            let now = chrono::Utc::now().date_naive();
-            let future_eol = chrono::Utc::now()
-                .checked_add_months(chrono::Months::new(12))
-                .unwrap()
-                .date_naive();
-            if item.eol < now {
-                return "EOL".to_string();
-            } else if item.eol > now {
-                if item.eol < future_eol {
-                    return format!("Ending {}", item.eol);
-                }
-                return "Supported".to_string();
-            }
-            println!("Item matches: {:#?}", item);
+    }
+
+    fn support_tier(&self, vm: &VMResult, _eol_data: &EolData) -> String {
+        if is_pro(vm) {
+            String::from("Ubuntu Pro (ESM)")
+        } else {
+            String::from("Standard")
         }
     }
+}
 
-    "--".to_string()
+/// Pro images name it in the offer or SKU, captured images and specialized
+/// disks only in the purchase plan
+pub fn is_pro(vm: &VMResult) -> bool {
+    [&vm.offer, &vm.plan_product]
+        .iter()
+        .any(|offer| offer.to_lowercase().contains("ubuntu-pro"))
+        || [&vm.sku, &vm.plan_name]
+            .iter()
+            .any(|sku| sku.to_lowercase().starts_with("pro-"))
 }
 
-pub fn parse_azure_version(az_version: &String) -> Option<String> {
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
     // Examples:
-    // 18.04-LTS, 20_04-lts-gen2
+    // 18.04-LTS, 20_04-lts-gen2, pro-18_04-lts
+    let az_version = az_version
+        .strip_prefix("pro-")
+        .or_else(|| az_version.strip_prefix("PRO-"))
+        .unwrap_or(az_version);
     let parts: Vec<&str> = az_version.split("-").collect();
     if parts.is_empty() {
         return None;
@@ -51,3 +73,57 @@ pub fn parse_azure_version(az_version: &String) -> Option<String> {
     }
     Some(String::from(first))
 }
+
+#[cfg(test)]
+mod test {
+    use super::{is_pro, parse_azure_version};
+    use crate::VMResult;
+
+    #[test]
+    fn test_regular() {
+        assert_eq!(
+            parse_azure_version("18.04-LTS"),
+            Some(String::from("18.04"))
+        );
+        assert_eq!(
+            parse_azure_version("20_04-lts-gen2"),
+            Some(String::from("20.04"))
+        );
+    }
+
+    #[test]
+    fn test_pro() {
+        assert_eq!(
+            parse_azure_version("pro-18_04-lts"),
+            Some(String::from("18.04"))
+        );
+        assert_eq!(
+            parse_azure_version("pro-20_04-lts-gen2"),
+            Some(String::from("20.04"))
+        );
+    }
+
+    #[test]
+    fn test_is_pro() {
+        let marketplace = VMResult {
+            offer: String::from("0001-com-ubuntu-pro-jammy"),
+            sku: String::from("pro-22_04-lts-gen2"),
+            ..Default::default()
+        };
+        assert!(is_pro(&marketplace));
+        let captured = VMResult {
+            offer: String::from("ubuntu-golden"),
+            sku: String::from("22_04-lts"),
+            plan_product: String::from("0001-com-ubuntu-pro-jammy"),
+            plan_name: String::from("pro-22_04-lts-gen2"),
+            ..Default::default()
+        };
+        assert!(is_pro(&captured));
+        let standard = VMResult {
+            offer: String::from("0001-com-ubuntu-server-jammy"),
+            sku: String::from("22_04-lts-gen2"),
+            ..Default::default()
+        };
+        assert!(!is_pro(&standard));
+    }
+}
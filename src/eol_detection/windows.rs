@@ -1,47 +1,180 @@
-use crate::eol_detection::eol::{fetch_eol, EOLEntity};
+use crate::eol_detection::detector::OsDetector;
+use crate::eol_detection::sku::normalize_sku;
 use crate::VMResult;
-use reqwest::Error;
 
-pub async fn list() -> Result<Vec<EOLEntity>, Error> {
-    fetch_eol("windowsserver").await
+// Build numbers of the exact image version (17763.4252.230404) and the
+// release they belong to.
+const BUILDS: [(&str, &str); 15] = [
+    ("6002", "2008-sp2"),
+    ("7601", "2008-r2-sp1"),
+    ("9200", "2012"),
+    ("9600", "2012-r2"),
+    ("14393", "2016"),
+    ("16299", "1709"),
+    ("17134", "1803"),
+    ("17763", "2019"),
+    ("18362", "1903"),
+    ("18363", "1909"),
+    ("19041", "2004"),
+    ("19042", "20h2"),
+    ("20348", "2022"),
+    ("25398", "23h2"),
+    ("26100", "2025"),
+];
+
+pub struct WindowsServer {
+    /// Identify the release by the build number of the exact image version,
+    /// falls back to the SKU when the build is unknown
+    pub minor_versions: bool,
 }
 
-pub fn is_outdated(vm: &VMResult, eol_list: &Vec<EOLEntity>) -> String {
-    let version = match parse_azure_version(&vm.sku) {
-        Some(v) => v,
-        None => {
-            eprintln!("[ ERROR ] Parsing azure version falied for {:#?}", vm);
-            return "--".to_string();
+impl OsDetector for WindowsServer {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.offer.to_lowercase().contains("windows")
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("windowsserver")
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        if self.minor_versions {
+            if let Some(release) = parse_build(&vm.exact_version) {
+                return Some(release);
+            }
         }
-    };
-    for item in eol_list {
-        if item.cycle == version {
-            let now = chrono::Utc::now().date_naive();
-            let future_eol = chrono::Utc::now()
-                .checked_add_months(chrono::Months::new(12))
-                .unwrap()
-                .date_naive();
-            if item.eol < now {
-                return "EOL".to_string();
-            } else if item.eol > now {
-                if item.eol < future_eol {
-                    return format!("Ending {}", item.eol);
-                }
-                return "Supported".to_string();
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // 2012-R2-Datacenter, 2019-datacenter-core-smalldisk,
+    // 2022-datacenter-azure-edition-hotpatch, 2016-Datacenter-Server-Core,
+    // 2008-R2-SP1, datacenter-core-1809-with-containers-smalldisk, 23h2-datacenter-core
+    // endoflife.date cycles look like 2022, 2012-r2, 2008-r2-sp1, 1809 and 20h2.
+    let lower = az_version.to_lowercase();
+    let parts: Vec<&str> = lower.split(['-', '_']).collect();
+    let idx = parts
+        .iter()
+        .position(|p| is_release_year(p) || is_semi_annual(p))?;
+    let mut version = parts[idx].to_string();
+    if is_release_year(parts[idx]) {
+        let mut next = idx + 1;
+        for suffix in ["r2", "sp1", "sp2"] {
+            if parts.get(next) == Some(&suffix) {
+                version = format!("{}-{}", version, suffix);
+                next += 1;
             }
-            println!("Item matches: {:#?}", item);
         }
     }
+    Some(version)
+}
 
-    "--".to_string()
+/// Release of an exact image version by its build number
+pub fn parse_build(exact_version: &str) -> Option<String> {
+    let build = exact_version.split('.').next()?;
+    BUILDS
+        .iter()
+        .find(|(b, _)| *b == build)
+        .map(|(_, release)| release.to_string())
 }
 
-pub fn parse_azure_version(az_version: &String) -> Option<String> {
-    // Examples:
-    // 18.04-LTS, 20_04-lts-gen2
-    let parts: Vec<&str> = az_version.split("-").collect();
-    if parts.is_empty() {
-        return None;
+/// Long term servicing releases: 2008 up to 2025 and beyond
+fn is_release_year(part: &str) -> bool {
+    part.len() == 4 && part.chars().all(|c| c.is_ascii_digit()) && part >= "2008"
+}
+
+/// Semi-annual channel releases: 1709 .. 1909, 20h1, 23h2
+fn is_semi_annual(part: &str) -> bool {
+    if part.len() != 4 || !part.is_char_boundary(2) {
+        return false;
+    }
+    let (year, release) = part.split_at(2);
+    if !year.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    match release {
+        "h1" | "h2" => true,
+        _ => ("1709".."2000").contains(&part) && release.chars().all(|c| c.is_ascii_digit()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_azure_version, parse_build};
+
+    fn version(v: &str) -> Option<String> {
+        Some(String::from(v))
+    }
+
+    #[test]
+    fn test_datacenter() {
+        assert_eq!(parse_azure_version("2019-Datacenter"), version("2019"));
+        assert_eq!(
+            parse_azure_version("2019-datacenter-core-smalldisk"),
+            version("2019")
+        );
+        assert_eq!(
+            parse_azure_version("2016-Datacenter-Server-Core"),
+            version("2016")
+        );
+        assert_eq!(parse_azure_version("2022-datacenter-g2"), version("2022"));
+        assert_eq!(
+            parse_azure_version("2016-datacenter-gensecond"),
+            version("2016")
+        );
+    }
+
+    #[test]
+    fn test_azure_edition() {
+        assert_eq!(
+            parse_azure_version("2022-datacenter-azure-edition-hotpatch"),
+            version("2022")
+        );
+        assert_eq!(
+            parse_azure_version("2025-datacenter-azure-edition"),
+            version("2025")
+        );
+    }
+
+    #[test]
+    fn test_r2() {
+        assert_eq!(
+            parse_azure_version("2012-R2-Datacenter"),
+            version("2012-r2")
+        );
+        assert_eq!(
+            parse_azure_version("2012-r2-datacenter-smalldisk-g2"),
+            version("2012-r2")
+        );
+        assert_eq!(parse_azure_version("2008-R2-SP1"), version("2008-r2-sp1"));
+        assert_eq!(
+            parse_azure_version("2008-R2-SP1-smalldisk"),
+            version("2008-r2-sp1")
+        );
+        assert_eq!(parse_azure_version("2012-Datacenter"), version("2012"));
+    }
+
+    #[test]
+    fn test_semi_annual() {
+        assert_eq!(
+            parse_azure_version("datacenter-core-1809-with-containers-smalldisk"),
+            version("1809")
+        );
+        assert_eq!(
+            parse_azure_version("datacenter-core-20h2-with-containers-smalldisk-gen2"),
+            version("20h2")
+        );
+        assert_eq!(parse_azure_version("23h2-datacenter-core"), version("23h2"));
+        assert_eq!(parse_azure_version("Datacenter"), None);
+    }
+
+    #[test]
+    fn test_build() {
+        assert_eq!(parse_build("17763.4252.230404"), version("2019"));
+        assert_eq!(parse_build("9600.21013.230404"), version("2012-r2"));
+        assert_eq!(parse_build("20348.1726.230505"), version("2022"));
+        assert_eq!(parse_build("latest"), None);
     }
-    return Some(parts[0].to_string());
 }
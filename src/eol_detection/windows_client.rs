@@ -0,0 +1,166 @@
+use crate::eol_detection::detector::{
+    cycles, eol_status, image_version, support_phase, OsDetector,
+};
+use crate::eol_detection::eol::{EOLEntity, EolData};
+use crate::eol_detection::sku::normalize_sku;
+use crate::VMResult;
+
+pub struct WindowsClient;
+
+impl WindowsClient {
+    /// The cycle of the edition, else the one all editions share. Some
+    /// feature updates have a single cycle (e.g. 10-22h2).
+    fn cycle<'a>(&self, vm: &VMResult, eol_data: &'a EolData) -> Option<&'a EOLEntity> {
+        let version = self.parse_azure_version(vm)?;
+        let generic = version
+            .strip_suffix("-e")
+            .or_else(|| version.strip_suffix("-w"))
+            .unwrap_or(&version);
+        let eol_list = cycles(self.product(), eol_data);
+        eol_list
+            .iter()
+            .find(|item| item.cycle == version)
+            .or_else(|| eol_list.iter().find(|item| item.cycle == generic))
+    }
+}
+
+impl OsDetector for WindowsClient {
+    fn matches(&self, vm: &VMResult) -> bool {
+        vm.publisher.to_lowercase() == "microsoftwindowsdesktop"
+    }
+
+    fn product(&self) -> Option<&str> {
+        Some("windows")
+    }
+
+    fn parse_azure_version(&self, vm: &VMResult) -> Option<String> {
+        parse_azure_version(normalize_sku(&vm.sku))
+    }
+
+    fn is_outdated(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        if image_version(vm, self.parse_azure_version(vm)).is_none() {
+            return "--".to_string();
+        }
+        self.cycle(vm, eol_data)
+            .map(|item| eol_status(item.eol))
+            .unwrap_or_else(|| "--".to_string())
+    }
+
+    // The defaults would look up the edition's cycle only and record the
+    // shared ones as near-misses
+    fn phase(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        self.cycle(vm, eol_data)
+            .map(|item| support_phase(item, chrono::Utc::now().date_naive()))
+            .unwrap_or_default()
+    }
+
+    fn eol_source(&self, vm: &VMResult, eol_data: &EolData) -> String {
+        self.cycle(vm, eol_data)
+            .and_then(|item| item.override_source.clone())
+            .unwrap_or_default()
+    }
+}
+
+pub fn parse_azure_version(az_version: &str) -> Option<String> {
+    // Examples:
+    // win10-22h2-pro, win11-23h2-ent, win10-22h2-avd, 20h2-pro, rs5-enterprise
+    // endoflife.date cycles look like 11-23h2-e (Enterprise/Education) and
+    // 11-23h2-w (Home/Pro), LTSC releases as 10-21h2-e-lts.
+    let lower = az_version.to_lowercase();
+    let mut parts: Vec<&str> = lower.split('-').collect();
+    let major = match parts[0].strip_prefix("win") {
+        Some(major) => {
+            parts.remove(0);
+            major.to_string()
+        }
+        None => String::from("10"),
+    };
+    if major.is_empty() || parts.is_empty() {
+        return None;
+    }
+    let release = match parts[0] {
+        "rs1" => "1607",
+        "rs2" => "1703",
+        "rs3" => "1709",
+        "rs4" => "1803",
+        "rs5" => "1809",
+        r => r,
+    };
+    if release.len() != 4 {
+        return None;
+    }
+    let editions = &parts[1..];
+    let enterprise = editions.iter().any(|e| {
+        e.starts_with("ent") || e.starts_with("avd") || e.starts_with("edu") || *e == "evd"
+    });
+    let mut cycle = format!(
+        "{}-{}-{}",
+        major,
+        release,
+        if enterprise { "e" } else { "w" }
+    );
+    if editions
+        .iter()
+        .any(|e| e.starts_with("ltsc") || *e == "ltsb")
+    {
+        cycle = format!("{}-{}-e-lts", major, release);
+    }
+    Some(cycle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_azure_version, WindowsClient};
+    use crate::eol_detection::detector::OsDetector;
+    use crate::eol_detection::eol::EolData;
+    use crate::VMResult;
+
+    #[test]
+    fn test_shared_cycle() {
+        let eol_data: EolData = serde_json::from_str(
+            r#"{"windows": [{"cycle": "10-22h2", "support": "2025-10-14", "eol": "2025-10-14"}]}"#,
+        )
+        .unwrap();
+        let vm = VMResult {
+            publisher: String::from("MicrosoftWindowsDesktop"),
+            sku: String::from("win10-22h2-pro"),
+            ..Default::default()
+        };
+        // The verdict and the phase come from the same cycle
+        assert_eq!(WindowsClient.is_outdated(&vm, &eol_data), "EOL");
+        assert_eq!(WindowsClient.phase(&vm, &eol_data), "EOL");
+    }
+
+    #[test]
+    fn test_editions() {
+        assert_eq!(
+            parse_azure_version("win10-22h2-pro"),
+            Some(String::from("10-22h2-w"))
+        );
+        assert_eq!(
+            parse_azure_version("win11-23h2-ent"),
+            Some(String::from("11-23h2-e"))
+        );
+        assert_eq!(
+            parse_azure_version("win11-22h2-avd"),
+            Some(String::from("11-22h2-e"))
+        );
+    }
+
+    #[test]
+    fn test_legacy_skus() {
+        assert_eq!(
+            parse_azure_version("20h2-pro"),
+            Some(String::from("10-20h2-w"))
+        );
+        assert_eq!(
+            parse_azure_version("rs5-enterprise"),
+            Some(String::from("10-1809-e"))
+        );
+        assert_eq!(
+            parse_azure_version("win10-21h2-entn-ltsc"),
+            Some(String::from("10-21h2-e-lts"))
+        );
+        assert_eq!(parse_azure_version("office-365"), None);
+    }
+}
@@ -0,0 +1,428 @@
+use crate::logging::warn;
+use async_trait::async_trait;
+use azure_core::error::ErrorKind;
+use azure_core::headers::RETRY_AFTER;
+use azure_core::{HttpClient, Request, Response, TransportOptions};
+use chrono::{DateTime, Utc};
+use reqwest::RequestBuilder;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+// Exponential backoff is capped here, Retry-After is honored as sent
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How throttled (429) and transiently failing (408, 5xx) requests are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryOptions {
+    /// Attempts per request including the first one
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub backoff: Duration,
+}
+
+impl Default for RetryOptions {
+    fn default() -> Self {
+        RetryOptions {
+            attempts: 5,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryOptions {
+    /// Delay before retry `retry` (1-based), Retry-After takes precedence
+    fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after.unwrap_or_else(|| {
+            self.backoff
+                .saturating_mul(2u32.saturating_pow(retry - 1))
+                .min(MAX_BACKOFF)
+        })
+    }
+}
+
+// Requests in flight at once while ARM reports plenty of quota left
+const MAX_CONCURRENCY: usize = 16;
+
+// Below this many remaining reads concurrency is scaled down
+const LOW_QUOTA: u32 = 200;
+
+/// Limits the requests in flight across all clients, lowered as the
+/// x-ms-ratelimit-remaining-* headers show the read quota running out
+struct Throttle {
+    limit: AtomicUsize,
+    in_flight: AtomicUsize,
+    released: Notify,
+}
+
+struct Permit<'a> {
+    throttle: &'a Throttle,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.throttle.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.throttle.released.notify_waiters();
+    }
+}
+
+impl Throttle {
+    async fn acquire(&self) -> Permit<'_> {
+        loop {
+            // Created before checking so a release in between isn't missed
+            let released = self.released.notified();
+            let in_flight = self.in_flight.load(Ordering::SeqCst);
+            if in_flight < self.limit.load(Ordering::SeqCst)
+                && self
+                    .in_flight
+                    .compare_exchange(in_flight, in_flight + 1, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            {
+                return Permit { throttle: self };
+            }
+            released.await;
+        }
+    }
+
+    fn observe<'a>(&self, headers: impl Iterator<Item = (&'a str, &'a str)>) {
+        let remaining = match remaining_reads(headers) {
+            Some(remaining) => remaining,
+            None => return,
+        };
+        let limit = concurrency_for(remaining);
+        let previous = self.limit.swap(limit, Ordering::SeqCst);
+        if limit < previous && limit < MAX_CONCURRENCY {
+            warn!(
+                "ARM read quota low ({} left), slowing down to {} concurrent requests",
+                remaining, limit
+            );
+        }
+        if limit > previous {
+            self.released.notify_waiters();
+        }
+    }
+}
+
+static THROTTLE: Throttle = Throttle {
+    limit: AtomicUsize::new(MAX_CONCURRENCY),
+    in_flight: AtomicUsize::new(0),
+    released: Notify::const_new(),
+};
+
+/// Lowest quota of the x-ms-ratelimit-remaining-*-reads headers and of
+/// x-ms-ratelimit-remaining-resource (`Microsoft.Compute/HighCostGet3Min;107,...`)
+fn remaining_reads<'a>(headers: impl Iterator<Item = (&'a str, &'a str)>) -> Option<u32> {
+    headers
+        .filter_map(|(name, value)| {
+            let name = name.to_ascii_lowercase();
+            if name == "x-ms-ratelimit-remaining-resource" {
+                value
+                    .split(',')
+                    .filter_map(|policy| policy.rsplit(';').next()?.trim().parse().ok())
+                    .min()
+            } else if name.starts_with("x-ms-ratelimit-remaining-") && name.ends_with("-reads") {
+                value.trim().parse().ok()
+            } else {
+                None
+            }
+        })
+        .min()
+}
+
+/// Full concurrency with plenty of quota left, down to one request at a time
+/// as it runs out
+fn concurrency_for(remaining: u32) -> usize {
+    if remaining >= LOW_QUOTA {
+        return MAX_CONCURRENCY;
+    }
+    (remaining as usize * MAX_CONCURRENCY / LOW_QUOTA as usize).max(1)
+}
+
+static RETRY: OnceLock<RetryOptions> = OnceLock::new();
+
+static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+static PROXY: OnceLock<reqwest::Proxy> = OnceLock::new();
+
+static CA_CERTIFICATES: OnceLock<Vec<reqwest::Certificate>> = OnceLock::new();
+
+/// Sets the policy used by every client, only the first call has an effect
+pub fn set_retry(options: RetryOptions) {
+    _ = RETRY.set(options);
+}
+
+fn retry_options() -> RetryOptions {
+    RETRY.get().copied().unwrap_or_default()
+}
+
+/// Sets the per request timeout of clients created afterwards, only the
+/// first call has an effect
+pub fn set_timeout(timeout: Duration) {
+    _ = TIMEOUT.set(timeout);
+}
+
+/// Sends every request through `url`, only the first call has an effect.
+/// Without it reqwest picks up HTTPS_PROXY/HTTP_PROXY/NO_PROXY itself.
+pub fn set_proxy(url: &str) -> reqwest::Result<()> {
+    _ = PROXY.set(reqwest::Proxy::all(url)?);
+    Ok(())
+}
+
+/// Trusts the certificates in a PEM bundle on top of the system store, e.g.
+/// the root of a TLS-intercepting proxy. Only the first call has an effect.
+pub fn set_ca_bundle(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let pem = std::fs::read_to_string(path)?;
+    let certificates = pem_certificates(&pem)
+        .iter()
+        .map(|c| reqwest::Certificate::from_pem(c.as_bytes()))
+        .collect::<reqwest::Result<Vec<_>>>()?;
+    if certificates.is_empty() {
+        return Err(format!("no certificates found in {}", path.display()).into());
+    }
+    _ = CA_CERTIFICATES.set(certificates);
+    Ok(())
+}
+
+/// Splits a bundle into its certificates, the native TLS backend only reads
+/// the first one of a PEM blob
+pub fn pem_certificates(pem: &str) -> Vec<String> {
+    const END: &str = "-----END CERTIFICATE-----";
+    pem.split_inclusive(END)
+        .filter_map(|block| {
+            let start = block.find("-----BEGIN CERTIFICATE-----")?;
+            block.ends_with(END).then(|| block[start..].to_string())
+        })
+        .collect()
+}
+
+/// reqwest client with the configured timeout, proxy and CA certificates,
+/// used for every request
+pub fn client() -> reqwest::Client {
+    let mut builder = reqwest::Client::builder();
+    if let Some(timeout) = TIMEOUT.get() {
+        builder = builder.timeout(*timeout);
+    }
+    if let Some(proxy) = PROXY.get() {
+        builder = builder.proxy(proxy.clone());
+    }
+    for certificate in CA_CERTIFICATES.get().into_iter().flatten() {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    builder.build().unwrap_or_default()
+}
+
+fn is_transient(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// Retry-After is either a number of seconds or an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Parses `500ms`, `30s` or `2m`, a bare number is taken as seconds
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let lower = s.trim().to_lowercase();
+    let digits: String = lower.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration '{}', expected e.g. 30s or 2m", s))?;
+    match lower[digits.len()..].trim() {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" | "sec" | "secs" => Ok(Duration::from_secs(value)),
+        "m" | "min" | "mins" => Ok(Duration::from_secs(value * 60)),
+        "h" | "hour" | "hours" => Ok(Duration::from_secs(value * 3600)),
+        "d" | "day" | "days" => Ok(Duration::from_secs(value * 86400)),
+        _ => Err(format!("invalid duration '{}', expected e.g. 30s or 2m", s)),
+    }
+}
+
+/// Sends a reqwest request, retrying throttled and transient failures.
+/// Requests with a streamed body can't be cloned and are sent once.
+pub async fn send(request: RequestBuilder) -> reqwest::Result<reqwest::Response> {
+    let options = retry_options();
+    let mut retry = 0;
+    loop {
+        let attempt = match request.try_clone() {
+            Some(attempt) if retry + 1 < options.attempts => attempt,
+            _ => {
+                let _permit = THROTTLE.acquire().await;
+                return request.send().await;
+            }
+        };
+        retry += 1;
+        let permit = THROTTLE.acquire().await;
+        let response = attempt.send().await;
+        drop(permit);
+        if let Ok(response) = &response {
+            THROTTLE.observe(
+                response
+                    .headers()
+                    .iter()
+                    .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+            );
+        }
+        let retry_after = match response {
+            Ok(response) if is_transient(response.status().as_u16()) => {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after);
+                warn!(
+                    "{} returned {}, retrying",
+                    response.url(),
+                    response.status()
+                );
+                retry_after
+            }
+            Ok(response) => return Ok(response),
+            Err(e) if e.is_connect() || e.is_timeout() => {
+                warn!("Request failed, retrying: {}", e);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        tokio::time::sleep(options.delay(retry, retry_after)).await;
+    }
+}
+
+/// Transport for the Azure SDK clients applying the same policy, their own
+/// retries are turned off as they ignore Retry-After.
+#[derive(Debug)]
+struct RetryingClient {
+    http: reqwest::Client,
+}
+
+#[async_trait]
+impl HttpClient for RetryingClient {
+    async fn execute_request(&self, request: &Request) -> azure_core::Result<Response> {
+        let options = retry_options();
+        let mut retry = 0;
+        loop {
+            let permit = THROTTLE.acquire().await;
+            let response = self.http.execute_request(request).await;
+            drop(permit);
+            if let Ok(response) = &response {
+                THROTTLE.observe(
+                    response
+                        .headers()
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.as_str())),
+                );
+            }
+            retry += 1;
+            if retry >= options.attempts {
+                return response;
+            }
+            let retry_after = match &response {
+                Ok(r) if is_transient(u16::from(r.status())) => {
+                    warn!("{} returned {}, retrying", request.url(), r.status());
+                    r.headers()
+                        .get_optional_str(&RETRY_AFTER)
+                        .and_then(parse_retry_after)
+                }
+                Err(e) if e.kind() == &ErrorKind::Io => {
+                    warn!("Request failed, retrying: {}", e);
+                    None
+                }
+                _ => return response,
+            };
+            tokio::time::sleep(options.delay(retry, retry_after)).await;
+        }
+    }
+}
+
+pub fn transport() -> TransportOptions {
+    TransportOptions::new(Arc::new(RetryingClient { http: client() }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        concurrency_for, parse_duration, parse_retry_after, pem_certificates, remaining_reads,
+        RetryOptions, MAX_CONCURRENCY,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_delay() {
+        let options = RetryOptions::default();
+        assert_eq!(options.delay(1, None), Duration::from_secs(1));
+        assert_eq!(options.delay(3, None), Duration::from_secs(4));
+        assert_eq!(options.delay(10, None), Duration::from_secs(60));
+        assert_eq!(
+            options.delay(1, Some(Duration::from_secs(90))),
+            Duration::from_secs(90)
+        );
+    }
+
+    #[test]
+    fn test_retry_after() {
+        assert_eq!(parse_retry_after("17"), Some(Duration::from_secs(17)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("24h"), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(604800)));
+        assert!(parse_duration("soon").is_err());
+    }
+
+    #[test]
+    fn test_pem_certificates() {
+        let bundle = "# proxy root\n-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n\
+            -----BEGIN CERTIFICATE-----\nMIIC\n-----END CERTIFICATE-----\n";
+        let certificates = pem_certificates(bundle);
+        assert_eq!(certificates.len(), 2);
+        assert_eq!(
+            certificates[0],
+            "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----"
+        );
+        assert!(pem_certificates("no certificates").is_empty());
+    }
+
+    #[test]
+    fn test_remaining_reads() {
+        let headers = [
+            ("x-ms-ratelimit-remaining-subscription-reads", "11993"),
+            (
+                "x-ms-ratelimit-remaining-resource",
+                "Microsoft.Compute/HighCostGet3Min;107,Microsoft.Compute/HighCostGet30Min;677",
+            ),
+            ("content-type", "application/json"),
+        ];
+        assert_eq!(remaining_reads(headers.into_iter()), Some(107));
+        assert_eq!(
+            remaining_reads([("x-ms-request-id", "1")].into_iter()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_concurrency_for() {
+        assert_eq!(concurrency_for(11993), MAX_CONCURRENCY);
+        assert_eq!(concurrency_for(100), MAX_CONCURRENCY / 2);
+        assert_eq!(concurrency_for(0), 1);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::inventory::Inventory;
+use crate::VMResult;
+use azure_mgmt_compute::models::os_disk::OsType;
+use serde::Deserialize;
+
+/// Publisher set on cluster and node pool results
+pub const PUBLISHER: &str = "AKS";
+
+const API_VERSION: &str = "2023-08-01";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedCluster {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub properties: ClusterProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterProperties {
+    pub kubernetes_version: Option<String>,
+    pub current_kubernetes_version: Option<String>,
+    pub support_plan: Option<String>,
+    #[serde(default)]
+    pub agent_pool_profiles: Vec<AgentPool>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentPool {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub count: i64,
+    pub os_type: Option<String>,
+    #[serde(rename = "osSKU")]
+    pub os_sku: Option<String>,
+    pub node_image_version: Option<String>,
+    pub orchestrator_version: Option<String>,
+    pub current_orchestrator_version: Option<String>,
+}
+
+/// One row per node pool, the nodes themselves live in the managed node
+/// resource group as scale sets but are not listed from there. Every cluster
+/// gets a row of its own for the Kubernetes version.
+pub async fn list_clusters(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.ContainerService/managedClusters?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let clusters = match inventory.arm.list::<ManagedCluster>(&path).await {
+        Ok(clusters) => clusters,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "AKS clusters", &*e);
+            return;
+        }
+    };
+    for cluster in clusters {
+        inventory
+            .send(kubernetes_result(&cluster, subscription_id))
+            .await;
+        for pool in cluster.properties.agent_pool_profiles {
+            let node_image = pool.node_image_version.unwrap_or_default();
+            let os_sku = pool.os_sku.unwrap_or_default();
+            let (offer, sku) = node_image_os(&node_image, &os_sku).unwrap_or_default();
+            let os_type = match pool.os_type.as_deref() {
+                Some(t) if t.eq_ignore_ascii_case("windows") => Some(OsType::Windows),
+                Some(t) if t.eq_ignore_ascii_case("linux") => Some(OsType::Linux),
+                _ => None,
+            };
+            let machine = VMResult {
+                id: format!("{}/agentPools/{}", cluster.id, pool.name),
+                resource_type: String::from("AKS node pool"),
+                instances: pool.count,
+                scale_set: cluster.id.clone(),
+                location: cluster.location.clone(),
+                subscription_id: subscription_id.clone(),
+                publisher: String::from(PUBLISHER),
+                offer,
+                sku,
+                version: os_sku,
+                exact_version: node_image,
+                os_type,
+                // The node image isn't a marketplace image that can be looked up
+                azure_deprecation: String::from("--"),
+                ..Default::default()
+            };
+            inventory.send(machine).await;
+        }
+    }
+}
+
+/// The cluster is as supported as its oldest Kubernetes version, node pools
+/// can lag behind the control plane.
+fn kubernetes_result(cluster: &ManagedCluster, subscription_id: &str) -> VMResult {
+    let properties = &cluster.properties;
+    let control_plane = properties
+        .current_kubernetes_version
+        .clone()
+        .or_else(|| properties.kubernetes_version.clone())
+        .unwrap_or_default();
+    let oldest = properties
+        .agent_pool_profiles
+        .iter()
+        .filter_map(|pool| {
+            pool.current_orchestrator_version
+                .as_ref()
+                .or(pool.orchestrator_version.as_ref())
+        })
+        .chain(std::iter::once(&control_plane))
+        .filter(|v| !v.is_empty())
+        .min_by_key(|v| version_key(v))
+        .cloned()
+        .unwrap_or_default();
+    VMResult {
+        id: cluster.id.clone(),
+        resource_type: String::from("AKS cluster"),
+        instances: properties.agent_pool_profiles.iter().map(|p| p.count).sum(),
+        location: cluster.location.clone(),
+        subscription_id: subscription_id.to_string(),
+        publisher: String::from(PUBLISHER),
+        offer: String::from("kubernetes"),
+        sku: oldest,
+        version: control_plane,
+        support_plan: properties.support_plan.clone().unwrap_or_default(),
+        azure_deprecation: String::from("--"),
+        ..Default::default()
+    }
+}
+
+fn version_key(version: &str) -> Vec<u32> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|p| p.parse().unwrap_or_default())
+        .collect()
+}
+
+/// Translates a node image version into an offer and SKU the regular
+/// detectors understand, falling back to the pool's OS SKU.
+///
+/// AKSUbuntu-2204gen2containerd-202310.04.0 -> (ubuntu, 22_04)
+/// AKSCBLMariner-V2gen2-202310.09.0 -> (azure-linux, azure-linux-2)
+/// AKSWindows-2019-containerd-17763.4974.231011 -> (windows, 2019-datacenter)
+pub fn node_image_os(node_image: &str, os_sku: &str) -> Option<(String, String)> {
+    let lower = node_image.to_lowercase();
+    let mut parts = lower.split('-');
+    let family = parts.next().unwrap_or_default();
+    let release = parts.next().unwrap_or_default();
+    let digits: String = release
+        .trim_start_matches('v')
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    let os_sku = os_sku.to_lowercase();
+    if family == "aksubuntu" || (family.is_empty() && os_sku.starts_with("ubuntu")) {
+        let digits = if digits.is_empty() {
+            os_sku.trim_start_matches("ubuntu").to_string()
+        } else {
+            digits
+        };
+        if digits.len() != 4 {
+            return None;
+        }
+        let (major, minor) = digits.split_at(2);
+        return Some((String::from("ubuntu"), format!("{}_{}", major, minor)));
+    }
+    if family == "akscblmariner" || family == "aksazurelinux" {
+        if digits.is_empty() {
+            return None;
+        }
+        return Some((
+            String::from("azure-linux"),
+            format!("azure-linux-{}", digits),
+        ));
+    }
+    if family == "akswindows" || (family.is_empty() && os_sku.starts_with("windows")) {
+        let year = if digits.is_empty() {
+            os_sku.trim_start_matches("windows").to_string()
+        } else {
+            digits
+        };
+        if year.len() != 4 {
+            return None;
+        }
+        return Some((String::from("windows"), format!("{}-datacenter", year)));
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::{kubernetes_result, node_image_os, ManagedCluster};
+
+    fn os(offer: &str, sku: &str) -> Option<(String, String)> {
+        Some((offer.to_string(), sku.to_string()))
+    }
+
+    #[test]
+    fn test_node_images() {
+        assert_eq!(
+            node_image_os("AKSUbuntu-2204gen2containerd-202310.04.0", "Ubuntu"),
+            os("ubuntu", "22_04")
+        );
+        assert_eq!(
+            node_image_os("AKSUbuntu-1804gen2containerd-2023.01.10", "Ubuntu"),
+            os("ubuntu", "18_04")
+        );
+        assert_eq!(
+            node_image_os("AKSCBLMariner-V2gen2-202310.09.0", "CBLMariner"),
+            os("azure-linux", "azure-linux-2")
+        );
+        assert_eq!(
+            node_image_os("AKSAzureLinux-V3gen2-202409.23.0", "AzureLinux"),
+            os("azure-linux", "azure-linux-3")
+        );
+        assert_eq!(
+            node_image_os(
+                "AKSWindows-2019-containerd-17763.4974.231011",
+                "Windows2019"
+            ),
+            os("windows", "2019-datacenter")
+        );
+    }
+
+    #[test]
+    fn test_os_sku_fallback() {
+        assert_eq!(node_image_os("", "Ubuntu2204"), os("ubuntu", "22_04"));
+        assert_eq!(
+            node_image_os("", "Windows2022"),
+            os("windows", "2022-datacenter")
+        );
+        assert_eq!(node_image_os("", "Ubuntu"), None);
+    }
+
+    #[test]
+    fn test_oldest_kubernetes_version() {
+        let cluster: ManagedCluster = serde_json::from_str(
+            r#"{"id": "aks", "properties": {
+                "currentKubernetesVersion": "1.28.3",
+                "agentPoolProfiles": [
+                    {"name": "system", "count": 3, "currentOrchestratorVersion": "1.28.3"},
+                    {"name": "user", "count": 2, "orchestratorVersion": "1.9.11"}
+                ]
+            }}"#,
+        )
+        .unwrap();
+        let result = kubernetes_result(&cluster, "sub");
+        assert_eq!(result.version, "1.28.3");
+        assert_eq!(result.sku, "1.9.11");
+        assert_eq!(result.instances, 5);
+    }
+}
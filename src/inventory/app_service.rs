@@ -0,0 +1,364 @@
+use crate::inventory::arm::ArmError;
+use crate::inventory::Inventory;
+use crate::logging::error;
+use crate::VMResult;
+use futures::stream::StreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Publisher set on App Service results
+pub const PUBLISHER: &str = "Microsoft.Web";
+
+const API_VERSION: &str = "2022-03-01";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Site {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub kind: String,
+    #[serde(default)]
+    pub location: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteConfig {
+    pub linux_fx_version: Option<String>,
+    pub net_framework_version: Option<String>,
+    pub php_version: Option<String>,
+    pub python_version: Option<String>,
+    pub node_version: Option<String>,
+    pub power_shell_version: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Resource<T> {
+    #[serde(default)]
+    properties: T,
+}
+
+/// One row per web app with a runtime endoflife.date tracks, apps running a
+/// custom container or Java are skipped. Function Apps get a row each.
+pub async fn list_sites(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.Web/sites?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let sites = match inventory.arm.list::<Site>(&path).await {
+        Ok(sites) => sites,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "web apps", &*e);
+            return;
+        }
+    };
+    futures::stream::iter(sites)
+        .for_each_concurrent(10, |site| async move {
+            let config = match site_config(inventory, &site.id).await {
+                Ok(config) => config,
+                Err(e) => {
+                    error!("Fetching site config of {} failed: {}", site.id, e);
+                    return;
+                }
+            };
+            let machine = if site.kind.contains("functionapp") {
+                Some(function_app(inventory, site, config).await)
+            } else {
+                web_app(inventory, site, config).await
+            };
+            if let Some(mut machine) = machine {
+                machine.subscription_id = subscription_id.clone();
+                inventory.send(machine).await;
+            }
+        })
+        .await;
+}
+
+async fn web_app(inventory: &Inventory, site: Site, config: SiteConfig) -> Option<VMResult> {
+    // Windows apps only name their stack in the portal metadata, which
+    // takes more than Reader to list
+    let windows = config
+        .linux_fx_version
+        .as_deref()
+        .unwrap_or_default()
+        .is_empty();
+    let stack = if windows {
+        current_stack(inventory, &site.id)
+            .await
+            .or_else(|| inferred_stack(&config).map(String::from))
+    } else {
+        None
+    };
+    let (offer, sku) = match runtime(&config, stack.as_deref()) {
+        Some(runtime) => runtime,
+        // Reported with the runtime unknown rather than left out
+        None if windows && stack.is_none() => (String::new(), String::new()),
+        None => return None,
+    };
+    Some(VMResult {
+        id: site.id,
+        resource_type: String::from("App Service"),
+        location: site.location,
+        publisher: String::from(PUBLISHER),
+        version: stack.or(config.linux_fx_version).unwrap_or_default(),
+        offer,
+        sku,
+        azure_deprecation: String::from("--"),
+        ..Default::default()
+    })
+}
+
+async fn function_app(inventory: &Inventory, site: Site, config: SiteConfig) -> VMResult {
+    // Only the app settings name the host version, listing them takes more
+    // than Reader. The language runtime comes from the site config.
+    let path = format!(
+        "{}/config/appsettings/list?api-version={}",
+        site.id, API_VERSION
+    );
+    let settings = match inventory
+        .arm
+        .post::<Resource<HashMap<String, String>>>(&path)
+        .await
+    {
+        Ok(settings) => settings.properties,
+        Err(e) => {
+            error!("Fetching app settings of {} failed: {}", site.id, e);
+            HashMap::new()
+        }
+    };
+    let language = function_runtime(&config)
+        .map(|(offer, version)| format!("{}|{}", offer, version))
+        .unwrap_or_default();
+    VMResult {
+        id: site.id,
+        resource_type: String::from("Function App"),
+        location: site.location,
+        publisher: String::from(PUBLISHER),
+        offer: String::from("functions"),
+        sku: settings
+            .get("FUNCTIONS_EXTENSION_VERSION")
+            .cloned()
+            .unwrap_or_default(),
+        version: language,
+        azure_deprecation: String::from("--"),
+        ..Default::default()
+    }
+}
+
+async fn site_config(inventory: &Inventory, site_id: &str) -> Result<SiteConfig, ArmError> {
+    let path = format!("{}/config/web?api-version={}", site_id, API_VERSION);
+    let config: Resource<SiteConfig> = inventory.arm.get(&path).await?;
+    Ok(config.properties)
+}
+
+async fn current_stack(inventory: &Inventory, site_id: &str) -> Option<String> {
+    let path = format!(
+        "{}/config/metadata/list?api-version={}",
+        site_id, API_VERSION
+    );
+    match inventory
+        .arm
+        .post::<Resource<HashMap<String, Value>>>(&path)
+        .await
+    {
+        Ok(metadata) => metadata
+            .properties
+            .get("CURRENT_STACK")
+            .and_then(Value::as_str)
+            .map(str::to_lowercase),
+        Err(e) => {
+            error!("Fetching metadata of {} failed: {}", site_id, e);
+            None
+        }
+    }
+}
+
+/// Stack of a Windows app without metadata, when exactly one of the PHP,
+/// Python and Node versions is set. `netFrameworkVersion` is set on every
+/// app, whatever it runs.
+pub fn inferred_stack(config: &SiteConfig) -> Option<&'static str> {
+    let stacks: Vec<&str> = [
+        ("php", &config.php_version),
+        ("python", &config.python_version),
+        ("node", &config.node_version),
+    ]
+    .into_iter()
+    .filter(|(_, version)| !version.as_deref().unwrap_or_default().is_empty())
+    .map(|(stack, _)| stack)
+    .collect();
+    match stacks[..] {
+        [stack] => Some(stack),
+        _ => None,
+    }
+}
+
+/// The runtime (offer) and its version (SKU) an app runs on, from
+/// `linuxFxVersion` (`PHP|8.2`, `NODE|18-lts`) on Linux or the stack's own
+/// version setting on Windows. Windows apps of an unknown stack have none.
+pub fn runtime(config: &SiteConfig, current_stack: Option<&str>) -> Option<(String, String)> {
+    let (stack, version) = match config.linux_fx_version.as_deref() {
+        Some(fx) if !fx.is_empty() => {
+            let (stack, version) = fx.split_once('|')?;
+            (stack.to_lowercase(), version.to_string())
+        }
+        _ => {
+            let stack = current_stack?;
+            let version = match stack {
+                "php" => config.php_version.clone(),
+                "python" => config.python_version.clone(),
+                "node" => config.node_version.clone(),
+                "dotnet" => config.net_framework_version.clone(),
+                _ => None,
+            };
+            (stack.to_string(), version.unwrap_or_default())
+        }
+    };
+    if version.is_empty() {
+        return None;
+    }
+    let offer = match stack.as_str() {
+        "php" | "node" | "python" => stack,
+        "dotnetcore" => String::from("dotnet"),
+        // v4.0 is the CLR version, the sites run the latest 4.8.x
+        "dotnet" if version.eq_ignore_ascii_case("v4.0") => {
+            return Some((String::from("dotnetfx"), String::from("4.8")))
+        }
+        "dotnet" => String::from("dotnet"),
+        _ => return None,
+    };
+    Some((offer, version))
+}
+
+/// The language runtime (offer) and its version of a Function App, from
+/// `linuxFxVersion` (`Python|3.11`) on Linux or whichever of the PowerShell,
+/// Node and .NET versions is set on Windows. `v4.0` is the .NET Framework
+/// default every Windows app carries, it doesn't tell the worker runtime.
+pub fn function_runtime(config: &SiteConfig) -> Option<(String, String)> {
+    let set = |version: &Option<String>| version.clone().filter(|v| !v.is_empty());
+    let (stack, version) = match config.linux_fx_version.as_deref() {
+        Some(fx) if !fx.is_empty() => {
+            let (stack, version) = fx.split_once('|')?;
+            (stack.to_lowercase(), version.to_string())
+        }
+        _ => {
+            if let Some(version) = set(&config.power_shell_version) {
+                (String::from("powershell"), version)
+            } else if let Some(version) = set(&config.node_version) {
+                (String::from("node"), version)
+            } else {
+                let version = set(&config.net_framework_version)
+                    .filter(|v| !v.eq_ignore_ascii_case("v4.0"))?;
+                (String::from("dotnet"), version)
+            }
+        }
+    };
+    if version.is_empty() {
+        return None;
+    }
+    let offer = match stack.as_str() {
+        "node" | "python" | "powershell" => stack,
+        "dotnet" | "dotnet-isolated" | "dotnetcore" => String::from("dotnet"),
+        _ => return None,
+    };
+    Some((offer, version))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{function_runtime, inferred_stack, runtime, SiteConfig};
+
+    fn linux(fx: &str) -> Option<(String, String)> {
+        let config = SiteConfig {
+            linux_fx_version: Some(fx.to_string()),
+            ..Default::default()
+        };
+        runtime(&config, None)
+    }
+
+    fn stack(offer: &str, version: &str) -> Option<(String, String)> {
+        Some((offer.to_string(), version.to_string()))
+    }
+
+    #[test]
+    fn test_linux_fx_version() {
+        assert_eq!(linux("PHP|8.2"), stack("php", "8.2"));
+        assert_eq!(linux("NODE|18-lts"), stack("node", "18-lts"));
+        assert_eq!(linux("PYTHON|3.11"), stack("python", "3.11"));
+        assert_eq!(linux("DOTNETCORE|6.0"), stack("dotnet", "6.0"));
+        assert_eq!(linux("DOCKER|nginx:latest"), None);
+        assert_eq!(linux("JAVA|17-java17"), None);
+    }
+
+    #[test]
+    fn test_windows_stack() {
+        let config = SiteConfig {
+            linux_fx_version: Some(String::new()),
+            net_framework_version: Some(String::from("v4.0")),
+            php_version: Some(String::from("7.4")),
+            ..Default::default()
+        };
+        assert_eq!(runtime(&config, Some("php")), stack("php", "7.4"));
+        assert_eq!(runtime(&config, Some("dotnet")), stack("dotnetfx", "4.8"));
+        assert_eq!(runtime(&config, None), None);
+        let config = SiteConfig {
+            net_framework_version: Some(String::from("v8.0")),
+            ..Default::default()
+        };
+        assert_eq!(runtime(&config, Some("dotnet")), stack("dotnet", "v8.0"));
+        assert_eq!(runtime(&config, Some("python")), None);
+    }
+
+    #[test]
+    fn test_inferred_stack() {
+        let php = SiteConfig {
+            net_framework_version: Some(String::from("v4.0")),
+            php_version: Some(String::from("8.2")),
+            python_version: Some(String::new()),
+            ..Default::default()
+        };
+        assert_eq!(inferred_stack(&php), Some("php"));
+        let dotnet = SiteConfig {
+            net_framework_version: Some(String::from("v4.0")),
+            ..Default::default()
+        };
+        assert_eq!(inferred_stack(&dotnet), None);
+        let both = SiteConfig {
+            php_version: Some(String::from("5.6")),
+            node_version: Some(String::from("~18")),
+            ..Default::default()
+        };
+        assert_eq!(inferred_stack(&both), None);
+    }
+
+    #[test]
+    fn test_function_runtime() {
+        let linux = SiteConfig {
+            linux_fx_version: Some(String::from("Python|3.11")),
+            ..Default::default()
+        };
+        assert_eq!(function_runtime(&linux), stack("python", "3.11"));
+        let node = SiteConfig {
+            net_framework_version: Some(String::from("v4.0")),
+            node_version: Some(String::from("~18")),
+            ..Default::default()
+        };
+        assert_eq!(function_runtime(&node), stack("node", "~18"));
+        let powershell = SiteConfig {
+            net_framework_version: Some(String::from("v4.0")),
+            power_shell_version: Some(String::from("7.2")),
+            ..Default::default()
+        };
+        assert_eq!(function_runtime(&powershell), stack("powershell", "7.2"));
+        let dotnet = SiteConfig {
+            net_framework_version: Some(String::from("v6.0")),
+            ..Default::default()
+        };
+        assert_eq!(function_runtime(&dotnet), stack("dotnet", "v6.0"));
+        let unknown = SiteConfig {
+            net_framework_version: Some(String::from("v4.0")),
+            ..Default::default()
+        };
+        assert_eq!(function_runtime(&unknown), None);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::inventory::Inventory;
+use crate::logging::error;
+use crate::VMResult;
+use azure_mgmt_compute::models::os_disk::OsType;
+use serde::Deserialize;
+
+/// Publisher set on Arc-enabled server results
+pub const PUBLISHER: &str = "Microsoft.HybridCompute";
+
+const API_VERSION: &str = "2022-12-27";
+
+const HCI_API_VERSION: &str = "2023-09-01-preview";
+
+// Pretty names in osSku on Linux and the osName the VM agent would report
+const DISTRIBUTIONS: [(&str, &str); 10] = [
+    ("ubuntu", "ubuntu"),
+    ("centos linux", "centos"),
+    ("centos", "centos"),
+    ("red hat enterprise linux", "rhel"),
+    ("debian gnu/linux", "debian"),
+    ("almalinux", "almalinux"),
+    ("rocky linux", "rocky"),
+    ("opensuse leap", "opensuse-leap"),
+    ("suse linux enterprise server", "sles"),
+    ("alpine linux", "alpine"),
+];
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Machine {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+    // HCI for Arc VMs on Azure Stack HCI, empty for Arc-enabled servers
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub properties: MachineProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineProperties {
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub os_sku: Option<String>,
+    pub os_type: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HciVirtualMachine {
+    #[serde(default)]
+    pub properties: HciVirtualMachineProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HciVirtualMachineProperties {
+    #[serde(default)]
+    pub storage_profile: HciStorageProfile,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HciStorageProfile {
+    pub image_reference: Option<HciImageReference>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HciImageReference {
+    #[serde(default)]
+    pub id: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HciGalleryImage {
+    #[serde(default)]
+    pub properties: HciGalleryImageProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HciGalleryImageProperties {
+    pub identifier: Option<HciImageIdentifier>,
+    pub version: Option<HciImageVersion>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HciImageIdentifier {
+    #[serde(default)]
+    pub publisher: String,
+    #[serde(default)]
+    pub offer: String,
+    #[serde(default)]
+    pub sku: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HciImageVersion {
+    #[serde(default)]
+    pub name: String,
+}
+
+/// Arc-enabled servers have no image, they are classified by the OS the
+/// Connected Machine agent reports like `--deep` does for VMs. Arc VMs on
+/// Azure Stack HCI report the same way, when their agent hasn't reported yet
+/// they fall back to the marketplace image they were created from.
+pub async fn list_machines(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.HybridCompute/machines?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let machines = match inventory.arm.list::<Machine>(&path).await {
+        Ok(machines) => machines,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "Arc machines", &*e);
+            return;
+        }
+    };
+    for machine in machines {
+        let properties = machine.properties;
+        let os_type = properties
+            .os_type
+            .as_deref()
+            .or(properties.os_name.as_deref())
+            .and_then(|t| match t.to_lowercase().as_str() {
+                "windows" => Some(OsType::Windows),
+                "linux" => Some(OsType::Linux),
+                _ => None,
+            });
+        let (guest_os_name, guest_os_version) = guest_os(
+            properties.os_sku.as_deref().unwrap_or_default(),
+            properties.os_version.as_deref().unwrap_or_default(),
+        );
+        let hci = machine
+            .kind
+            .as_deref()
+            .is_some_and(|k| k.eq_ignore_ascii_case("hci"));
+        let mut result = VMResult {
+            id: machine.id,
+            resource_type: if hci {
+                String::from("Arc VM (Azure Stack HCI)")
+            } else {
+                String::from("Arc machine")
+            },
+            location: machine.location,
+            subscription_id: subscription_id.clone(),
+            publisher: String::from(PUBLISHER),
+            version: properties.os_sku.unwrap_or_default(),
+            exact_version: properties.os_version.unwrap_or_default(),
+            os_type,
+            guest_os_name,
+            guest_os_version,
+            azure_deprecation: String::from("--"),
+            ..Default::default()
+        };
+        if hci && result.guest_os_name.is_empty() {
+            if let Some((identifier, version)) = hci_image(&result.id, inventory).await {
+                result.publisher = identifier.publisher;
+                result.offer = identifier.offer;
+                result.sku = identifier.sku;
+                result.version = version;
+                result.exact_version.clear();
+            }
+        }
+        inventory.send(result).await;
+    }
+}
+
+/// Marketplace identifier and version of the gallery image an HCI VM was
+/// created from, images uploaded by the customer usually have neither.
+async fn hci_image(
+    machine_id: &str,
+    inventory: &Inventory,
+) -> Option<(HciImageIdentifier, String)> {
+    let path = format!(
+        "{}/providers/Microsoft.AzureStackHCI/virtualMachineInstances/default?api-version={}",
+        machine_id, HCI_API_VERSION
+    );
+    let image_id = match inventory.arm.get::<HciVirtualMachine>(&path).await {
+        Ok(vm) => vm.properties.storage_profile.image_reference?.id,
+        Err(e) => {
+            error!("Fetching HCI VM instance failed for {}: {}", machine_id, e);
+            return None;
+        }
+    };
+    let path = format!("{}?api-version={}", image_id, HCI_API_VERSION);
+    match inventory.arm.get::<HciGalleryImage>(&path).await {
+        Ok(image) => {
+            let version = image.properties.version.unwrap_or_default().name;
+            image.properties.identifier.map(|i| (i, version))
+        }
+        Err(e) => {
+            error!("Fetching HCI gallery image {} failed: {}", image_id, e);
+            None
+        }
+    }
+}
+
+/// Turns osSku/osVersion into the osName/osVersion pair the VM agent reports.
+/// Windows names the edition in osSku, Linux names the distribution and its
+/// release while osVersion is the kernel.
+///
+/// Ubuntu 22.04.3 LTS -> (ubuntu, 22.04.3)
+/// Red Hat Enterprise Linux 8.8 (Ootpa) -> (rhel, 8.8)
+/// SUSE Linux Enterprise Server 15 SP5 -> (sles, 15.5)
+pub fn guest_os(os_sku: &str, os_version: &str) -> (String, String) {
+    let lower = os_sku.trim().to_lowercase();
+    if lower.starts_with("windows") {
+        return (os_sku.trim().to_string(), os_version.to_string());
+    }
+    let (prefix, name) = match DISTRIBUTIONS
+        .iter()
+        .find(|(prefix, _)| lower.starts_with(&format!("{} ", prefix)))
+    {
+        Some(distribution) => *distribution,
+        None => return (String::new(), String::new()),
+    };
+    let mut words = lower[prefix.len()..].split_whitespace();
+    let mut version = words.next().unwrap_or_default().to_string();
+    if let Some(sp) = words.next().and_then(|w| w.strip_prefix("sp")) {
+        version = format!("{}.{}", version, sp);
+    }
+    (name.to_string(), version)
+}
+
+#[cfg(test)]
+mod test {
+    use super::guest_os;
+
+    fn os(name: &str, version: &str) -> (String, String) {
+        (name.to_string(), version.to_string())
+    }
+
+    #[test]
+    fn test_linux() {
+        assert_eq!(
+            guest_os("Ubuntu 22.04.3 LTS", "5.15.0-1051-azure"),
+            os("ubuntu", "22.04.3")
+        );
+        assert_eq!(
+            guest_os("Red Hat Enterprise Linux 8.8 (Ootpa)", "4.18.0"),
+            os("rhel", "8.8")
+        );
+        assert_eq!(guest_os("CentOS Linux 7 (Core)", ""), os("centos", "7"));
+        assert_eq!(
+            guest_os("SUSE Linux Enterprise Server 15 SP5", ""),
+            os("sles", "15.5")
+        );
+        assert_eq!(guest_os("Gentoo", ""), os("", ""));
+    }
+
+    #[test]
+    fn test_windows() {
+        assert_eq!(
+            guest_os("Windows Server 2019 Datacenter", "10.0.17763.4252"),
+            os("Windows Server 2019 Datacenter", "10.0.17763.4252")
+        );
+    }
+}
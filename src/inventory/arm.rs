@@ -0,0 +1,238 @@
+use crate::http;
+use azure_core::auth::TokenCredential;
+use azure_core::resource_manager_endpoint::{
+    AZURE_CHINA_CLOUD, AZURE_PUBLIC_CLOUD, AZURE_US_GOVERNMENT_CLOUD,
+};
+use azure_identity::authority_hosts;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+
+// Tokens are renewed this long before they expire so requests in flight
+// don't fail
+const TOKEN_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+pub type ArmError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A request ARM answered with an error status, `code` comes from the error
+/// body (`AuthorizationFailed`, `MissingSubscriptionRegistration`)
+#[derive(Debug)]
+pub struct ResponseError {
+    pub status: u16,
+    pub code: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.code.is_empty() {
+            write!(f, "{}: {}", self.status, self.message)
+        } else {
+            write!(f, "{} {}: {}", self.status, self.code, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ResponseError {}
+
+#[derive(Deserialize, Default)]
+struct ErrorBody {
+    #[serde(default)]
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize, Default)]
+struct ErrorDetail {
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// Azure cloud the tenant lives in. Tokens come from the Azure CLI, which has
+/// to be pointed at the same cloud with `az cloud set`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Cloud {
+    #[default]
+    Public,
+    Government,
+    China,
+}
+
+impl FromStr for Cloud {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "azure-public" | "public" => Ok(Cloud::Public),
+            "azure-government" | "government" | "usgov" => Ok(Cloud::Government),
+            "azure-china" | "china" => Ok(Cloud::China),
+            _ => Err(format!(
+                "invalid cloud '{}', expected azure-public, azure-government or azure-china",
+                s
+            )),
+        }
+    }
+}
+
+impl Cloud {
+    /// Resource Manager endpoint, without a trailing slash
+    pub fn endpoint(&self) -> &'static str {
+        match self {
+            Cloud::Public => AZURE_PUBLIC_CLOUD,
+            Cloud::Government => AZURE_US_GOVERNMENT_CLOUD,
+            Cloud::China => AZURE_CHINA_CLOUD,
+        }
+    }
+
+    /// Microsoft Entra ID host service principals sign in at
+    pub fn authority_host(&self) -> &'static str {
+        match self {
+            Cloud::Public => authority_hosts::AZURE_PUBLIC_CLOUD,
+            Cloud::Government => authority_hosts::AZURE_GOVERNMENT,
+            Cloud::China => authority_hosts::AZURE_CHINA,
+        }
+    }
+
+    /// Resource tokens for the endpoint are requested for
+    pub fn resource(&self) -> String {
+        format!("{}/", self.endpoint())
+    }
+}
+
+/// Plain ARM REST calls for resource providers that have no management crate
+/// compatible with the azure_core version used here.
+pub struct ArmClient {
+    credential: Arc<dyn TokenCredential>,
+    cloud: Cloud,
+    http: reqwest::Client,
+    // Tokens per resource and when they expire
+    tokens: Mutex<HashMap<String, (String, OffsetDateTime)>>,
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    #[serde(default = "Vec::new")]
+    value: Vec<T>,
+    #[serde(rename = "nextLink")]
+    next_link: Option<String>,
+}
+
+impl ArmClient {
+    pub fn new(credential: Arc<dyn TokenCredential>, cloud: Cloud) -> Self {
+        ArmClient {
+            credential,
+            cloud,
+            http: http::client(),
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn token(&self, resource: &str) -> Result<String, ArmError> {
+        let mut tokens = self.tokens.lock().await;
+        // The Azure CLI hands out the token it cached, which may be close
+        // to expiring
+        if let Some((token, expires_on)) = tokens.get(resource) {
+            if *expires_on > OffsetDateTime::now_utc() + TOKEN_MARGIN {
+                return Ok(token.clone());
+            }
+        }
+        let response = self.credential.get_token(resource).await?;
+        let token = response.token.secret().to_string();
+        tokens.insert(resource.to_string(), (token.clone(), response.expires_on));
+        Ok(token)
+    }
+
+    fn url(&self, path: &str) -> String {
+        if path.starts_with("https://") {
+            path.to_string()
+        } else {
+            format!("{}{}", self.cloud.endpoint(), path)
+        }
+    }
+
+    /// GETs `path` (relative to the management endpoint) or an absolute URL
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ArmError> {
+        self.get_with(&self.cloud.resource(), &self.url(path)).await
+    }
+
+    /// GETs `url` outside of ARM with a token for `resource`, e.g. the
+    /// Databricks workspace API
+    pub async fn get_with<T: DeserializeOwned>(
+        &self,
+        resource: &str,
+        url: &str,
+    ) -> Result<T, ArmError> {
+        let response =
+            http::send(self.http.get(url).bearer_auth(self.token(resource).await?)).await?;
+        json(response).await
+    }
+
+    /// POSTs without a body, used by the `list` actions returning secrets or
+    /// settings (e.g. `config/appsettings/list`)
+    pub async fn post<T: DeserializeOwned>(&self, path: &str) -> Result<T, ArmError> {
+        let response = http::send(
+            self.http
+                .post(self.url(path))
+                .bearer_auth(self.token(&self.cloud.resource()).await?)
+                .header(reqwest::header::CONTENT_LENGTH, 0),
+        )
+        .await?;
+        json(response).await
+    }
+
+    /// POSTs `body` as JSON, used by query APIs like Resource Graph
+    pub async fn post_json<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, ArmError> {
+        let response = http::send(
+            self.http
+                .post(self.url(path))
+                .bearer_auth(self.token(&self.cloud.resource()).await?)
+                .json(body),
+        )
+        .await?;
+        json(response).await
+    }
+
+    /// Collects every item of a list operation, following `nextLink`
+    pub async fn list<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>, ArmError> {
+        let mut items = Vec::new();
+        let mut next = Some(path.to_string());
+        while let Some(path) = next {
+            let page: Page<T> = self.get(&path).await?;
+            items.extend(page.value);
+            next = page.next_link;
+        }
+        Ok(items)
+    }
+}
+
+/// Deserializes a successful response, or turns the error body into a
+/// `ResponseError`
+async fn json<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, ArmError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response.json::<T>().await?);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let detail = serde_json::from_str::<ErrorBody>(&body)
+        .unwrap_or_default()
+        .error;
+    Err(Box::new(ResponseError {
+        status: status.as_u16(),
+        code: detail.code,
+        message: if detail.message.is_empty() {
+            body
+        } else {
+            detail.message
+        },
+    }))
+}
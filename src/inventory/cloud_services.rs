@@ -0,0 +1,140 @@
+use crate::inventory::Inventory;
+use crate::VMResult;
+use azure_mgmt_compute::models::os_disk::OsType;
+use futures::stream::StreamExt;
+use serde::Deserialize;
+
+/// Publisher set on classic Cloud Service results
+pub const CLASSIC_PUBLISHER: &str = "Microsoft.ClassicCompute";
+
+const CLASSIC_API_VERSION: &str = "2018-06-01";
+
+// Guest OS families and the Windows Server release they are based on
+const OS_FAMILIES: [(&str, &str); 6] = [
+    ("2", "2008-R2"),
+    ("3", "2012"),
+    ("4", "2012-R2"),
+    ("5", "2016"),
+    ("6", "2019"),
+    ("7", "2022"),
+];
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DomainName {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+}
+
+/// One row per role of a Cloud Service (extended support), classified as the
+/// Windows Server release of the guest OS family in its configuration.
+pub async fn list_cloud_services(subscription_id: &String, inventory: &Inventory) {
+    let services = inventory
+        .client
+        .cloud_services_client()
+        .list_all(subscription_id)
+        .into_stream();
+    services
+        .for_each_concurrent(10, |services| async {
+            let services = match services {
+                Ok(services) => services,
+                Err(e) => {
+                    inventory.listing_failed(subscription_id, "cloud services", &e);
+                    return;
+                }
+            };
+            for service in services.value {
+                let resource_id = service.id.unwrap_or_default();
+                let properties = service.properties.unwrap_or_default();
+                let family = properties
+                    .configuration
+                    .as_deref()
+                    .and_then(os_family)
+                    .unwrap_or_default();
+                let release = OS_FAMILIES
+                    .iter()
+                    .find(|(f, _)| *f == family)
+                    .map(|(_, release)| format!("{}-Datacenter", release))
+                    .unwrap_or_default();
+                let roles = properties.role_profile.map(|p| p.roles).unwrap_or_default();
+                for role in roles {
+                    let sku = role.sku.unwrap_or_default();
+                    let machine = VMResult {
+                        id: format!("{}/roles/{}", resource_id, role.name.unwrap_or_default()),
+                        resource_type: String::from("Cloud service role"),
+                        instances: sku.capacity.unwrap_or_default(),
+                        scale_set: resource_id.clone(),
+                        location: service.location.clone(),
+                        subscription_id: subscription_id.clone(),
+                        publisher: String::from("MicrosoftWindowsServer"),
+                        offer: String::from("WindowsServer"),
+                        sku: release.clone(),
+                        version: format!("osFamily {}", family),
+                        os_type: Some(OsType::Windows),
+                        // Guest OS families aren't marketplace images
+                        azure_deprecation: String::from("--"),
+                        ..Default::default()
+                    };
+                    inventory.send(machine).await;
+                }
+            }
+        })
+        .await;
+    list_classic(subscription_id, inventory).await;
+}
+
+/// Classic Cloud Services are retired as a whole, the row carries no OS
+async fn list_classic(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.ClassicCompute/domainNames?api-version={}",
+        subscription_id, CLASSIC_API_VERSION
+    );
+    let domains = match inventory.arm.list::<DomainName>(&path).await {
+        Ok(domains) => domains,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "classic cloud services", &*e);
+            return;
+        }
+    };
+    for domain in domains {
+        let machine = VMResult {
+            id: domain.id,
+            resource_type: String::from("Cloud service (classic)"),
+            location: domain.location,
+            subscription_id: subscription_id.clone(),
+            publisher: String::from(CLASSIC_PUBLISHER),
+            os_type: Some(OsType::Windows),
+            azure_deprecation: String::from("--"),
+            ..Default::default()
+        };
+        inventory.send(machine).await;
+    }
+}
+
+/// The `osFamily` attribute of a service configuration (.cscfg)
+pub fn os_family(configuration: &str) -> Option<String> {
+    let start = configuration.find("osFamily=")? + "osFamily=".len();
+    let rest = &configuration[start..];
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value: String = rest[1..].chars().take_while(|c| *c != quote).collect();
+    Some(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::os_family;
+
+    #[test]
+    fn test_os_family() {
+        assert_eq!(
+            os_family(r#"<ServiceConfiguration serviceName="web" osFamily="6" osVersion="*">"#),
+            Some(String::from("6"))
+        );
+        assert_eq!(
+            os_family("<ServiceConfiguration osFamily='4'>"),
+            Some(String::from("4"))
+        );
+        assert_eq!(os_family("<ServiceConfiguration>"), None);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::inventory::Inventory;
+use crate::VMResult;
+use azure_mgmt_compute::models::os_disk::OsType;
+use serde::Deserialize;
+
+/// Publisher set on container results, mappings can match on it
+pub const PUBLISHER: &str = "Microsoft.ContainerInstance";
+
+const API_VERSION: &str = "2023-05-01";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ContainerGroup {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub properties: ContainerGroupProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerGroupProperties {
+    pub os_type: Option<String>,
+    #[serde(default)]
+    pub containers: Vec<Container>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Container {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub properties: ContainerProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ContainerProperties {
+    #[serde(default)]
+    pub image: String,
+}
+
+/// One row per container (`--containers`), the offer holds the image
+/// repository and the SKU the base OS named in its tag.
+pub async fn list_container_groups(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.ContainerInstance/containerGroups?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let groups = match inventory.arm.list::<ContainerGroup>(&path).await {
+        Ok(groups) => groups,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "container groups", &*e);
+            return;
+        }
+    };
+    for group in groups {
+        let os_type = match group.properties.os_type.as_deref() {
+            Some(t) if t.eq_ignore_ascii_case("windows") => Some(OsType::Windows),
+            Some(t) if t.eq_ignore_ascii_case("linux") => Some(OsType::Linux),
+            _ => None,
+        };
+        for container in group.properties.containers {
+            let image = container.properties.image;
+            let (repository, tag) = parse_image(&image);
+            let machine = VMResult {
+                id: format!("{}/containers/{}", group.id, container.name),
+                resource_type: String::from("Container instance"),
+                instances: 1,
+                scale_set: group.id.clone(),
+                location: group.location.clone(),
+                subscription_id: subscription_id.clone(),
+                publisher: String::from(PUBLISHER),
+                offer: repository.to_string(),
+                sku: base_os(tag).to_string(),
+                version: image.clone(),
+                os_type: os_type.clone(),
+                azure_deprecation: String::from("--"),
+                ..Default::default()
+            };
+            inventory.send(machine).await;
+        }
+    }
+}
+
+/// Splits an image reference into repository and tag, digests are dropped.
+///
+/// mcr.microsoft.com/dotnet/aspnet:6.0 -> (mcr.microsoft.com/dotnet/aspnet, 6.0)
+/// localhost:5000/app@sha256:... -> (localhost:5000/app, "")
+pub fn parse_image(image: &str) -> (&str, &str) {
+    let image = image.split('@').next().unwrap_or_default();
+    let name_start = image.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match image[name_start..].rfind(':') {
+        Some(i) => (&image[..name_start + i], &image[name_start + i + 1..]),
+        None => (image, ""),
+    }
+}
+
+/// Tags name the base OS in their last segment by convention (1.2-centos7,
+/// 18-alpine3.17), otherwise the tag is the OS version itself (ubuntu:18.04).
+pub fn base_os(tag: &str) -> &str {
+    let last = tag.rsplit('-').next().unwrap_or_default();
+    let named = last.starts_with(|c: char| c.is_ascii_alphabetic())
+        && last.chars().any(|c| c.is_ascii_digit());
+    if named {
+        last
+    } else {
+        tag
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base_os, parse_image};
+
+    #[test]
+    fn test_parse_image() {
+        assert_eq!(
+            parse_image("mcr.microsoft.com/dotnet/aspnet:6.0"),
+            ("mcr.microsoft.com/dotnet/aspnet", "6.0")
+        );
+        assert_eq!(parse_image("centos:7"), ("centos", "7"));
+        assert_eq!(parse_image("nginx"), ("nginx", ""));
+        assert_eq!(
+            parse_image("localhost:5000/app@sha256:abc"),
+            ("localhost:5000/app", "")
+        );
+    }
+
+    #[test]
+    fn test_base_os() {
+        assert_eq!(base_os("1.2-centos7"), "centos7");
+        assert_eq!(base_os("18-alpine3.17"), "alpine3.17");
+        assert_eq!(base_os("ubuntu18.04"), "ubuntu18.04");
+        assert_eq!(base_os("18.04"), "18.04");
+        assert_eq!(base_os("3.9-slim-bullseye"), "3.9-slim-bullseye");
+    }
+}
@@ -0,0 +1,99 @@
+use crate::inventory::Inventory;
+use crate::VMResult;
+use serde::Deserialize;
+
+/// A managed database service, servers are reported with the provider as
+/// publisher, `offer` as offer and the engine version as SKU.
+pub struct Service {
+    pub provider: &'static str,
+    pub resource: &'static str,
+    pub api_version: &'static str,
+    pub offer: &'static str,
+    pub resource_type: &'static str,
+}
+
+pub const SERVICES: [Service; 5] = [
+    Service {
+        provider: "Microsoft.DBforPostgreSQL",
+        resource: "flexibleServers",
+        api_version: "2022-12-01",
+        offer: "postgresql-flexible",
+        resource_type: "PostgreSQL flexible server",
+    },
+    Service {
+        provider: "Microsoft.DBforPostgreSQL",
+        resource: "servers",
+        api_version: "2017-12-01",
+        offer: "postgresql-single",
+        resource_type: "PostgreSQL single server",
+    },
+    Service {
+        provider: "Microsoft.DBforMySQL",
+        resource: "flexibleServers",
+        api_version: "2023-06-30",
+        offer: "mysql-flexible",
+        resource_type: "MySQL flexible server",
+    },
+    Service {
+        provider: "Microsoft.DBforMySQL",
+        resource: "servers",
+        api_version: "2017-12-01",
+        offer: "mysql-single",
+        resource_type: "MySQL single server",
+    },
+    Service {
+        provider: "Microsoft.DBforMariaDB",
+        resource: "servers",
+        api_version: "2018-06-01",
+        offer: "mariadb",
+        resource_type: "MariaDB server",
+    },
+];
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Server {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub properties: ServerProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ServerProperties {
+    #[serde(default)]
+    pub version: String,
+}
+
+pub async fn list_servers(subscription_id: &String, inventory: &Inventory) {
+    for service in &SERVICES {
+        let path = format!(
+            "/subscriptions/{}/providers/{}/{}?api-version={}",
+            subscription_id, service.provider, service.resource, service.api_version
+        );
+        let servers = match inventory.arm.list::<Server>(&path).await {
+            Ok(servers) => servers,
+            Err(e) => {
+                let what = format!("{}s", service.resource_type);
+                inventory.listing_failed(subscription_id, &what, &*e);
+                continue;
+            }
+        };
+        for server in servers {
+            let machine = VMResult {
+                id: server.id,
+                resource_type: String::from(service.resource_type),
+                location: server.location,
+                subscription_id: subscription_id.clone(),
+                publisher: String::from(service.provider),
+                offer: String::from(service.offer),
+                sku: server.properties.version.clone(),
+                version: server.properties.version,
+                azure_deprecation: String::from("--"),
+                ..Default::default()
+            };
+            inventory.send(machine).await;
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::inventory::Inventory;
+use crate::logging::error;
+use crate::VMResult;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// Publisher set on Databricks cluster results
+pub const PUBLISHER: &str = "Microsoft.Databricks";
+
+const API_VERSION: &str = "2023-02-01";
+
+// Application ID of the Azure Databricks first-party app, the resource tokens
+// for the workspace API are requested for
+const DATABRICKS_RESOURCE: &str = "2ff814a6-3304-4ab8-85cb-cd0e6f879c1d";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Workspace {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub properties: WorkspaceProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceProperties {
+    pub workspace_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Clusters {
+    #[serde(default)]
+    clusters: Vec<Cluster>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Cluster {
+    #[serde(default)]
+    pub cluster_id: String,
+    #[serde(default)]
+    pub spark_version: String,
+    #[serde(default)]
+    pub num_workers: i64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct SparkVersions {
+    #[serde(default)]
+    versions: Vec<SparkVersion>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct SparkVersion {
+    #[serde(default)]
+    key: String,
+}
+
+/// One row per cluster of every workspace, a runtime the workspace no longer
+/// offers (`clusters/spark-versions`) is past its end of support.
+pub async fn list_workspaces(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.Databricks/workspaces?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let workspaces = match inventory.arm.list::<Workspace>(&path).await {
+        Ok(workspaces) => workspaces,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "Databricks workspaces", &*e);
+            return;
+        }
+    };
+    for workspace in workspaces {
+        let url = match workspace.properties.workspace_url {
+            Some(url) => url,
+            None => continue,
+        };
+        let clusters = match inventory
+            .arm
+            .get_with::<Clusters>(
+                DATABRICKS_RESOURCE,
+                &format!("https://{}/api/2.0/clusters/list", url),
+            )
+            .await
+        {
+            Ok(clusters) => clusters.clusters,
+            Err(e) => {
+                error!("Listing clusters failed for {}: {}", workspace.id, e);
+                continue;
+            }
+        };
+        let supported: Option<HashSet<String>> = match inventory
+            .arm
+            .get_with::<SparkVersions>(
+                DATABRICKS_RESOURCE,
+                &format!("https://{}/api/2.0/clusters/spark-versions", url),
+            )
+            .await
+        {
+            Ok(versions) => Some(versions.versions.into_iter().map(|v| v.key).collect()),
+            Err(e) => {
+                error!("Listing runtimes failed for {}: {}", workspace.id, e);
+                None
+            }
+        };
+        for cluster in clusters {
+            // No dates are published, only whether the runtime is still offered
+            let support_end = supported.as_ref().map(|supported| {
+                if supported.contains(&cluster.spark_version) {
+                    NaiveDate::MAX
+                } else {
+                    NaiveDate::MIN
+                }
+            });
+            let machine = VMResult {
+                id: format!("{}/clusters/{}", workspace.id, cluster.cluster_id),
+                resource_type: String::from("Databricks cluster"),
+                instances: cluster.num_workers + 1,
+                scale_set: workspace.id.clone(),
+                location: workspace.location.clone(),
+                subscription_id: subscription_id.clone(),
+                publisher: String::from(PUBLISHER),
+                offer: String::from("databricks-runtime"),
+                sku: cluster.spark_version.clone(),
+                version: cluster.spark_version,
+                support_end,
+                azure_deprecation: String::from("--"),
+                ..Default::default()
+            };
+            inventory.send(machine).await;
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::inventory::Inventory;
+use crate::logging::error;
+use crate::VMResult;
+use azure_mgmt_compute::models::os_disk::OsType;
+use serde::Deserialize;
+
+const API_VERSION: &str = "2018-09-15";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Lab {
+    #[serde(default)]
+    pub id: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct LabVirtualMachine {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub properties: LabVirtualMachineProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LabVirtualMachineProperties {
+    pub compute_id: Option<String>,
+    pub os_type: Option<String>,
+    pub gallery_image_reference: Option<GalleryImageReference>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct GalleryImageReference {
+    #[serde(default)]
+    pub publisher: String,
+    #[serde(default)]
+    pub offer: String,
+    #[serde(default)]
+    pub sku: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+/// Lab VMs are reported under the compute VM backing them, those already
+/// listed with the subscription's VMs are skipped. The rest (VMs in a lab
+/// resource group the caller can't read, claimable VMs that haven't been
+/// created yet) are classified from the lab's own image reference.
+pub async fn list_labs(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.DevTestLab/labs?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let labs = match inventory.arm.list::<Lab>(&path).await {
+        Ok(labs) => labs,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "DevTest Labs", &*e);
+            return;
+        }
+    };
+    for lab in labs {
+        let path = format!("{}/virtualmachines?api-version={}", lab.id, API_VERSION);
+        let vms = match inventory.arm.list::<LabVirtualMachine>(&path).await {
+            Ok(vms) => vms,
+            Err(e) => {
+                error!("Listing lab VMs failed for {}: {}", lab.id, e);
+                continue;
+            }
+        };
+        for vm in vms {
+            let properties = vm.properties;
+            let id = match properties.compute_id {
+                Some(id) if !id.is_empty() => id,
+                _ => vm.id,
+            };
+            let os_type = match properties.os_type.as_deref() {
+                Some(t) if t.eq_ignore_ascii_case("windows") => Some(OsType::Windows),
+                Some(t) if t.eq_ignore_ascii_case("linux") => Some(OsType::Linux),
+                _ => None,
+            };
+            let image = properties.gallery_image_reference.unwrap_or_default();
+            let custom_image =
+                image.publisher.is_empty() && image.offer.is_empty() && image.sku.is_empty();
+            let machine = VMResult {
+                id,
+                resource_type: String::from("DevTest Labs VM"),
+                instances: 1,
+                scale_set: lab.id.clone(),
+                location: vm.location,
+                subscription_id: subscription_id.clone(),
+                publisher: image.publisher,
+                offer: image.offer,
+                sku: image.sku,
+                version: image.version,
+                os_type,
+                custom_image,
+                ..Default::default()
+            };
+            inventory.send(machine).await;
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::eol_detection::detector::{Detection, Verdict};
+use crate::eol_detection::mapping::glob_match;
+use crate::inventory::normalize_location;
+use crate::inventory::tags::TagFilter;
+use crate::VMResult;
+use regex::Regex;
+
+/// Which results make it into the report, applied while scanning and again
+/// when reporting from a cache
+#[derive(Debug, Clone, Default)]
+pub struct ResultFilter {
+    // Lowercased `--resource-group` globs, empty to scan every group
+    pub resource_groups: Vec<String>,
+    // `--location` names as ARM spells them (westeurope), empty for all
+    pub locations: Vec<String>,
+    pub tags: TagFilter,
+    // `--match`/`--exclude`, tested against the resource ID
+    pub matches: Vec<Regex>,
+    pub excludes: Vec<Regex>,
+    // Leave out deallocated VMs
+    pub skip_deallocated: bool,
+}
+
+/// Which classified results get a row, applied while writing the report.
+/// The summary counts every result.
+#[derive(Debug, Clone, Default)]
+pub struct RowFilter {
+    // `--only`, empty for every verdict
+    pub only: Vec<Verdict>,
+    // `--os`/`--exclude-os`, lowercased endoflife.date product prefixes or
+    // an OS type (linux, windows)
+    pub os: Vec<String>,
+    pub exclude_os: Vec<String>,
+}
+
+impl RowFilter {
+    pub fn matches(&self, machine: &VMResult, detection: &Detection) -> bool {
+        let os_type = machine
+            .os_type
+            .as_ref()
+            .map(|t| format!("{:?}", t).to_lowercase())
+            .unwrap_or_default();
+        let is_os = |wanted: &String| {
+            (!detection.product.is_empty() && detection.product.starts_with(wanted.as_str()))
+                || *wanted == os_type
+        };
+        (self.only.is_empty() || self.only.contains(&detection.verdict()))
+            && (self.os.is_empty() || self.os.iter().any(is_os))
+            && !self.exclude_os.iter().any(is_os)
+    }
+}
+
+impl ResultFilter {
+    /// Whether a resource group passes `--resource-group`
+    pub fn wants_resource_group(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.resource_groups.is_empty() || self.resource_groups.iter().any(|p| glob_match(p, &name))
+    }
+
+    pub fn matches(&self, machine: &VMResult) -> bool {
+        self.wants_resource_group(machine.resource_group())
+            && self.tags.matches(&machine.tags)
+            && (self.matches.is_empty() || self.matches.iter().any(|r| r.is_match(&machine.id)))
+            && !self.excludes.iter().any(|r| r.is_match(&machine.id))
+            && (self.locations.is_empty()
+                || self
+                    .locations
+                    .contains(&normalize_location(&machine.location)))
+            && !(self.skip_deallocated && machine.power_state == "deallocated")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RowFilter;
+    use crate::eol_detection::detector::{Detection, Verdict};
+    use crate::VMResult;
+
+    #[test]
+    fn test_row_filter() {
+        let vm = VMResult::default();
+        let detection = |product: &str, status: &str| Detection {
+            product: product.to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        };
+        let windows = RowFilter {
+            os: vec![String::from("windows")],
+            ..Default::default()
+        };
+        assert!(windows.matches(&vm, &detection("windowsserver", "EOL")));
+        assert!(!windows.matches(&vm, &detection("ubuntu", "EOL")));
+        let no_centos = RowFilter {
+            exclude_os: vec![String::from("centos")],
+            only: vec![Verdict::Eol],
+            ..Default::default()
+        };
+        assert!(!no_centos.matches(&vm, &detection("centos-stream", "EOL")));
+        assert!(!no_centos.matches(&vm, &detection("ubuntu", "Supported")));
+        assert!(no_centos.matches(&vm, &detection("ubuntu", "EOL")));
+    }
+}
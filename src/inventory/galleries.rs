@@ -0,0 +1,216 @@
+use crate::inventory::{gallery_base_image, is_gallery_image, tagged_image, ImageUrn, Inventory};
+use crate::logging::{debug, error};
+use crate::VMResult;
+use azure_mgmt_compute::models::gallery_image_properties;
+use azure_mgmt_compute::models::os_disk::OsType;
+use azure_mgmt_compute::models::GalleryImage;
+use futures::stream::StreamExt;
+
+/// `--images` mode: one row per Compute Gallery image version, classified by
+/// the base image it was built from so golden images on an EOL base are
+/// caught before new VMs are deployed from them.
+pub async fn list_galleries(subscription_id: &String, inventory: &Inventory) {
+    let mut galleries = inventory
+        .client
+        .galleries_client()
+        .list(subscription_id)
+        .into_stream();
+    while let Some(page) = galleries.next().await {
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                inventory.listing_failed(subscription_id, "galleries", &e);
+                return;
+            }
+        };
+        for gallery in page.value {
+            let id = gallery.resource.id.unwrap_or_default();
+            let parts: Vec<&str> = id.split('/').collect();
+            if parts.len() < 9 {
+                continue;
+            }
+            list_definitions(subscription_id, parts[4], parts[8], inventory).await;
+        }
+    }
+}
+
+async fn list_definitions(
+    subscription_id: &String,
+    resource_group: &str,
+    gallery: &str,
+    inventory: &Inventory,
+) {
+    let mut definitions = inventory
+        .client
+        .gallery_images_client()
+        .list_by_gallery(subscription_id, resource_group, gallery)
+        .into_stream();
+    while let Some(page) = definitions.next().await {
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Listing image definitions failed for {}: {}", gallery, e);
+                return;
+            }
+        };
+        for definition in page.value {
+            list_versions(
+                subscription_id,
+                resource_group,
+                gallery,
+                definition,
+                inventory,
+            )
+            .await;
+        }
+    }
+}
+
+async fn list_versions(
+    subscription_id: &String,
+    resource_group: &str,
+    gallery: &str,
+    definition: GalleryImage,
+    inventory: &Inventory,
+) {
+    let name = definition.resource.name.clone().unwrap_or_default();
+    let tagged = tagged_image(&definition, &inventory.gallery_tag);
+    let (identifier, os_type) = match &definition.properties {
+        Some(p) => (
+            Some((
+                p.identifier.publisher.clone(),
+                p.identifier.offer.clone(),
+                p.identifier.sku.clone(),
+            )),
+            match p.os_type {
+                gallery_image_properties::OsType::Windows => Some(OsType::Windows),
+                gallery_image_properties::OsType::Linux => Some(OsType::Linux),
+            },
+        ),
+        None => (None, None),
+    };
+    let mut versions = inventory
+        .client
+        .gallery_image_versions_client()
+        .list_by_gallery_image(subscription_id, resource_group, gallery, &name)
+        .into_stream();
+    while let Some(page) = versions.next().await {
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Listing image versions failed for {}: {}", name, e);
+                return;
+            }
+        };
+        for version in page.value {
+            let source = version
+                .properties
+                .and_then(|p| p.storage_profile.source)
+                .and_then(|s| s.gallery_artifact_version_source.id)
+                .unwrap_or_default();
+            // The tag is set deliberately, the source VM may have been
+            // upgraded in place, the definition's identifier is free-form.
+            let urn = match tagged.clone() {
+                Some(urn) => Some(urn),
+                None => source_image(&source, inventory)
+                    .await
+                    .or_else(|| identifier.clone()),
+            };
+            let (publisher, offer, sku) = urn.unwrap_or_default();
+            let id = version.resource.id.unwrap_or_default();
+            let machine = VMResult {
+                resource_type: String::from("Gallery image version"),
+                instances: 0,
+                location: version.resource.location,
+                subscription_id: subscription_id.clone(),
+                custom_image: publisher.is_empty() && offer.is_empty() && sku.is_empty(),
+                publisher,
+                offer,
+                sku,
+                version: version.resource.name.unwrap_or_default(),
+                os_type: os_type.clone(),
+                gallery_image: id.clone(),
+                azure_deprecation: String::from("--"),
+                id,
+                ..Default::default()
+            };
+            inventory.send(machine).await;
+        }
+    }
+}
+
+/// Marketplace image of the VM, managed image or gallery image version an
+/// image version was captured from, as long as the source still exists.
+async fn source_image(source: &str, inventory: &Inventory) -> Option<ImageUrn> {
+    if is_gallery_image(source) {
+        return gallery_base_image(inventory, source).await;
+    }
+    // /subscriptions/{sub}/resourceGroups/{rg}/providers/Microsoft.Compute/
+    // {virtualMachines,images}/{name}, possibly in another subscription
+    let parts: Vec<&str> = source.split('/').collect();
+    if parts.len() < 9 {
+        return None;
+    }
+    if parts[7].eq_ignore_ascii_case("images") {
+        let vm = managed_image_source(source, &parts, inventory).await?;
+        return vm_image(&vm, inventory).await;
+    }
+    if !parts[7].eq_ignore_ascii_case("virtualMachines") {
+        debug!("Unsupported image version source {}", source);
+        return None;
+    }
+    vm_image(source, inventory).await
+}
+
+/// VM a managed image was generalized from, images created from a VHD or a
+/// snapshot name none
+async fn managed_image_source(
+    image_id: &str,
+    parts: &[&str],
+    inventory: &Inventory,
+) -> Option<String> {
+    match inventory
+        .client
+        .images_client()
+        .get(parts[4], parts[8], parts[2])
+        .await
+    {
+        Ok(image) => {
+            let vm = image
+                .properties
+                .and_then(|p| p.source_virtual_machine)
+                .and_then(|vm| vm.id);
+            if vm.is_none() {
+                debug!("Managed image {} wasn't captured from a VM", image_id);
+            }
+            vm
+        }
+        Err(e) => {
+            error!("Fetching managed image {} failed: {}", image_id, e);
+            None
+        }
+    }
+}
+
+async fn vm_image(vm_id: &str, inventory: &Inventory) -> Option<ImageUrn> {
+    let parts: Vec<&str> = vm_id.split('/').collect();
+    if parts.len() < 9 {
+        return None;
+    }
+    let vm = match inventory
+        .client
+        .virtual_machines_client()
+        .get(parts[4], parts[8], parts[2])
+        .await
+    {
+        Ok(vm) => vm,
+        // Usually deleted after capturing
+        Err(_) => return None,
+    };
+    let reference = vm.properties?.storage_profile?.image_reference?;
+    let gallery_image = reference.sub_resource.id.unwrap_or_default();
+    if is_gallery_image(&gallery_image) {
+        return gallery_base_image(inventory, &gallery_image).await;
+    }
+    Some((reference.publisher?, reference.offer?, reference.sku?))
+}
@@ -0,0 +1,109 @@
+use crate::inventory::Inventory;
+use crate::VMResult;
+use azure_mgmt_compute::models::os_disk::OsType;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Publisher set on HDInsight cluster results
+pub const PUBLISHER: &str = "Microsoft.HDInsight";
+
+const API_VERSION: &str = "2021-06-01";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Cluster {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub properties: ClusterProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterProperties {
+    #[serde(default)]
+    pub cluster_version: String,
+    pub os_type: Option<String>,
+    #[serde(default)]
+    pub cluster_definition: ClusterDefinition,
+    #[serde(default)]
+    pub compute_profile: ComputeProfile,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterDefinition {
+    #[serde(default)]
+    pub kind: String,
+    #[serde(default)]
+    pub component_version: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ComputeProfile {
+    #[serde(default)]
+    pub roles: Vec<Role>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Role {
+    #[serde(default)]
+    pub target_instance_count: i64,
+}
+
+/// One row per cluster, the SKU holds the HDInsight version and the version
+/// holds its component versions (`Spark 3.3`), or only the cluster kind.
+pub async fn list_clusters(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.HDInsight/clusters?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let clusters = match inventory.arm.list::<Cluster>(&path).await {
+        Ok(clusters) => clusters,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "HDInsight clusters", &*e);
+            return;
+        }
+    };
+    for cluster in clusters {
+        let properties = cluster.properties;
+        let definition = properties.cluster_definition;
+        let components: Vec<String> = definition
+            .component_version
+            .iter()
+            .map(|(component, version)| format!("{} {}", component, version))
+            .collect();
+        let stack = if components.is_empty() {
+            definition.kind
+        } else {
+            components.join(", ")
+        };
+        let os_type = match properties.os_type.as_deref() {
+            Some(t) if t.eq_ignore_ascii_case("windows") => Some(OsType::Windows),
+            Some(t) if t.eq_ignore_ascii_case("linux") => Some(OsType::Linux),
+            _ => None,
+        };
+        let machine = VMResult {
+            id: cluster.id,
+            resource_type: String::from("HDInsight cluster"),
+            instances: properties
+                .compute_profile
+                .roles
+                .iter()
+                .map(|role| role.target_instance_count)
+                .sum(),
+            location: cluster.location,
+            subscription_id: subscription_id.clone(),
+            publisher: String::from(PUBLISHER),
+            offer: String::from("hdinsight"),
+            sku: properties.cluster_version,
+            version: stack,
+            os_type,
+            azure_deprecation: String::from("--"),
+            ..Default::default()
+        };
+        inventory.send(machine).await;
+    }
+}
@@ -0,0 +1,459 @@
+pub mod aks;
+pub mod app_service;
+pub mod arc;
+pub mod arm;
+pub mod cloud_services;
+pub mod containers;
+pub mod databases;
+pub mod databricks;
+pub mod devtest_labs;
+pub mod filter;
+pub mod galleries;
+pub mod hdinsight;
+pub mod resource_graph;
+pub mod scale_sets;
+pub mod service_fabric;
+pub mod spring_apps;
+pub mod sql_vms;
+pub mod tags;
+pub mod vms;
+
+use crate::checkpoint::Checkpoint;
+use crate::logging::{debug, error};
+use crate::progress;
+use crate::subscriptions::{failure_reason, Skipped};
+use crate::VMResult;
+use arm::ArmError;
+use azure_core::error::ErrorKind;
+use azure_core::StatusCode;
+use azure_mgmt_compute::models::image_deprecation_status::ImageState;
+use azure_mgmt_compute::models::{GalleryImage, ImageReference, Plan};
+use filter::ResultFilter;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Mutex;
+
+/// Publisher, offer and SKU an image is classified by
+pub type ImageUrn = (String, String, String);
+
+/// Clients and caches shared by every subscription of a run
+pub struct Inventory {
+    pub client: azure_mgmt_compute::Client,
+    pub arm: arm::ArmClient,
+    pub tx: Mutex<Sender<VMResult>>,
+    pub image_cache: Mutex<HashMap<String, String>>,
+    pub gallery_cache: Mutex<HashMap<String, Option<ImageUrn>>>,
+    // Resource IDs already reported, see `first_seen`, shared by the
+    // inventories of every tenant scanned
+    pub seen: Arc<Mutex<HashSet<String>>>,
+    // SQL Server offer of VMs registered with the SQL IaaS Agent extension
+    pub sql_vms: Mutex<HashMap<String, String>>,
+    // Power state of VMs (running, deallocated), see `vms::list_power_states`
+    pub power_states: Mutex<HashMap<String, String>>,
+    pub deep: bool,
+    // Scan Container Instances as well
+    pub containers: bool,
+    // Report Compute Gallery image versions instead of deployed resources
+    pub images: bool,
+    // VMs and scale sets come from Resource Graph, see `resource_graph`
+    pub resource_graph: bool,
+    pub gallery_tag: String,
+    pub filter: ResultFilter,
+    // Progress of the scan for `--resume`
+    pub checkpoint: Option<Arc<Checkpoint>>,
+    // Home tenant of each subscription by lowercased ID, for the Tenant
+    // column. Lighthouse-delegated subscriptions are in the customer's.
+    pub tenants: Mutex<HashMap<String, String>>,
+    // Display name of each subscription by lowercased ID
+    pub subscription_names: Mutex<HashMap<String, String>>,
+    // Subscriptions listing VMs failed for, shared with the report writer
+    pub skipped: Arc<Skipped>,
+}
+
+#[derive(serde::Deserialize)]
+struct ResourceGroup {
+    name: String,
+}
+
+impl Inventory {
+    pub async fn list_subscription(&self, subscription_id: &String) {
+        if self.images {
+            galleries::list_galleries(subscription_id, self).await;
+            return;
+        }
+        if !self.resource_graph {
+            sql_vms::list_sql_vms(subscription_id, self).await;
+            vms::list_power_states(subscription_id, self).await;
+            vms::list_vms(subscription_id, self).await;
+            scale_sets::list_scale_sets(subscription_id, self).await;
+        }
+        devtest_labs::list_labs(subscription_id, self).await;
+        aks::list_clusters(subscription_id, self).await;
+        app_service::list_sites(subscription_id, self).await;
+        databases::list_servers(subscription_id, self).await;
+        arc::list_machines(subscription_id, self).await;
+        hdinsight::list_clusters(subscription_id, self).await;
+        service_fabric::list_clusters(subscription_id, self).await;
+        cloud_services::list_cloud_services(subscription_id, self).await;
+        databricks::list_workspaces(subscription_id, self).await;
+        spring_apps::list_services(subscription_id, self).await;
+        if self.containers {
+            containers::list_container_groups(subscription_id, self).await;
+        }
+    }
+
+    /// Resource groups of a subscription matching `--resource-group`, `None`
+    /// when the filter isn't set and subscription-wide listings can be used.
+    /// A failure is recorded with the subscription skipped, its callers have
+    /// nothing to list.
+    pub async fn selected_resource_groups(
+        &self,
+        subscription_id: &str,
+    ) -> Result<Option<Vec<String>>, ArmError> {
+        if self.filter.resource_groups.is_empty() {
+            return Ok(None);
+        }
+        let path = format!(
+            "/subscriptions/{}/resourcegroups?api-version=2021-04-01",
+            subscription_id
+        );
+        match self.arm.list::<ResourceGroup>(&path).await {
+            Ok(groups) => Ok(Some(
+                groups
+                    .into_iter()
+                    .map(|g| g.name)
+                    .filter(|name| self.filter.wants_resource_group(name))
+                    .collect(),
+            )),
+            Err(e) => {
+                self.listing_failed(subscription_id, "resource groups", &*e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Logs a subscription-wide listing that failed and records the
+    /// subscription as skipped, so the report names the hole and `--resume`
+    /// scans it again. A subscription not registered for the resource
+    /// provider has none of its resources and isn't skipped.
+    pub fn listing_failed(
+        &self,
+        subscription_id: &str,
+        what: &str,
+        e: &(dyn std::error::Error + 'static),
+    ) {
+        if e.downcast_ref::<arm::ResponseError>()
+            .is_some_and(|e| e.code == "MissingSubscriptionRegistration")
+        {
+            debug!("No {} in {}: {}", what, subscription_id, e);
+            return;
+        }
+        error!("Listing {} failed for {}: {}", what, subscription_id, e);
+        self.skipped.add(
+            subscription_id,
+            format!("Listing {}: {}", what, failure_reason(e)),
+        );
+    }
+
+    pub async fn send(&self, mut machine: VMResult) {
+        if machine.power_state.is_empty() {
+            if let Some(state) = self
+                .power_states
+                .lock()
+                .await
+                .get(&machine.id.to_lowercase())
+            {
+                machine.power_state = state.clone();
+            }
+        }
+        if machine.tenant_id.is_empty() {
+            if let Some(tenant) = self
+                .tenants
+                .lock()
+                .await
+                .get(&machine.subscription_id.to_lowercase())
+            {
+                machine.tenant_id = tenant.clone();
+            }
+        }
+        if machine.subscription_name.is_empty() {
+            if let Some(name) = self
+                .subscription_names
+                .lock()
+                .await
+                .get(&machine.subscription_id.to_lowercase())
+            {
+                machine.subscription_name = name.clone();
+            }
+        }
+        // Scanners listing subscription-wide are filtered here
+        if !self.filter.matches(&machine) {
+            return;
+        }
+        if !self.first_seen(&machine.id).await {
+            return;
+        }
+        // Scanners of non-compute resources fill in "--" themselves
+        if machine.azure_deprecation.is_empty() {
+            machine.azure_deprecation =
+                image_deprecation(&self.client, &self.image_cache, &machine).await;
+        }
+        if let Some(checkpoint) = &self.checkpoint {
+            checkpoint.result(&machine);
+        }
+        progress::found();
+        let tx = self.tx.lock().await;
+        _ = tx.send(machine).await;
+    }
+
+    /// Every result passes here before it is written so each resource gets
+    /// exactly one row: Flex scale set instances show up in both the VM and
+    /// the scale set listing, pages may overlap, and a resumed scan repeats
+    /// part of the previous one. ARM IDs are case-insensitive.
+    async fn first_seen(&self, id: &str) -> bool {
+        let key = id.trim_end_matches('/').to_lowercase();
+        // Results without an ID can't be told apart, they are all kept
+        key.is_empty() || self.seen.lock().await.insert(key)
+    }
+
+    /// Reports a result of the run being resumed as it was recorded, unless
+    /// the filters of this run exclude it
+    pub async fn replay(&self, machine: VMResult) {
+        if !self.filter.matches(&machine) {
+            return;
+        }
+        if !self.first_seen(&machine.id).await {
+            return;
+        }
+        progress::found();
+        let tx = self.tx.lock().await;
+        _ = tx.send(machine).await;
+    }
+}
+
+/// `West Europe` -> `westeurope`
+pub fn normalize_location(location: &str) -> String {
+    location
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// The image a VM or scale set was created from, after resolving gallery
+/// images and falling back to the purchase plan
+#[derive(Debug, Default)]
+pub struct ResolvedImage {
+    pub publisher: String,
+    pub offer: String,
+    pub sku: String,
+    pub version: String,
+    pub exact_version: String,
+    pub gallery_image: String,
+    pub plan_publisher: String,
+    pub plan_product: String,
+    pub plan_name: String,
+    pub custom_image: bool,
+}
+
+impl ResolvedImage {
+    /// Copies the image details into a result
+    pub fn apply(self, machine: &mut VMResult) {
+        machine.publisher = self.publisher;
+        machine.offer = self.offer;
+        machine.sku = self.sku;
+        machine.version = self.version;
+        machine.exact_version = self.exact_version;
+        machine.gallery_image = self.gallery_image;
+        machine.plan_publisher = self.plan_publisher;
+        machine.plan_product = self.plan_product;
+        machine.plan_name = self.plan_name;
+        machine.custom_image = self.custom_image;
+    }
+}
+
+pub async fn resolve_image(
+    inventory: &Inventory,
+    reference: Option<ImageReference>,
+    plan: Option<Plan>,
+) -> ResolvedImage {
+    let mut image = ResolvedImage::default();
+    if let Some(r) = reference {
+        image.gallery_image = r.sub_resource.id.unwrap_or_default();
+        image.sku = r.sku.unwrap_or_default();
+        image.publisher = r.publisher.unwrap_or_default();
+        image.offer = r.offer.unwrap_or_default();
+        image.version = r.version.unwrap_or_default();
+        image.exact_version = r.exact_version.unwrap_or_default();
+    }
+    let plan = plan.unwrap_or_default();
+    image.plan_publisher = plan.publisher.unwrap_or_default();
+    image.plan_product = plan.product.unwrap_or_default();
+    image.plan_name = plan.name.unwrap_or_default();
+    if is_gallery_image(&image.gallery_image) {
+        match gallery_base_image(inventory, &image.gallery_image).await {
+            Some((publisher, offer, sku)) => {
+                image.sku = sku;
+                image.publisher = publisher;
+                image.offer = offer;
+            }
+            None => error!("No base OS found for gallery image {}", image.gallery_image),
+        }
+        let version = image.gallery_image.split('/').nth(12).unwrap_or_default();
+        image.version = version.to_string();
+        image.exact_version = version.to_string();
+    } else {
+        image.gallery_image.clear();
+    }
+    // The plan is often the only hint left for BYOS and paid images
+    // that were captured or attached as specialized disks.
+    if image.sku.is_empty() && image.publisher.is_empty() && image.offer.is_empty() {
+        image.sku = image.plan_name.clone();
+        image.publisher = image.plan_publisher.clone();
+        image.offer = image.plan_product.clone();
+    }
+    image.custom_image =
+        image.sku.is_empty() && image.publisher.is_empty() && image.offer.is_empty();
+    image
+}
+
+/// Deprecation state Azure publishes for the marketplace image version a VM
+/// runs, looked up once per distinct image.
+async fn image_deprecation(
+    client: &azure_mgmt_compute::Client,
+    cache: &Mutex<HashMap<String, String>>,
+    vm: &VMResult,
+) -> String {
+    let version = if vm.exact_version.is_empty() {
+        &vm.version
+    } else {
+        &vm.exact_version
+    };
+    if vm.custom_image
+        || !vm.gallery_image.is_empty()
+        || vm.location.is_empty()
+        || version.is_empty()
+        || version == "latest"
+    {
+        return String::from("--");
+    }
+    let key = format!(
+        "{}/{}/{}/{}/{}",
+        vm.location, vm.publisher, vm.offer, vm.sku, version
+    )
+    .to_lowercase();
+    if let Some(status) = cache.lock().await.get(&key) {
+        return status.clone();
+    }
+    let status = match client
+        .virtual_machine_images_client()
+        .get(
+            &vm.location,
+            &vm.publisher,
+            &vm.offer,
+            &vm.sku,
+            version,
+            &vm.subscription_id,
+        )
+        .await
+    {
+        Ok(image) => {
+            let deprecation = image
+                .properties
+                .and_then(|p| p.image_deprecation_status)
+                .unwrap_or_default();
+            match deprecation.image_state {
+                Some(ImageState::Deprecated) => String::from("Deprecated"),
+                Some(ImageState::ScheduledForDeprecation) => {
+                    match deprecation.scheduled_deprecation_time {
+                        Some(time) => format!("Scheduled for deprecation {}", time.date()),
+                        None => String::from("Scheduled for deprecation"),
+                    }
+                }
+                _ => String::from("Active"),
+            }
+        }
+        // The image version is gone from the marketplace altogether
+        Err(e) if is_not_found(&e) => String::from("Removed"),
+        Err(e) => {
+            error!("Fetching image {} failed: {}", key, e);
+            String::from("--")
+        }
+    };
+    cache.lock().await.insert(key, status.clone());
+    status
+}
+
+pub fn is_gallery_image(image_id: &str) -> bool {
+    image_id
+        .to_lowercase()
+        .contains("/providers/microsoft.compute/galleries/")
+}
+
+/// Resolves the marketplace image a Compute Gallery image is based on, from
+/// the configured tag (`publisher:offer:sku`) on the image definition or
+/// else from the definition's own publisher/offer/SKU identifier.
+pub async fn gallery_base_image(inventory: &Inventory, image_id: &str) -> Option<ImageUrn> {
+    // /subscriptions/{sub}/resourceGroups/{rg}/providers/Microsoft.Compute/
+    // galleries/{gallery}/images/{image}/versions/{version}
+    let parts: Vec<&str> = image_id.split('/').collect();
+    if parts.len() < 11 {
+        return None;
+    }
+    let definition = parts[..11].join("/").to_lowercase();
+    if let Some(urn) = inventory.gallery_cache.lock().await.get(&definition) {
+        return urn.clone();
+    }
+    let urn = match inventory
+        .client
+        .gallery_images_client()
+        .get(parts[2], parts[4], parts[8], parts[10])
+        .await
+    {
+        Ok(image) => tagged_image(&image, &inventory.gallery_tag).or_else(|| {
+            image
+                .properties
+                .map(|p| (p.identifier.publisher, p.identifier.offer, p.identifier.sku))
+        }),
+        Err(e) => {
+            error!("Fetching gallery image {} failed: {}", definition, e);
+            None
+        }
+    };
+    inventory
+        .gallery_cache
+        .lock()
+        .await
+        .insert(definition, urn.clone());
+    urn
+}
+
+/// Base image named by the `tag` on an image definition
+pub fn tagged_image(image: &GalleryImage, tag: &str) -> Option<ImageUrn> {
+    image
+        .resource
+        .tags
+        .as_ref()
+        .and_then(|tags| tags.as_object())
+        .and_then(|tags| tags.iter().find(|(k, _)| k.eq_ignore_ascii_case(tag)))
+        .and_then(|(_, v)| v.as_str())
+        .and_then(parse_urn)
+}
+
+/// `Canonical:0001-com-ubuntu-server-jammy:22_04-lts-gen2[:version]`
+fn parse_urn(urn: &str) -> Option<ImageUrn> {
+    let parts: Vec<&str> = urn.split(':').map(str::trim).collect();
+    if parts.len() < 3 || parts[..3].iter().any(|p| p.is_empty()) {
+        return None;
+    }
+    Some((
+        parts[0].to_string(),
+        parts[1].to_string(),
+        parts[2].to_string(),
+    ))
+}
+
+pub fn is_not_found(e: &azure_core::Error) -> bool {
+    matches!(e.kind(), ErrorKind::HttpResponse { status, .. } if *status == StatusCode::NotFound)
+}
@@ -0,0 +1,181 @@
+use crate::inventory::arm::{ArmClient, ArmError};
+use crate::inventory::sql_vms::{self, SqlVirtualMachine};
+use crate::inventory::{scale_sets, vms, Inventory};
+use crate::logging::error;
+use azure_mgmt_compute::models::{VirtualMachine, VirtualMachineScaleSet};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const QUERY_PATH: &str = "/providers/Microsoft.ResourceGraph/resources?api-version=2021-03-01";
+
+// Rows per page, the maximum Resource Graph returns
+const PAGE_SIZE: u32 = 1000;
+
+const VM_QUERY: &str = "resources
+| where type =~ 'microsoft.compute/virtualmachines'
+| project id, name, type, location, tags, plan, properties";
+
+// Flexible scale set instances are returned by the VM query already
+const SCALE_SET_QUERY: &str = "resources
+| where type =~ 'microsoft.compute/virtualmachinescalesets'
+| where tostring(properties.orchestrationMode) !~ 'Flexible'
+| project id, name, type, location, tags, plan, sku, properties";
+
+const SQL_VM_QUERY: &str = "resources
+| where type =~ 'microsoft.sqlvirtualmachine/sqlvirtualmachines'
+| project properties";
+
+const POWER_STATE_QUERY: &str = "resources
+| where type =~ 'microsoft.compute/virtualmachines'
+| project id, code = tostring(properties.extended.instanceView.powerState.code)";
+
+const VM_COUNT_QUERY: &str = "resources
+| where type =~ 'microsoft.compute/virtualmachines'
+| summarize vms = count() by subscriptionId";
+
+// Subscriptions a single query may name
+const MAX_SUBSCRIPTIONS: usize = 1000;
+
+#[derive(Serialize)]
+struct QueryRequest<'a> {
+    subscriptions: &'a [String],
+    query: &'a str,
+    options: QueryOptions,
+}
+
+#[derive(Serialize)]
+struct QueryOptions {
+    #[serde(rename = "$top")]
+    top: u32,
+    #[serde(rename = "$skipToken", skip_serializing_if = "Option::is_none")]
+    skip_token: Option<String>,
+    #[serde(rename = "resultFormat")]
+    result_format: &'static str,
+}
+
+#[derive(Deserialize)]
+struct PowerStateRow {
+    id: String,
+    #[serde(default)]
+    code: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VmCountRow {
+    subscription_id: String,
+    vms: u64,
+}
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    #[serde(default)]
+    data: Vec<Value>,
+    #[serde(rename = "$skipToken")]
+    skip_token: Option<String>,
+}
+
+/// Runs `query` against `subscriptions`, following `$skipToken`. Rows
+/// failing to deserialize are logged and skipped rather than failing the
+/// whole query.
+pub async fn query<T: DeserializeOwned>(
+    arm: &ArmClient,
+    query: &str,
+    subscriptions: &[String],
+) -> Result<Vec<T>, ArmError> {
+    let mut rows = Vec::new();
+    for subscriptions in subscriptions.chunks(MAX_SUBSCRIPTIONS) {
+        let mut skip_token = None;
+        loop {
+            let request = QueryRequest {
+                subscriptions,
+                query,
+                options: QueryOptions {
+                    top: PAGE_SIZE,
+                    skip_token,
+                    result_format: "objectArray",
+                },
+            };
+            let response: QueryResponse = arm.post_json(QUERY_PATH, &request).await?;
+            for row in response.data {
+                let id = row["id"].as_str().unwrap_or_default().to_string();
+                match serde_json::from_value::<T>(row) {
+                    Ok(row) => rows.push(row),
+                    Err(e) => error!("Parsing Resource Graph row {} failed: {}", id, e),
+                }
+            }
+            skip_token = response.skip_token;
+            if skip_token.is_none() {
+                break;
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// VMs per subscription ID as Resource Graph has indexed them, which can
+/// trail recent changes slightly
+pub async fn count_vms(
+    arm: &ArmClient,
+    subscriptions: &[String],
+) -> Result<HashMap<String, u64>, ArmError> {
+    let rows = query::<VmCountRow>(arm, VM_COUNT_QUERY, subscriptions).await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.subscription_id, row.vms))
+        .collect())
+}
+
+/// `--backend resource-graph`: fetches the VMs, their power states, scale sets and
+/// SQL VM registrations of all `subscriptions` with a few paged queries instead of
+/// compute list calls per subscription. The rows carry the same properties
+/// as the compute API and are reported by the same code.
+pub async fn list_compute(inventory: &Inventory, subscriptions: &[String]) {
+    // A failed query leaves a hole in every subscription it covers
+    let failed = |what: &str, e: ArmError| {
+        for subscription_id in subscriptions {
+            inventory.listing_failed(subscription_id, what, &*e);
+        }
+    };
+    match query::<SqlVirtualMachine>(&inventory.arm, SQL_VM_QUERY, subscriptions).await {
+        Ok(registered) => sql_vms::remember(registered, inventory).await,
+        Err(e) => failed("SQL VMs", e),
+    }
+    match query::<PowerStateRow>(&inventory.arm, POWER_STATE_QUERY, subscriptions).await {
+        Ok(rows) => {
+            let mut known = inventory.power_states.lock().await;
+            for row in rows.into_iter().filter(|row| !row.code.is_empty()) {
+                known.insert(row.id.to_lowercase(), vms::power_state(&row.code));
+            }
+        }
+        Err(e) => failed("power states", e),
+    }
+    match query::<VirtualMachine>(&inventory.arm, VM_QUERY, subscriptions).await {
+        Ok(machines) => {
+            for vm in machines {
+                let subscription_id = subscription(vm.resource.id.as_deref());
+                vms::report_vm(vm, &subscription_id, inventory).await;
+            }
+        }
+        Err(e) => failed("VMs", e),
+    }
+    match query::<VirtualMachineScaleSet>(&inventory.arm, SCALE_SET_QUERY, subscriptions).await {
+        Ok(sets) => {
+            for scale_set in sets {
+                let subscription_id = subscription(scale_set.resource.id.as_deref());
+                scale_sets::report_scale_set(scale_set, &subscription_id, inventory).await;
+            }
+        }
+        Err(e) => failed("scale sets", e),
+    }
+}
+
+/// /subscriptions/{id}/resourceGroups/... -> {id}
+fn subscription(resource_id: Option<&str>) -> String {
+    resource_id
+        .and_then(|id| id.split('/').nth(2))
+        .unwrap_or_default()
+        .to_string()
+}
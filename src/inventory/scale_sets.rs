@@ -0,0 +1,115 @@
+use crate::inventory::{resolve_image, tags, vms, Inventory};
+use crate::logging::error;
+use crate::VMResult;
+use azure_mgmt_compute::models::os_disk::OsType;
+use azure_mgmt_compute::models::{
+    virtual_machine_scale_set_os_disk, OrchestrationMode, VirtualMachineScaleSet,
+};
+use futures::stream::{Stream, StreamExt};
+
+/// One row per Uniform scale set, all instances share the model's image
+/// reference. The guest OS isn't queried per instance with `--deep`.
+/// Flexible scale sets are reported per instance instead.
+pub async fn list_scale_sets(subscription_id: &String, inventory: &Inventory) {
+    let Ok(groups) = inventory.selected_resource_groups(subscription_id).await else {
+        return;
+    };
+    if let Some(groups) = groups {
+        for group in groups {
+            let scale_sets = inventory
+                .client
+                .virtual_machine_scale_sets_client()
+                .list(&group, subscription_id)
+                .into_stream()
+                .map(|page| page.map(|p| p.value));
+            report_pages(scale_sets, subscription_id, inventory).await;
+        }
+        return;
+    }
+    let scale_sets = inventory
+        .client
+        .virtual_machine_scale_sets_client()
+        .list_all(subscription_id)
+        .into_stream()
+        .map(|page| page.map(|p| p.value));
+    report_pages(scale_sets, subscription_id, inventory).await;
+}
+
+async fn report_pages(
+    scale_sets: impl Stream<Item = azure_core::Result<Vec<VirtualMachineScaleSet>>>,
+    subscription_id: &String,
+    inventory: &Inventory,
+) {
+    scale_sets
+        .for_each_concurrent(10, |scale_sets| async {
+            let scale_sets = match scale_sets {
+                Ok(scale_sets) => scale_sets,
+                Err(e) => {
+                    inventory.listing_failed(subscription_id, "scale sets", &e);
+                    return;
+                }
+            };
+            for scale_set in scale_sets {
+                report_scale_set(scale_set, subscription_id, inventory).await;
+            }
+        })
+        .await;
+}
+
+pub async fn report_scale_set(
+    scale_set: VirtualMachineScaleSet,
+    subscription_id: &String,
+    inventory: &Inventory,
+) {
+    let tags = tags::from_json(scale_set.resource.tags.as_ref());
+    let resource_id = scale_set.resource.id.unwrap_or_default();
+    let properties = scale_set.properties.unwrap_or_default();
+    if properties.orchestration_mode == Some(OrchestrationMode::Flexible) {
+        vms::list_scale_set_instances(subscription_id, &resource_id, inventory).await;
+        return;
+    }
+    let profile = match properties.virtual_machine_profile {
+        Some(p) => p,
+        None => {
+            error!("No VM profile found for: {}", resource_id);
+            return;
+        }
+    };
+    let storage_profile = profile.storage_profile.unwrap_or_default();
+    let image = resolve_image(inventory, storage_profile.image_reference, scale_set.plan).await;
+    let os_type = storage_profile
+        .os_disk
+        .and_then(|d| d.os_type)
+        .map(|os_type| match os_type {
+            virtual_machine_scale_set_os_disk::OsType::Windows => OsType::Windows,
+            virtual_machine_scale_set_os_disk::OsType::Linux => OsType::Linux,
+        })
+        .or_else(|| {
+            profile.os_profile.as_ref().and_then(|p| {
+                if p.windows_configuration.is_some() {
+                    Some(OsType::Windows)
+                } else if p.linux_configuration.is_some() {
+                    Some(OsType::Linux)
+                } else {
+                    None
+                }
+            })
+        });
+    // The size of every instance is the scale set's SKU name
+    let sku = scale_set.sku.unwrap_or_default();
+    let mut machine = VMResult {
+        id: resource_id,
+        resource_type: String::from("Scale set"),
+        instances: sku.capacity.unwrap_or_default(),
+        location: scale_set.resource.location,
+        subscription_id: subscription_id.clone(),
+        os_type,
+        license_type: profile.license_type.unwrap_or_default(),
+        tags,
+        vm_size: sku.name.unwrap_or_default(),
+        created: vms::created(properties.time_created),
+        ..Default::default()
+    };
+    image.apply(&mut machine);
+    inventory.send(machine).await;
+}
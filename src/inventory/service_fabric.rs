@@ -0,0 +1,219 @@
+use crate::inventory::Inventory;
+use crate::logging::error;
+use crate::VMResult;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Publisher set on Service Fabric cluster results
+pub const PUBLISHER: &str = "Microsoft.ServiceFabric";
+
+const API_VERSION: &str = "2021-06-01";
+const MANAGED_API_VERSION: &str = "2022-01-01";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Cluster {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub properties: ClusterProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterProperties {
+    #[serde(default)]
+    pub cluster_code_version: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct NodeType {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub properties: NodeTypeProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeTypeProperties {
+    #[serde(default)]
+    pub vm_image_publisher: String,
+    #[serde(default)]
+    pub vm_image_offer: String,
+    #[serde(default)]
+    pub vm_image_sku: String,
+    #[serde(default)]
+    pub vm_image_version: String,
+    #[serde(default)]
+    pub vm_instance_count: i64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ClusterVersion {
+    #[serde(default)]
+    pub properties: ClusterVersionProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterVersionProperties {
+    #[serde(default)]
+    pub code_version: String,
+    #[serde(default)]
+    pub support_expiry_utc: String,
+}
+
+/// One row per cluster for its runtime version, evaluated against the support
+/// expiry the resource provider publishes per version. The node types of
+/// classic clusters are regular scale sets and reported by the scale set
+/// scan, those of managed clusters get a row each.
+pub async fn list_clusters(subscription_id: &String, inventory: &Inventory) {
+    // Supported runtime versions per location
+    let mut versions: HashMap<String, Option<HashMap<String, NaiveDate>>> = HashMap::new();
+    for (resource, api_version, resource_type) in [
+        ("clusters", API_VERSION, "Service Fabric cluster"),
+        (
+            "managedClusters",
+            MANAGED_API_VERSION,
+            "Service Fabric managed cluster",
+        ),
+    ] {
+        let path = format!(
+            "/subscriptions/{}/providers/Microsoft.ServiceFabric/{}?api-version={}",
+            subscription_id, resource, api_version
+        );
+        let clusters = match inventory.arm.list::<Cluster>(&path).await {
+            Ok(clusters) => clusters,
+            Err(e) => {
+                let what = format!("{}s", resource_type);
+                inventory.listing_failed(subscription_id, &what, &*e);
+                continue;
+            }
+        };
+        for cluster in clusters {
+            let location = cluster.location.to_lowercase();
+            if !versions.contains_key(&location) {
+                let supported = supported_versions(subscription_id, &location, inventory).await;
+                versions.insert(location.clone(), supported);
+            }
+            let code_version = cluster.properties.cluster_code_version;
+            // Versions past their support expiry are dropped from the list
+            let support_end = versions[&location].as_ref().map(|supported| {
+                supported
+                    .get(&code_version)
+                    .copied()
+                    .unwrap_or(NaiveDate::MIN)
+            });
+            if resource == "managedClusters" {
+                list_node_types(subscription_id, &cluster.id, &cluster.location, inventory).await;
+            }
+            let machine = VMResult {
+                id: cluster.id,
+                resource_type: String::from(resource_type),
+                location: cluster.location,
+                subscription_id: subscription_id.clone(),
+                publisher: String::from(PUBLISHER),
+                offer: String::from("service-fabric"),
+                sku: code_version.clone(),
+                version: code_version,
+                support_end,
+                azure_deprecation: String::from("--"),
+                ..Default::default()
+            };
+            inventory.send(machine).await;
+        }
+    }
+}
+
+async fn list_node_types(
+    subscription_id: &str,
+    cluster_id: &str,
+    location: &str,
+    inventory: &Inventory,
+) {
+    let path = format!(
+        "{}/nodeTypes?api-version={}",
+        cluster_id, MANAGED_API_VERSION
+    );
+    let node_types = match inventory.arm.list::<NodeType>(&path).await {
+        Ok(node_types) => node_types,
+        Err(e) => {
+            error!("Listing node types failed for {}: {}", cluster_id, e);
+            return;
+        }
+    };
+    for node_type in node_types {
+        let properties = node_type.properties;
+        let machine = VMResult {
+            id: node_type.id,
+            resource_type: String::from("Service Fabric node type"),
+            instances: properties.vm_instance_count,
+            scale_set: cluster_id.to_string(),
+            location: location.to_string(),
+            subscription_id: subscription_id.to_string(),
+            publisher: properties.vm_image_publisher,
+            offer: properties.vm_image_offer,
+            sku: properties.vm_image_sku,
+            version: properties.vm_image_version,
+            ..Default::default()
+        };
+        inventory.send(machine).await;
+    }
+}
+
+async fn supported_versions(
+    subscription_id: &str,
+    location: &str,
+    inventory: &Inventory,
+) -> Option<HashMap<String, NaiveDate>> {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.ServiceFabric/locations/{}/clusterVersions?api-version={}",
+        subscription_id, location, API_VERSION
+    );
+    match inventory.arm.list::<ClusterVersion>(&path).await {
+        Ok(versions) => Some(
+            versions
+                .into_iter()
+                .filter_map(|v| {
+                    let expiry = parse_expiry(&v.properties.support_expiry_utc)?;
+                    Some((v.properties.code_version, expiry))
+                })
+                .collect(),
+        ),
+        Err(e) => {
+            error!(
+                "Listing Service Fabric versions failed for {}: {}",
+                location, e
+            );
+            None
+        }
+    }
+}
+
+/// `2024-06-30T00:00:00` or `9999-12-31T23:59:59.9999999`
+pub fn parse_expiry(expiry: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(expiry.get(..10)?, "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_expiry;
+
+    #[test]
+    fn test_expiry() {
+        assert_eq!(
+            parse_expiry("2024-06-30T00:00:00").unwrap().to_string(),
+            "2024-06-30"
+        );
+        assert_eq!(
+            parse_expiry("9999-12-31T23:59:59.9999999")
+                .unwrap()
+                .to_string(),
+            "9999-12-31"
+        );
+        assert_eq!(parse_expiry(""), None);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::inventory::Inventory;
+use crate::logging::error;
+use crate::VMResult;
+use serde::Deserialize;
+
+/// Publisher set on Spring Apps deployment results
+pub const PUBLISHER: &str = "Microsoft.AppPlatform";
+
+const API_VERSION: &str = "2022-12-01";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Resource {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub location: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Deployment {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub properties: DeploymentProperties,
+    pub sku: Option<Sku>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DeploymentProperties {
+    #[serde(default)]
+    pub source: Source,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+    pub runtime_version: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Sku {
+    #[serde(default)]
+    pub capacity: i64,
+}
+
+/// One row per deployment of every app. Only the language runtime is known,
+/// ARM doesn't expose the Spring Boot version an app is built with and
+/// deployments of the Enterprise tier (built from source) don't name their
+/// runtime either.
+pub async fn list_services(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.AppPlatform/Spring?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let services = match inventory.arm.list::<Resource>(&path).await {
+        Ok(services) => services,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "Spring Apps services", &*e);
+            return;
+        }
+    };
+    for service in services {
+        let path = format!("{}/apps?api-version={}", service.id, API_VERSION);
+        let apps = match inventory.arm.list::<Resource>(&path).await {
+            Ok(apps) => apps,
+            Err(e) => {
+                error!("Listing apps failed for {}: {}", service.id, e);
+                continue;
+            }
+        };
+        for app in apps {
+            let path = format!("{}/deployments?api-version={}", app.id, API_VERSION);
+            let deployments = match inventory.arm.list::<Deployment>(&path).await {
+                Ok(deployments) => deployments,
+                Err(e) => {
+                    error!("Listing deployments failed for {}: {}", app.id, e);
+                    continue;
+                }
+            };
+            for deployment in deployments {
+                let runtime_version = deployment
+                    .properties
+                    .source
+                    .runtime_version
+                    .unwrap_or_default();
+                let (offer, sku) = runtime(&runtime_version).unwrap_or_default();
+                let machine = VMResult {
+                    id: deployment.id,
+                    resource_type: String::from("Spring Apps deployment"),
+                    instances: deployment.sku.map(|s| s.capacity).unwrap_or_default(),
+                    scale_set: service.id.clone(),
+                    location: service.location.clone(),
+                    subscription_id: subscription_id.clone(),
+                    publisher: String::from(PUBLISHER),
+                    offer,
+                    sku,
+                    version: runtime_version,
+                    azure_deprecation: String::from("--"),
+                    ..Default::default()
+                };
+                inventory.send(machine).await;
+            }
+        }
+    }
+}
+
+/// Java_17 -> (java, 17), NetCore_31 -> (dotnet, 3.1)
+pub fn runtime(runtime_version: &str) -> Option<(String, String)> {
+    let (runtime, version) = runtime_version.split_once('_')?;
+    match runtime.to_lowercase().as_str() {
+        "java" => Some((String::from("java"), version.to_string())),
+        "netcore" if version.len() == 2 => {
+            let (major, minor) = version.split_at(1);
+            Some((String::from("dotnet"), format!("{}.{}", major, minor)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::runtime;
+
+    #[test]
+    fn test_runtime() {
+        assert_eq!(
+            runtime("Java_17"),
+            Some((String::from("java"), String::from("17")))
+        );
+        assert_eq!(
+            runtime("NetCore_31"),
+            Some((String::from("dotnet"), String::from("3.1")))
+        );
+        assert_eq!(runtime(""), None);
+    }
+}
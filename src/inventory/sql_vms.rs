@@ -0,0 +1,52 @@
+use crate::inventory::Inventory;
+use serde::Deserialize;
+
+const API_VERSION: &str = "2022-02-01";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SqlVirtualMachine {
+    #[serde(default)]
+    pub properties: SqlVirtualMachineProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlVirtualMachineProperties {
+    #[serde(default)]
+    pub virtual_machine_resource_id: String,
+    #[serde(default)]
+    pub sql_image_offer: String,
+}
+
+/// Remembers the SQL Server offer (SQL2019-WS2022) of every VM registered
+/// with the SQL IaaS Agent extension, keyed by lowercased VM resource ID.
+/// Registering the extension creates the SQL virtual machine resource, so
+/// this covers VMs where SQL Server was installed on a plain OS image too.
+pub async fn list_sql_vms(subscription_id: &String, inventory: &Inventory) {
+    let path = format!(
+        "/subscriptions/{}/providers/Microsoft.SqlVirtualMachine/sqlVirtualMachines?api-version={}",
+        subscription_id, API_VERSION
+    );
+    let sql_vms = match inventory.arm.list::<SqlVirtualMachine>(&path).await {
+        Ok(sql_vms) => sql_vms,
+        Err(e) => {
+            inventory.listing_failed(subscription_id, "SQL VMs", &*e);
+            return;
+        }
+    };
+    remember(sql_vms, inventory).await;
+}
+
+pub async fn remember(sql_vms: Vec<SqlVirtualMachine>, inventory: &Inventory) {
+    let mut known = inventory.sql_vms.lock().await;
+    for sql_vm in sql_vms {
+        let properties = sql_vm.properties;
+        if properties.sql_image_offer.is_empty() {
+            continue;
+        }
+        known.insert(
+            properties.virtual_machine_resource_id.to_lowercase(),
+            properties.sql_image_offer,
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Resources tagged like this are left out of the report, e.g. machines
+/// scheduled for decommissioning
+pub const EXEMPT_TAG: &str = "azindex:exempt";
+
+/// `key=value`, or just `key` to match any value. Tag names are
+/// case-insensitive in Azure, values are compared case-insensitively too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagSelector {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl FromStr for TagSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = match s.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim().to_string())),
+            None => (s.trim(), None),
+        };
+        if key.is_empty() {
+            return Err(format!("invalid tag '{}', expected key=value", s));
+        }
+        Ok(TagSelector {
+            key: key.to_string(),
+            value,
+        })
+    }
+}
+
+impl TagSelector {
+    pub fn matches(&self, tags: &HashMap<String, String>) -> bool {
+        tags.iter().any(|(key, value)| {
+            key.eq_ignore_ascii_case(&self.key)
+                && self
+                    .value
+                    .as_ref()
+                    .is_none_or(|wanted| wanted.eq_ignore_ascii_case(value))
+        })
+    }
+}
+
+/// `--include-tag` keeps resources carrying any of the tags, `--exclude-tag`
+/// and the exempt tag drop them.
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    pub include: Vec<TagSelector>,
+    pub exclude: Vec<TagSelector>,
+}
+
+impl TagFilter {
+    pub fn matches(&self, tags: &HashMap<String, String>) -> bool {
+        let exempt = TagSelector {
+            key: EXEMPT_TAG.to_string(),
+            value: Some(String::from("true")),
+        };
+        (self.include.is_empty() || self.include.iter().any(|t| t.matches(tags)))
+            && !self.exclude.iter().any(|t| t.matches(tags))
+            && !exempt.matches(tags)
+    }
+}
+
+/// Tags as returned by ARM, a JSON object of strings
+pub fn from_json(tags: Option<&serde_json::Value>) -> HashMap<String, String> {
+    tags.and_then(|tags| tags.as_object())
+        .map(|tags| {
+            tags.iter()
+                .map(|(k, v)| (k.clone(), v.as_str().unwrap_or_default().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TagFilter, TagSelector};
+    use std::collections::HashMap;
+
+    fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_selector() {
+        let selector: TagSelector = "env=prod".parse().unwrap();
+        assert!(selector.matches(&tags(&[("Env", "Prod")])));
+        assert!(!selector.matches(&tags(&[("env", "dev")])));
+        let any: TagSelector = "owner".parse().unwrap();
+        assert!(any.matches(&tags(&[("owner", "team-a")])));
+        assert!("=prod".parse::<TagSelector>().is_err());
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = TagFilter {
+            include: vec!["env=prod".parse().unwrap()],
+            exclude: vec!["decommission".parse().unwrap()],
+        };
+        assert!(filter.matches(&tags(&[("env", "prod")])));
+        assert!(!filter.matches(&tags(&[("env", "dev")])));
+        assert!(!filter.matches(&tags(&[("env", "prod"), ("decommission", "2024-06")])));
+        assert!(!filter.matches(&tags(&[("env", "prod"), ("azindex:exempt", "true")])));
+        assert!(TagFilter::default().matches(&HashMap::new()));
+    }
+}
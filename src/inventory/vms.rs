@@ -0,0 +1,283 @@
+use crate::inventory::{resolve_image, tags, Inventory};
+use crate::logging::error;
+use crate::VMResult;
+use azure_mgmt_compute::models::os_disk::OsType;
+use azure_mgmt_compute::models::{VirtualMachine, VirtualMachineListResult};
+use chrono::NaiveDate;
+use futures::stream::{Stream, StreamExt};
+use time::{OffsetDateTime, UtcOffset};
+
+pub async fn list_vms(subscription_id: &String, inventory: &Inventory) {
+    let Ok(groups) = inventory.selected_resource_groups(subscription_id).await else {
+        return;
+    };
+    if let Some(groups) = groups {
+        for group in groups {
+            let vms = inventory
+                .client
+                .virtual_machines_client()
+                .list(&group, subscription_id)
+                .into_stream();
+            report_pages(vms, subscription_id, inventory).await;
+        }
+        return;
+    }
+    let vms = inventory
+        .client
+        .virtual_machines_client()
+        .list_all(subscription_id)
+        .into_stream();
+    report_pages(vms, subscription_id, inventory).await;
+}
+
+async fn report_pages(
+    vms: impl Stream<Item = azure_core::Result<VirtualMachineListResult>>,
+    subscription_id: &str,
+    inventory: &Inventory,
+) {
+    vms.for_each_concurrent(10, |vms| async {
+        match vms {
+            Ok(vms) => {
+                for vm in vms.value {
+                    report_vm(vm, subscription_id, inventory).await;
+                }
+            }
+            Err(e) => inventory.listing_failed(subscription_id, "VMs", &e),
+        }
+    })
+    .await;
+}
+
+/// Remembers the power state (running, deallocated) of every VM, keyed by
+/// lowercased resource ID. The statusOnly listing only carries the instance
+/// view, so this is a separate call. Only the subscription-wide listing has
+/// statusOnly, VMs outside `--resource-group` and the tag filter are dropped
+/// from it instead of being listed per resource group like `list_vms` does.
+pub async fn list_power_states(subscription_id: &String, inventory: &Inventory) {
+    let mut vms = inventory
+        .client
+        .virtual_machines_client()
+        .list_all(subscription_id)
+        .status_only("true")
+        .into_stream();
+    let filter = &inventory.filter;
+    while let Some(page) = vms.next().await {
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                inventory.listing_failed(subscription_id, "power states", &e);
+                return;
+            }
+        };
+        let mut known = inventory.power_states.lock().await;
+        for vm in page.value {
+            let id = vm.resource.id.unwrap_or_default();
+            let resource_group = id.split('/').nth(4).unwrap_or_default();
+            // statusOnly may leave the tags out, those VMs are kept
+            let tagged = vm.resource.tags.is_none()
+                || filter
+                    .tags
+                    .matches(&tags::from_json(vm.resource.tags.as_ref()));
+            if !filter.wants_resource_group(resource_group) || !tagged {
+                continue;
+            }
+            let code = vm
+                .properties
+                .and_then(|p| p.instance_view)
+                .and_then(|view| {
+                    view.statuses
+                        .into_iter()
+                        .filter_map(|status| status.code)
+                        .find(|code| code.starts_with("PowerState/"))
+                });
+            if let (false, Some(code)) = (id.is_empty(), code) {
+                known.insert(id.to_lowercase(), power_state(&code));
+            }
+        }
+    }
+}
+
+/// PowerState/deallocated -> deallocated
+pub fn power_state(code: &str) -> String {
+    code.trim_start_matches("PowerState/").to_string()
+}
+
+/// timeCreated as a UTC date, missing on VMs older than the property
+pub fn created(time_created: Option<OffsetDateTime>) -> Option<NaiveDate> {
+    let time = time_created?.to_offset(UtcOffset::UTC);
+    NaiveDate::from_ymd_opt(time.year(), time.month() as u32, time.day() as u32)
+}
+
+/// Instances of a Flexible orchestration scale set are regular VMs that can
+/// each run a different image, they are reported individually.
+pub async fn list_scale_set_instances(
+    subscription_id: &String,
+    scale_set_id: &str,
+    inventory: &Inventory,
+) {
+    let resource_group = match scale_set_id.split('/').nth(4) {
+        Some(rg) => rg,
+        None => return,
+    };
+    let vms = inventory
+        .client
+        .virtual_machines_client()
+        .list(resource_group, subscription_id)
+        .filter(format!("virtualMachineScaleSet/id eq '{}'", scale_set_id))
+        .into_stream();
+    vms.for_each_concurrent(10, |vms| async {
+        match vms {
+            Ok(vms) => {
+                for vm in vms.value {
+                    report_vm(vm, subscription_id, inventory).await;
+                }
+            }
+            Err(e) => error!("Listing instances of {} failed: {}", scale_set_id, e),
+        }
+    })
+    .await;
+}
+
+pub async fn report_vm(vm: VirtualMachine, subscription_id: &str, inventory: &Inventory) {
+    let client = &inventory.client;
+    let properties = match vm.properties {
+        Some(p) => p,
+        None => {
+            error!(
+                "No properties found for: {}",
+                vm.resource.id.unwrap_or_default()
+            );
+            return;
+        }
+    };
+    let storage_profile = match properties.storage_profile {
+        Some(p) => p,
+        None => {
+            error!(
+                "No storage profile found for: {}",
+                vm.resource.id.unwrap_or_default()
+            );
+            return;
+        }
+    };
+    let os_disk = match storage_profile.os_disk {
+        Some(p) => p,
+        None => {
+            error!(
+                "No OS disk found for: {}",
+                vm.resource.id.unwrap_or_default()
+            );
+            return;
+        }
+    };
+    let image = resolve_image(inventory, storage_profile.image_reference, vm.plan).await;
+    // Specialized disks and custom images carry no marketplace
+    // reference, the OS profile still tells Linux from Windows.
+    let os_type = os_disk.os_type.or_else(|| {
+        properties.os_profile.as_ref().and_then(|p| {
+            if p.windows_configuration.is_some() {
+                Some(OsType::Windows)
+            } else if p.linux_configuration.is_some() {
+                Some(OsType::Linux)
+            } else {
+                None
+            }
+        })
+    });
+    let scale_set = properties
+        .virtual_machine_scale_set
+        .and_then(|s| s.id)
+        .unwrap_or_default();
+
+    // The size enum is deprecated, its serialized name holds sizes it
+    // doesn't list too
+    let vm_size = properties
+        .hardware_profile
+        .and_then(|p| p.vm_size)
+        .and_then(|size| serde_json::to_value(size).ok())
+        .and_then(|size| size.as_str().map(String::from))
+        .unwrap_or_default();
+    let created = created(properties.time_created);
+    let tags = tags::from_json(vm.resource.tags.as_ref());
+    let resource_id = vm.resource.id.unwrap_or_default();
+    // info!("Found VM: {}", &resource_id);
+    let (guest_os_name, guest_os_version) = if inventory.deep {
+        guest_os(client, subscription_id, &resource_id).await
+    } else {
+        (String::new(), String::new())
+    };
+    let sql_server = inventory
+        .sql_vms
+        .lock()
+        .await
+        .get(&resource_id.to_lowercase())
+        .cloned()
+        .unwrap_or_default();
+    let mut machine = VMResult {
+        id: resource_id,
+        resource_type: if scale_set.is_empty() {
+            String::from("Virtual machine")
+        } else {
+            String::from("Scale set instance")
+        },
+        instances: 1,
+        scale_set,
+        location: vm.resource.location,
+        subscription_id: subscription_id.to_string(),
+        os_type,
+        guest_os_name,
+        guest_os_version,
+        license_type: properties.license_type.unwrap_or_default(),
+        sql_server,
+        tags,
+        vm_size,
+        created,
+        ..Default::default()
+    };
+    image.apply(&mut machine);
+    inventory.send(machine).await;
+}
+
+/// osName and osVersion reported by the VM agent, empty when the agent
+/// isn't running or doesn't report them.
+async fn guest_os(
+    client: &azure_mgmt_compute::Client,
+    subscription_id: &str,
+    resource_id: &str,
+) -> (String, String) {
+    let parts: Vec<&str> = resource_id.split('/').collect();
+    if parts.len() < 9 {
+        return (String::new(), String::new());
+    }
+    match client
+        .virtual_machines_client()
+        .instance_view(parts[4], parts[8], subscription_id)
+        .await
+    {
+        Ok(view) => (
+            view.os_name.unwrap_or_default(),
+            view.os_version.unwrap_or_default(),
+        ),
+        Err(e) => {
+            error!("Fetching instance view failed for {}: {}", resource_id, e);
+            (String::new(), String::new())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::created;
+    use chrono::NaiveDate;
+    use time::{OffsetDateTime, UtcOffset};
+
+    #[test]
+    fn test_created() {
+        // 2021-01-01T00:00:00Z, returned with another offset
+        let time = OffsetDateTime::from_unix_timestamp(1_609_459_200)
+            .unwrap()
+            .to_offset(UtcOffset::from_hms(-5, 0, 0).unwrap());
+        assert_eq!(created(Some(time)), NaiveDate::from_ymd_opt(2021, 1, 1));
+        assert_eq!(created(None), None);
+    }
+}
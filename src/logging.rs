@@ -0,0 +1,297 @@
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use tracing::field::{Field, Visit};
+use tracing::span;
+
+/// How much is logged, from `--quiet` to `-vv`. Warnings and errors are
+/// always shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Quiet,
+    Normal,
+    // -v: what azindex decided and why, e.g. versions it couldn't parse
+    Verbose,
+    // -vv: the resources behind those decisions
+    Trace,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
+/// `--log-format`, paris' colored lines for people or one JSON object per
+/// line for log pipelines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("{} isn't text or json", s)),
+        }
+    }
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Switches to JSON, events then go through `tracing` to a subscriber
+/// writing them to stderr
+pub fn set_format(format: Format) -> Result<(), Box<dyn std::error::Error>> {
+    if format == Format::Json {
+        tracing::subscriber::set_global_default(JsonSubscriber)?;
+        JSON.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Info,
+    Success,
+    Warn,
+    Error,
+    Debug,
+    Trace,
+}
+
+/// paris' logger, dropping messages below the level set with `set_level`.
+/// Indented lines follow the message before them.
+pub struct Logger {
+    inner: paris::Logger<'static>,
+    indent: usize,
+    // Kind of the last message and whether it was shown, for the details
+    // logged after it
+    last: Kind,
+    shown: bool,
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Logger {
+            inner: paris::Logger::new(),
+            indent: 0,
+            last: Kind::Info,
+            shown: enabled(Level::Normal),
+        }
+    }
+}
+
+impl Logger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn write<T: Display>(
+        &mut self,
+        kind: Kind,
+        shown: bool,
+        message: T,
+        detail: bool,
+    ) -> &mut Self {
+        self.last = kind;
+        self.shown = shown;
+        let indent = std::mem::take(&mut self.indent);
+        if !shown {
+            return self;
+        }
+        if is_json() {
+            emit(kind, &message.to_string(), detail);
+            return self;
+        }
+        if indent > 0 {
+            self.inner.indent(indent);
+        }
+        match (kind, detail) {
+            (_, true) | (Kind::Trace, _) => self.inner.log(message),
+            (Kind::Info, _) => self.inner.info(message),
+            (Kind::Success, _) => self.inner.success(message),
+            (Kind::Warn, _) => self.inner.warn(message),
+            (Kind::Error, _) => self.inner.error(message),
+            (Kind::Debug, _) => self.inner.log(format!("<magenta>·</> {}", message)),
+        };
+        self
+    }
+
+    pub fn indent(&mut self, amount: usize) -> &mut Self {
+        self.indent = amount;
+        self
+    }
+
+    /// A detail of the message before it
+    pub fn log<T: Display>(&mut self, message: T) -> &mut Self {
+        self.write(self.last, self.shown, message, true)
+    }
+
+    pub fn info<T: Display>(&mut self, message: T) -> &mut Self {
+        self.write(Kind::Info, enabled(Level::Normal), message, false)
+    }
+
+    pub fn success<T: Display>(&mut self, message: T) -> &mut Self {
+        self.write(Kind::Success, enabled(Level::Normal), message, false)
+    }
+
+    pub fn warn<T: Display>(&mut self, message: T) -> &mut Self {
+        self.write(Kind::Warn, true, message, false)
+    }
+
+    pub fn error<T: Display>(&mut self, message: T) -> &mut Self {
+        self.write(Kind::Error, true, message, false)
+    }
+
+    pub fn debug<T: Display>(&mut self, message: T) -> &mut Self {
+        self.write(Kind::Debug, enabled(Level::Verbose), message, false)
+    }
+
+    pub fn trace<T: Display>(&mut self, message: T) -> &mut Self {
+        self.write(Kind::Trace, enabled(Level::Trace), message, false)
+    }
+}
+
+fn emit(kind: Kind, message: &str, detail: bool) {
+    match kind {
+        Kind::Info | Kind::Success => tracing::info!(detail, "{}", message),
+        Kind::Warn => tracing::warn!(detail, "{}", message),
+        Kind::Error => tracing::error!(detail, "{}", message),
+        Kind::Debug => tracing::debug!(detail, "{}", message),
+        Kind::Trace => tracing::trace!(detail, "{}", message),
+    }
+}
+
+/// Writes events as JSON lines: timestamp, level, target, message and the
+/// other fields. Dependencies only get their warnings and errors through.
+struct JsonSubscriber;
+
+impl tracing::Subscriber for JsonSubscriber {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        metadata.target().starts_with("azindex") || *metadata.level() <= tracing::Level::WARN
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        // Spans aren't recorded, every event stands on its own
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        let mut line = Map::new();
+        line.insert(
+            String::from("timestamp"),
+            Value::from(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        );
+        line.insert(
+            String::from("level"),
+            Value::from(event.metadata().level().as_str()),
+        );
+        line.insert(
+            String::from("target"),
+            Value::from(event.metadata().target()),
+        );
+        line.extend(fields.0);
+        let mut stderr = std::io::stderr().lock();
+        _ = writeln!(stderr, "{}", Value::Object(line));
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[derive(Default)]
+struct JsonFields(Map<String, Value>);
+
+impl Visit for JsonFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            Value::from(format!("{:?}", value)),
+        );
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => {{
+        $crate::logging::Logger::new().error(format!($($arg)*));
+    }};
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {{
+        $crate::logging::Logger::new().warn(format!($($arg)*));
+    }};
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => {{
+        $crate::logging::Logger::new().debug(format!($($arg)*));
+    }};
+}
+
+macro_rules! log_trace {
+    ($($arg:tt)*) => {{
+        $crate::logging::Logger::new().trace(format!($($arg)*));
+    }};
+}
+
+// `warn` alone would clash with the lint attribute
+pub(crate) use {log_debug as debug, log_error as error, log_trace as trace, log_warn as warn};
+
+#[cfg(test)]
+mod test {
+    use super::{enabled, Format, Level};
+
+    #[test]
+    fn test_levels() {
+        assert!(Level::Quiet < Level::Normal && Level::Verbose < Level::Trace);
+        // The default until main sets one
+        assert!(enabled(Level::Normal) && !enabled(Level::Verbose));
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!("JSON".parse::<Format>(), Ok(Format::Json));
+        assert!("yaml".parse::<Format>().is_err());
+    }
+}
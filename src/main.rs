@@ -1,19 +1,51 @@
+mod auth;
+mod cache;
+mod checkpoint;
+mod config;
+mod diff;
 mod eol_detection;
+mod http;
+mod inventory;
+mod logging;
+mod progress;
+mod subscriptions;
+mod summary;
+mod unclassified;
 mod vmresult;
 
-use azure_identity::AzureCliCredential;
-use clap::Parser;
-use futures::stream::StreamExt;
-use paris::{error, info, Logger};
+use crate::logging::{error, warn, Level, Logger};
+use clap::builder::BoolishValueParser;
+use clap::{ArgAction, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufWriter, Write};
-use std::path::PathBuf;
-use std::process::exit;
-use tokio::sync::mpsc::{Receiver, Sender};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
 use tokio::sync::{mpsc, Mutex};
 use xlsxwriter::prelude::*;
 
-use eol_detection::{centos, redhat, ubuntu, windows};
+use auth::{Auth, AuthOptions};
+use azure_core::auth::TokenCredential;
+use cache::Cache;
+use checkpoint::{Checkpoint, Resumed};
+use config::Config;
+use eol_detection::detector::{
+    report_mismatches, set_warn_within, Detection, OsDetector, Verdict, WarnWindow,
+};
+use eol_detection::eol::{self, fetch_products, load_eol_file, EolData, PinnedData, Provenance};
+use eol_detection::LazyEolData;
+use inventory::arm::{ArmClient, Cloud};
+use inventory::filter::{ResultFilter, RowFilter};
+use inventory::tags::{TagFilter, TagSelector};
+use inventory::Inventory;
+use subscriptions::{Skipped, Subscription, SubscriptionFilter};
+use summary::Summary;
+use unclassified::Unclassified;
 use vmresult::VMResult;
 
 #[derive(Parser, Debug)]
@@ -24,11 +56,329 @@ use vmresult::VMResult;
     long_about = None
 )]
 pub struct Cli {
+    /// Log more detail, -vv also logs the resources behind it
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Only log warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// text, or json for one event per line on stderr
+    #[arg(long, default_value = "text", global = true)]
+    pub log_format: logging::Format,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+// Parsed once, the size doesn't matter
+#[allow(clippy::large_enum_variant)]
+pub enum Command {
+    /// Scan the tenant and write the report, or save the raw inventory with
+    /// --cache to report on later
+    #[command(
+        mut_arg("format", |a| a.required(false).required_unless_present("cache")),
+        mut_arg("out", |a| a.required(false).required_unless_present("cache"))
+    )]
+    Scan {
+        /// TOML file with additional image mappings
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        #[command(flatten)]
+        scan: ScanArgs,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// JSON file the inventory is written to instead of a report
+        #[arg(long, conflicts_with = "format")]
+        cache: Option<PathBuf>,
+        // `None` with --cache
+        #[command(flatten)]
+        report: Option<ReportArgs>,
+    },
+    /// Report an inventory saved by `scan --cache` without querying Azure
+    /// again
+    Report {
+        /// TOML file with additional image mappings
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// JSON file written by `scan --cache`
+        #[arg(long)]
+        from_cache: PathBuf,
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+    /// Compare two inventories saved by `scan --cache`: resources added,
+    /// removed and redeployed from another image
+    Diff { old: PathBuf, new: PathBuf },
+    /// List the subscriptions a scan would cover, with a VM count from
+    /// Resource Graph, without scanning them
+    ListSubscriptions {
+        #[command(flatten)]
+        azure: AzureArgs,
+    },
+    /// Classify a single image and print the verdict, e.g. to validate image
+    /// choices before deployment. Exits with 1 when the image is EOL and 2
+    /// when azindex doesn't recognize it.
+    Check {
+        /// publisher:offer:sku:version, as `az vm image list` prints it
+        urn: String,
+        /// TOML file with additional image mappings
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        #[command(flatten)]
+        eol: EolArgs,
+    },
+    /// Manage the EOL data azindex uses
+    #[command(subcommand)]
+    Eol(EolCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EolCommand {
+    /// Fetch every supported product into a file for `--eol-data`
+    Update {
+        file: PathBuf,
+        /// Base URL of an endoflife.date API mirror
+        #[arg(long, default_value = eol::DEFAULT_ENDPOINT)]
+        eol_endpoint: String,
+    },
+    /// Download every supported product into a directory, e.g.
+    /// eol-snapshot/ before building for --offline
+    Snapshot {
+        dir: PathBuf,
+        /// Base URL of an endoflife.date API mirror
+        #[arg(long, default_value = eol::DEFAULT_ENDPOINT)]
+        eol_endpoint: String,
+    },
+}
+
+/// What is scanned
+#[derive(Args, Debug)]
+pub struct ScanArgs {
+    /// Query the instance view of every VM and classify it by the OS the VM
+    /// agent reports instead of the image it was created from
+    #[arg(long)]
+    pub deep: bool,
+    /// Also list Container Instances and classify every container by the base
+    /// OS its image tag names (centos7, ubuntu18.04)
+    #[arg(long)]
+    pub containers: bool,
+    /// Audit Compute Gallery image versions instead of deployed resources,
+    /// classified by the base image they were built from
+    #[arg(long)]
+    pub images: bool,
+    /// Where the VM and scale set inventory comes from: `compute` lists them
+    /// per subscription, `resource-graph` queries the whole tenant at once
+    #[arg(long, default_value = "compute")]
+    pub backend: Backend,
+    #[command(flatten)]
+    pub azure: AzureArgs,
+    /// Gives up on the remaining resources of a subscription after this long
+    #[arg(long, value_parser = http::parse_duration)]
+    pub subscription_timeout: Option<Duration>,
+    /// Continue an interrupted scan from the state file it left next to the
+    /// output, skipping the subscriptions it finished
+    #[arg(long)]
+    pub resume: bool,
+}
+
+/// How Azure is signed in to and queried, and which subscriptions
+#[derive(Args, Debug)]
+pub struct AzureArgs {
+    /// Attempts per request, throttled (429) and transient (5xx) failures are
+    /// retried honoring Retry-After
+    #[arg(long, default_value_t = 5)]
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further one
+    #[arg(long, default_value = "1s", value_parser = http::parse_duration)]
+    pub retry_backoff: Duration,
+    /// Gives up on a single HTTP request after this long, e.g. 30s or 2m
+    #[arg(long, default_value = "2m", value_parser = http::parse_duration)]
+    pub timeout: Duration,
+    /// Proxy for Azure and endoflife.date requests, HTTPS_PROXY is honored
+    /// without it
+    #[arg(long)]
+    pub proxy: Option<String>,
+    /// PEM bundle of additional CA certificates to trust, e.g. the root of a
+    /// TLS-intercepting proxy
+    #[arg(long)]
+    pub ca_bundle: Option<PathBuf>,
+    /// azure-public, azure-government or azure-china, the Azure CLI has to
+    /// be logged in to the same cloud (`az cloud set`)
+    #[arg(long, default_value = "azure-public")]
+    pub cloud: Cloud,
+    /// workload-identity, environment, managed-identity, azure-cli,
+    /// client-certificate or device-code. By default the first available of
+    /// the first four is used, client-certificate with --client-cert.
+    #[arg(long)]
+    pub auth: Option<Auth>,
+    /// Sign in as a service principal with this certificate (.pfx or PEM with
+    /// the private key) instead of using the Azure CLI's login
+    #[arg(long)]
+    pub client_cert: Option<PathBuf>,
+    /// Password of the certificate, AZURE_CLIENT_CERTIFICATE_PASSWORD is used
+    /// without it
+    #[arg(long)]
+    pub client_cert_password: Option<String>,
+    /// Tenant to sign in to instead of the Azure CLI's default one. Tenant of
+    /// the service principal, AZURE_TENANT_ID is used without it. Can be
+    /// repeated to scan several tenants into one report.
+    #[arg(long = "tenant-id", visible_alias = "tenant")]
+    pub tenant_ids: Vec<String>,
+    /// File listing tenants to scan, one ID per line, in addition to
+    /// --tenant-id
+    #[arg(long)]
+    pub tenants_file: Option<PathBuf>,
+    /// Application (client) ID of the service principal or user-assigned
+    /// managed identity, AZURE_CLIENT_ID is used without it
+    #[arg(long)]
+    pub client_id: Option<String>,
+    /// Only scan this subscription, by ID or name, can be repeated
+    #[arg(long = "subscription")]
+    pub subscriptions: Vec<String>,
+    /// Skip this subscription, by ID or name, can be repeated
+    #[arg(long = "exclude-subscription")]
+    pub exclude_subscriptions: Vec<String>,
+}
+
+/// Which resources are reported
+#[derive(Args, Debug)]
+pub struct FilterArgs {
+    /// Only scan resource groups matching this name, `*` matches anything,
+    /// can be repeated
+    #[arg(long = "resource-group")]
+    pub resource_groups: Vec<String>,
+    /// Only report resources in these regions, e.g. westeurope,northeurope
+    #[arg(long = "location", value_delimiter = ',')]
+    pub locations: Vec<String>,
+    /// Only report resources tagged key=value (or just key), can be repeated
+    #[arg(long = "include-tag")]
+    pub include_tags: Vec<TagSelector>,
+    /// Leave out resources tagged key=value (or just key), can be repeated.
+    /// Resources tagged azindex:exempt=true are always left out.
+    #[arg(long = "exclude-tag")]
+    pub exclude_tags: Vec<TagSelector>,
+    /// Only report resources whose ID matches this regex, e.g. `-prod-`, can
+    /// be repeated. The ID ends in the resource name.
+    #[arg(long = "match")]
+    pub matches: Vec<Regex>,
+    /// Leave out resources whose ID matches this regex, can be repeated
+    #[arg(long = "exclude")]
+    pub excludes: Vec<Regex>,
+    /// Leave out deallocated VMs, they aren't running the EOL OS right now
+    #[arg(long)]
+    pub skip_deallocated: bool,
+}
+
+impl FilterArgs {
+    fn result_filter(&self) -> ResultFilter {
+        ResultFilter {
+            resource_groups: self
+                .resource_groups
+                .iter()
+                .map(|g| g.to_lowercase())
+                .collect(),
+            locations: self
+                .locations
+                .iter()
+                .map(|l| inventory::normalize_location(l))
+                .collect(),
+            tags: TagFilter {
+                include: self.include_tags.clone(),
+                exclude: self.exclude_tags.clone(),
+            },
+            matches: self.matches.clone(),
+            excludes: self.excludes.clone(),
+            skip_deallocated: self.skip_deallocated,
+        }
+    }
+}
+
+/// How results are classified and written
+#[derive(Args, Debug)]
+pub struct ReportArgs {
     #[arg(short, long)]
     pub format: OutputType,
+    #[command(flatten)]
+    pub eol: EolArgs,
+    /// Exit with 1 when any resource is EOL, e.g. to fail a pipeline
+    #[arg(long)]
+    pub fail_on_eol: bool,
+    /// Exit with 2 when azindex couldn't give a verdict for a resource:
+    /// unclassified images, custom images and versions without a cycle
+    #[arg(long)]
+    pub fail_on_unknown: bool,
+    /// Exit with 1 when any resource is EOL or reaches it within this window,
+    /// e.g. 90d. Widens --warn-within if it is longer.
+    #[arg(long)]
+    pub fail_if_eol_within: Option<WarnWindow>,
+    /// Only write rows with these verdicts, e.g. eol,ending,unknown. The
+    /// summary still counts every resource.
+    #[arg(long, value_delimiter = ',')]
+    pub only: Vec<Verdict>,
+    /// Only write rows of these operating systems, endoflife.date products
+    /// like ubuntu or windowsserver (a prefix is enough: windows), or linux
+    #[arg(long, value_delimiter = ',')]
+    pub os: Vec<String>,
+    /// Write this tag as its own column instead of all tags in one, can be
+    /// repeated, e.g. --tag-column owner --tag-column costcenter
+    #[arg(long = "tag-column")]
+    pub tag_columns: Vec<String>,
+    /// Leave out rows of these operating systems, see --os
+    #[arg(long, value_delimiter = ',')]
+    pub exclude_os: Vec<String>,
     pub out: PathBuf,
 }
 
+impl ReportArgs {
+    fn row_filter(&self) -> RowFilter {
+        let lowercase = |values: &[String]| values.iter().map(|v| v.to_lowercase()).collect();
+        RowFilter {
+            only: self.only.clone(),
+            os: lowercase(&self.os),
+            exclude_os: lowercase(&self.exclude_os),
+        }
+    }
+}
+
+/// Where EOL data comes from and how verdicts are made
+#[derive(Args, Debug)]
+pub struct EolArgs {
+    /// JSON or TOML file with custom products and cycles, used alongside the
+    /// endoflife.date data
+    #[arg(long)]
+    pub eol_file: Option<PathBuf>,
+    /// Report EOL dates within this window as ending, e.g. 90d or 6m
+    #[arg(long, default_value = "12m")]
+    pub warn_within: WarnWindow,
+    /// Evaluate minor releases (RHEL EUS) and Windows builds using the exact
+    /// image version the VM was deployed from
+    #[arg(long)]
+    pub minor_versions: bool,
+    /// How long endoflife.date data cached in ~/.cache/azindex is used
+    /// before fetching it again, e.g. 24h or 7d
+    #[arg(long, default_value = "24h", value_parser = http::parse_duration)]
+    pub eol_cache_ttl: Duration,
+    /// Fetch endoflife.date data again even if the cached copy is recent
+    #[arg(long, conflicts_with = "offline")]
+    pub refresh_eol: bool,
+    /// Don't contact endoflife.date, use cached data or the snapshot built
+    /// into azindex, for hosts without internet access
+    #[arg(long)]
+    pub offline: bool,
+    /// Base URL of an endoflife.date API mirror
+    #[arg(long, default_value = eol::DEFAULT_ENDPOINT)]
+    pub eol_endpoint: String,
+    /// EOL dataset pinned with `azindex eol update`, used instead of
+    /// fetching. Its date is recorded in every row.
+    #[arg(long, conflicts_with_all = ["offline", "refresh_eol"])]
+    pub eol_data: Option<PathBuf>,
+    /// Warn when the EOL data used is older than this, e.g. 7d
+    #[arg(long, default_value = "7d", value_parser = http::parse_duration)]
+    pub eol_max_age: Duration,
+}
+
 #[derive(Clone, Debug)]
 pub enum OutputType {
     EXCEL,
@@ -48,56 +398,774 @@ impl From<String> for OutputType {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Compute,
+    ResourceGraph,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "compute" => Ok(Backend::Compute),
+            "resource-graph" | "arg" => Ok(Backend::ResourceGraph),
+            _ => Err(format!(
+                "invalid backend '{}', expected compute or resource-graph",
+                s
+            )),
+        }
+    }
+}
+
+// Set once SIGINT/SIGTERM stopped the scan
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// Last row of a report cut short by Ctrl+C
+const INCOMPLETE: &str = "INCOMPLETE: the scan was interrupted, resources are missing";
+
+/// Ctrl+C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            return;
+        }
+    }
+    _ = tokio::signal::ctrl_c().await;
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Cli::parse();
-    match args.format {
-        OutputType::UNKNOWN => {
-            error!("Unknown output format specified");
-            return Ok(());
+async fn main() {
+    let cli =
+        Cli::from_arg_matches(&with_env(Cli::command()).get_matches()).unwrap_or_else(|e| e.exit());
+    if let Err(e) = logging::set_format(cli.log_format) {
+        error!("Setting up logging failed: {}", e);
+    }
+    // Errors are logged like everything else, as JSON with --log-format json
+    if let Err(e) = run(cli).await {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    logging::set_level(match (cli.quiet, cli.verbose) {
+        (true, _) => Level::Quiet,
+        (false, 0) => Level::Normal,
+        (false, 1) => Level::Verbose,
+        (false, _) => Level::Trace,
+    });
+    match cli.command {
+        Command::Scan {
+            config,
+            scan,
+            filter,
+            cache,
+            report,
+        } => match (cache, report) {
+            (Some(cache), _) => scan_to_cache(config, scan, filter, cache).await,
+            (None, Some(report)) => scan_and_report(config, scan, filter, report).await,
+            (None, None) => Err("scan needs --format and an output file, or --cache".into()),
+        },
+        Command::Report {
+            config,
+            from_cache,
+            filter,
+            report,
+        } => report_from_cache(config, from_cache, filter, report).await,
+        Command::Diff { old, new } => diff_caches(&old, &new),
+        Command::ListSubscriptions { azure } => list_subscriptions(&azure).await,
+        Command::Check { urn, config, eol } => check_image(&urn, config, eol).await,
+        Command::Eol(EolCommand::Update { file, eol_endpoint }) => {
+            eol_update(file, eol_endpoint).await
+        }
+        Command::Eol(EolCommand::Snapshot { dir, eol_endpoint }) => {
+            eol_snapshot(dir, eol_endpoint).await
+        }
+    }
+}
+
+/// Lets every option also be set through an AZINDEX_* variable named after
+/// it, e.g. AZINDEX_EOL_CACHE_TTL for --eol-cache-ttl. Flags take true,
+/// false, 1 or 0, the command line wins over the environment.
+fn with_env(command: clap::Command) -> clap::Command {
+    let command = command.mut_args(|arg| match arg.get_long() {
+        Some(long) => {
+            let name = format!("AZINDEX_{}", long.replace('-', "_").to_uppercase());
+            match arg.get_action() {
+                ArgAction::SetTrue => arg.env(name).value_parser(BoolishValueParser::new()),
+                _ => arg.env(name),
+            }
         }
-        _ => {}
+        None => arg,
+    });
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|s| s.get_name().to_string())
+        .collect();
+    subcommands.into_iter().fold(command, |command, name| {
+        command.mut_subcommand(name, with_env)
+    })
+}
+
+fn load_config(path: &Option<PathBuf>) -> Result<Config, Box<dyn std::error::Error>> {
+    match path {
+        Some(path) => Config::load(path),
+        None => Ok(Config::default()),
+    }
+}
+
+async fn eol_update(file: PathBuf, endpoint: String) -> Result<(), Box<dyn std::error::Error>> {
+    eol::set_endpoint(&endpoint);
+    let products = supported_products();
+    let products: Vec<&str> = products.iter().map(String::as_str).collect();
+    let pinned = PinnedData::fetch(&products).await?;
+    pinned.save(&file)?;
+    Logger::new().success(format!(
+        "Pinned {} products as of {} to {}",
+        pinned.products.len(),
+        pinned.fetched.date_naive(),
+        file.display()
+    ));
+    Ok(())
+}
+
+/// Every endoflife.date product a built-in detector uses
+fn supported_products() -> Vec<String> {
+    let options = eol_detection::Options {
+        deep: true,
+        minor_versions: true,
     };
+    let detectors = eol_detection::registry(&Config::default(), options);
+    eol_detection::products(&detectors)
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+async fn eol_snapshot(dir: PathBuf, endpoint: String) -> Result<(), Box<dyn std::error::Error>> {
+    eol::set_endpoint(&endpoint);
+    eol::set_cache(eol::CacheOptions {
+        dir: Some(dir.clone()),
+        ttl: Duration::ZERO,
+        refresh: true,
+        offline: false,
+    });
+    let products = supported_products();
+    let products: Vec<&str> = products.iter().map(String::as_str).collect();
+    fetch_products(&products, EolData::new()).await?;
+    // For the suggestions of unclassified images
+    eol::all_products().await?;
+    // Falling back to what the directory already held isn't a snapshot
+    if let Some(product) = products.iter().find(|p| eol::data_as_of(p).is_some()) {
+        return Err(format!("{} couldn't be fetched from endoflife.date", product).into());
+    }
+    let date = chrono::Local::now().date_naive().to_string();
+    std::fs::write(dir.join("DATE"), date + "\n")?;
+    Logger::new().success(format!("Saved {} products", products.len()));
+    Ok(())
+}
+
+async fn scan_and_report(
+    config: Option<PathBuf>,
+    scan: ScanArgs,
+    filter: FilterArgs,
+    report: ReportArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let OutputType::UNKNOWN = report.format {
+        return Err("Unknown output format, use excel or csv".into());
+    }
+    let config = load_config(&config)?;
+    let state_path = Checkpoint::path(&report.out);
+    let skipped = Arc::new(Skipped::default());
+    let mut rx = start_scan(&config, &scan, &filter, &state_path, skipped.clone()).await?;
+    let summary = write_report(&mut rx, &config, &report, scan.deep, &skipped).await?;
+    finish_scan(&state_path)?;
+    fail_on_findings(&report, &summary);
+    Ok(())
+}
+
+async fn scan_to_cache(
+    config: Option<PathBuf>,
+    scan: ScanArgs,
+    filter: FilterArgs,
+    cache: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(&config)?;
+    let state_path = Checkpoint::path(&cache);
+    let skipped = Arc::new(Skipped::default());
+    let mut rx = start_scan(&config, &scan, &filter, &state_path, skipped.clone()).await?;
+    let mut results = Vec::new();
+    while let Some(vm) = rx.recv().await {
+        results.push(vm);
+        progress::written();
+    }
+    progress::finish();
+    let inventory = Cache {
+        deep: scan.deep,
+        interrupted: INTERRUPTED.load(Ordering::SeqCst),
+        skipped: skipped.entries(),
+        results,
+    };
+    inventory.save(&cache)?;
+    Logger::new().info(format!(
+        "Saved {} resources to {}",
+        inventory.results.len(),
+        cache.display()
+    ));
+    skipped.print();
+    finish_scan(&state_path)
+}
+
+async fn list_subscriptions(args: &AzureArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let credentials = sign_in(args).await?;
+    let filter = args.subscription_filter();
+    let skipped = Skipped::default();
+    let mut listed = HashSet::new();
     let mut log = Logger::new();
-    log.info("Detecting credentials");
+    for (credential, tenant) in credentials {
+        let client = args.subscription_client(credential.clone());
+        let subs: Vec<_> = subscriptions::list_subscriptions(&client, &filter, &skipped)
+            .await
+            .into_iter()
+            .filter(|s| listed.insert(s.id.clone()))
+            .collect();
+        match tenant {
+            Some(tenant) => log.info(format!("{} subscriptions in tenant {}", subs.len(), tenant)),
+            None => log.info(format!("{} subscriptions", subs.len())),
+        };
+        if subs.is_empty() {
+            continue;
+        }
+        let arm = ArmClient::new(credential, args.cloud);
+        let ids: Vec<String> = subs.iter().map(|s| s.id.clone()).collect();
+        let counts = match inventory::resource_graph::count_vms(&arm, &ids).await {
+            Ok(counts) => Some(counts),
+            Err(e) => {
+                warn!("Counting VMs with Resource Graph failed: {}", e);
+                None
+            }
+        };
+        for sub in &subs {
+            let vms = match &counts {
+                Some(counts) => counts.get(&sub.id).copied().unwrap_or(0).to_string(),
+                None => String::from("?"),
+            };
+            log.indent(1).log(format!(
+                "{}  {}  {}  {} VMs",
+                sub.id, sub.name, sub.state, vms
+            ));
+        }
+    }
+    skipped.print();
+    Ok(())
+}
 
-    let credential = std::sync::Arc::new(AzureCliCredential::new());
-    let tenant = AzureCliCredential::get_tenant()?;
-    log.info(format!("Listing VMs in tenant {}", tenant));
+/// The VM an image URN would create, version `latest` leaves the exact
+/// version unknown
+fn parse_urn(urn: &str) -> Result<VMResult, String> {
+    let parts: Vec<&str> = urn.split(':').map(str::trim).collect();
+    let [publisher, offer, sku, version] = parts[..] else {
+        return Err(format!("{} isn't a publisher:offer:sku:version URN", urn));
+    };
+    if parts.iter().any(|p| p.is_empty()) {
+        return Err(format!("{} isn't a publisher:offer:sku:version URN", urn));
+    }
+    let exact_version = match version.eq_ignore_ascii_case("latest") {
+        true => String::new(),
+        false => version.to_string(),
+    };
+    Ok(VMResult {
+        id: urn.to_string(),
+        resource_type: String::from("Image"),
+        instances: 1,
+        publisher: publisher.to_string(),
+        offer: offer.to_string(),
+        sku: sku.to_string(),
+        version: version.to_string(),
+        exact_version,
+        ..Default::default()
+    })
+}
+
+async fn check_image(
+    urn: &str,
+    config: Option<PathBuf>,
+    args: EolArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(&config)?;
+    let vm = parse_urn(urn)?;
+    let detectors = eol_detection::registry(&config, args.options(false));
+    let mut eol_data = load_eol_data(&config, &args, args.warn_within).await?;
+    eol_data.prepare(&vm, &detectors).await;
+    let detection = eol_detection::detect(&vm, &detectors, eol_data.data());
+    warn_old_eol_data(args.eol_max_age);
 
-    let subscription_client = azure_mgmt_subscription::Client::builder(credential.clone()).build();
-    let client = azure_mgmt_compute::Client::builder(credential).build();
+    let mut log = Logger::new();
+    if !detection.classified {
+        log.warn(format!("{}: not an image azindex recognizes", urn));
+        std::process::exit(2);
+    }
+    if detection.status == "--" {
+        log.warn(format!(
+            "{}: no EOL cycle found for version {}",
+            urn, detection.version
+        ));
+        std::process::exit(2);
+    }
+    let verdict = format!("{}: {} {}", urn, detection.version, detection.status);
+    match detection.status.as_str() {
+        "EOL" => log.error(verdict),
+        "Supported" => log.success(verdict),
+        _ => log.warn(verdict),
+    };
+    for (label, value) in [
+        ("Component", detection.component.as_str()),
+        ("Support", detection.support_tier.as_str()),
+        ("Phase", detection.phase.as_str()),
+        ("Source", detection.source.as_str()),
+    ] {
+        if !value.is_empty() {
+            log.indent(1).log(format!("{}: {}", label, value));
+        }
+    }
+    if let Some(date) = detection.data_as_of {
+        log.indent(1).log(format!("EOL data as of: {}", date));
+    }
+    if detection.status == "EOL" {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn diff_caches(old: &Path, new: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (old, new) = (Cache::load(old)?, Cache::load(new)?);
+    if old.interrupted || new.interrupted {
+        warn!("Comparing an interrupted scan, its missing resources show up as added or removed");
+    }
+    diff::diff(&old.results, &new.results).print();
+    Ok(())
+}
+
+async fn report_from_cache(
+    config: Option<PathBuf>,
+    from_cache: PathBuf,
+    filter: FilterArgs,
+    report: ReportArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let OutputType::UNKNOWN = report.format {
+        return Err("Unknown output format, use excel or csv".into());
+    }
+    let config = load_config(&config)?;
+    let cache = Cache::load(&from_cache)?;
+    if cache.interrupted {
+        warn!("{} is from an interrupted scan", from_cache.display());
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+    let skipped = Skipped::default();
+    for (id, reason) in cache.skipped {
+        skipped.add(&id, reason);
+    }
+    let filter = filter.result_filter();
     let (tx, mut rx) = mpsc::channel::<VMResult>(32);
+    tokio::spawn(async move {
+        for vm in cache.results.into_iter().filter(|vm| filter.matches(vm)) {
+            _ = tx.send(vm).await;
+        }
+    });
+    let summary = write_report(&mut rx, &config, &report, cache.deep, &skipped).await?;
+    Logger::new().success("Done!");
+    fail_on_findings(&report, &summary);
+    Ok(())
+}
+
+/// Applies the HTTP options and signs in to every tenant to scan
+async fn sign_in(
+    args: &AzureArgs,
+) -> Result<Vec<(Arc<dyn TokenCredential>, Option<String>)>, Box<dyn std::error::Error>> {
+    http::set_retry(http::RetryOptions {
+        attempts: args.retries,
+        backoff: args.retry_backoff,
+    });
+    http::set_timeout(args.timeout);
+    if let Some(proxy) = &args.proxy {
+        http::set_proxy(proxy)?;
+        // Picked up by the Azure CLI fetching tokens
+        std::env::set_var("HTTPS_PROXY", proxy);
+    }
+    if let Some(path) = &args.ca_bundle {
+        http::set_ca_bundle(path)?;
+        // The Azure CLI only trusts its own bundle unless told otherwise
+        std::env::set_var("REQUESTS_CA_BUNDLE", path);
+    }
+
+    Logger::new().info("Detecting credentials");
+    let mut tenant_ids = args.tenant_ids.clone();
+    if let Some(path) = &args.tenants_file {
+        tenant_ids.extend(auth::read_tenants(path)?);
+    }
+    if tenant_ids.len() > 1 && args.auth == Some(Auth::ManagedIdentity) {
+        return Err("--auth managed-identity can only sign in to its own tenant".into());
+    }
+    // All credentials are set up front so sign-in problems, and device code
+    // prompts, come before the scan
+    let mut credentials = Vec::new();
+    let tenants = match tenant_ids.is_empty() {
+        true => vec![None],
+        false => tenant_ids.into_iter().map(Some).collect(),
+    };
+    for tenant_id in tenants {
+        let auth = AuthOptions {
+            auth: args.auth,
+            tenant_id,
+            client_id: args.client_id.clone(),
+            client_cert: args.client_cert.clone(),
+            password: args.client_cert_password.clone(),
+        };
+        credentials.push(auth::credential(&auth, args.cloud).await?);
+    }
+    Ok(credentials)
+}
+
+impl AzureArgs {
+    fn subscription_filter(&self) -> SubscriptionFilter {
+        SubscriptionFilter {
+            include: self.subscriptions.clone(),
+            exclude: self.exclude_subscriptions.clone(),
+        }
+    }
+
+    fn subscription_client(
+        &self,
+        credential: Arc<dyn TokenCredential>,
+    ) -> azure_mgmt_subscription::Client {
+        azure_mgmt_subscription::Client::builder(credential)
+            .endpoint(self.cloud.endpoint())
+            .retry(azure_core::RetryOptions::none())
+            .transport(http::transport())
+            .build()
+    }
+}
+
+/// Sets up the HTTP clients and starts scanning in the background, results
+/// arrive on the returned channel until the scan is done or interrupted
+async fn start_scan(
+    config: &Config,
+    args: &ScanArgs,
+    filter: &FilterArgs,
+    state_path: &Path,
+    skipped: Arc<Skipped>,
+) -> Result<Receiver<VMResult>, Box<dyn std::error::Error>> {
+    let mut log = Logger::new();
+    let credentials = sign_in(&args.azure).await?;
+
+    let resumed = if args.resume {
+        match checkpoint::load(state_path) {
+            Ok(resumed) => {
+                log.info(format!(
+                    "Resuming from {}, {} subscriptions done",
+                    state_path.display(),
+                    resumed.subscriptions.len()
+                ));
+                resumed
+            }
+            Err(e) => {
+                warn!("Can't resume from {}: {}", state_path.display(), e);
+                Resumed::default()
+            }
+        }
+    } else {
+        Resumed::default()
+    };
+    let checkpoint = Arc::new(Checkpoint::create(state_path.to_path_buf(), args.resume)?);
+    let (tx, rx) = mpsc::channel::<VMResult>(32);
+    let seen = Arc::new(Mutex::new(HashSet::new()));
+    let mut scans = Vec::new();
+    for (credential, tenant) in credentials {
+        let subscription_client = args.azure.subscription_client(credential.clone());
+        let arm = ArmClient::new(credential.clone(), args.azure.cloud);
+        let client = azure_mgmt_compute::Client::builder(credential)
+            .endpoint(args.azure.cloud.endpoint())
+            .retry(azure_core::RetryOptions::none())
+            .transport(http::transport())
+            .build();
+        let inventory = Inventory {
+            client,
+            arm,
+            tx: Mutex::new(tx.clone()),
+            image_cache: Mutex::new(HashMap::new()),
+            gallery_cache: Mutex::new(HashMap::new()),
+            seen: seen.clone(),
+            sql_vms: Mutex::new(HashMap::new()),
+            power_states: Mutex::new(HashMap::new()),
+            deep: args.deep,
+            containers: args.containers,
+            images: args.images,
+            resource_graph: args.backend == Backend::ResourceGraph && !args.images,
+            gallery_tag: config.gallery_tag().to_string(),
+            filter: filter.result_filter(),
+            checkpoint: Some(checkpoint.clone()),
+            tenants: Mutex::new(HashMap::new()),
+            subscription_names: Mutex::new(HashMap::new()),
+            skipped: skipped.clone(),
+        };
+        scans.push((tenant, subscription_client, inventory));
+    }
+    // The inventories hold the senders, the report ends when they are dropped
+    drop(tx);
 
+    let subscription_timeout = args.subscription_timeout;
+    let filter = args.azure.subscription_filter();
+    progress::start();
+    let scan = tokio::spawn(async move {
+        // Subscriptions delegated to several of the tenants are only scanned
+        // from the first
+        let mut scanned = resumed.subscriptions;
+        let mut results = Some(resumed.results);
+        for (tenant, subscription_client, inventory) in scans {
+            match tenant {
+                Some(tenant) => log.info(format!("Listing VMs in tenant {}", tenant)),
+                None => log.info("Listing VMs visible to the managed identity"),
+            };
+            let subs = subscriptions::list_subscriptions(
+                &subscription_client,
+                &filter,
+                &inventory.skipped,
+            )
+            .await;
+            for result in results.take().unwrap_or_default() {
+                inventory.replay(result).await;
+            }
+            let subs: Vec<_> = subs
+                .into_iter()
+                .filter(|s| scanned.insert(s.id.clone()))
+                .collect();
+            progress::add_subscriptions(subs.len());
+            scan_tenant(&inventory, subs, subscription_timeout).await;
+        }
+    });
+    // Stopping the scan drops the sender, the writer then finishes the
+    // report with what it received
     tokio::spawn(async move {
-        let tx = Mutex::new(tx);
-        let subs = subscription_client
-            .subscriptions_client()
-            .list()
-            .into_stream();
-        subs.for_each_concurrent(10, |subs| async {
-            if let Ok(subs) = subs {
-                for sub in subs.value {
-                    let sub_id = sub.subscription_id.unwrap_or_default();
-                    let sub_name = sub.display_name.unwrap_or_default();
-                    Logger::new().info(format!("Listing subscription {} ({})", &sub_name, &sub_id));
-                    list_vms(&sub_id, &client, &tx).await;
+        shutdown_signal().await;
+        warn!("Interrupted, writing a partial report. Press Ctrl+C again to quit immediately");
+        // Nothing is missing once the scan is done
+        INTERRUPTED.store(!scan.is_finished(), Ordering::SeqCst);
+        scan.abort();
+        shutdown_signal().await;
+        std::process::exit(130);
+    });
+
+    Ok(rx)
+}
+
+/// Lists the subscriptions of one tenant with its inventory
+async fn scan_tenant(
+    inventory: &Inventory,
+    subs: Vec<Subscription>,
+    subscription_timeout: Option<Duration>,
+) {
+    let mut tenants = inventory.tenants.lock().await;
+    let mut names = inventory.subscription_names.lock().await;
+    for sub in &subs {
+        tenants.insert(sub.id.to_lowercase(), sub.tenant_id.clone());
+        names.insert(sub.id.to_lowercase(), sub.name.clone());
+    }
+    drop((tenants, names));
+    if inventory.resource_graph {
+        Logger::new().info("Querying VMs and scale sets from Resource Graph");
+        let ids: Vec<String> = subs.iter().map(|s| s.id.clone()).collect();
+        inventory::resource_graph::list_compute(inventory, &ids).await;
+    }
+    for sub in subs {
+        Logger::new().info(format!("Listing subscription {} ({})", &sub.name, &sub.id));
+        let listing = inventory.list_subscription(&sub.id);
+        let completed = match subscription_timeout {
+            Some(limit) => {
+                let completed = tokio::time::timeout(limit, listing).await.is_ok();
+                if !completed {
+                    error!(
+                        "Listing subscription {} timed out after {:?}, its results are incomplete",
+                        &sub.id, limit
+                    );
+                    inventory
+                        .skipped
+                        .add(&sub.id, format!("Timed out after {:?}", limit));
                 }
+                completed
             }
-        })
-        .await;
-        log.done();
-    });
+            None => {
+                listing.await;
+                true
+            }
+        };
+        // Skipped subscriptions are tried again when resuming
+        let completed = completed && !inventory.skipped.contains(&sub.id);
+        if let (true, Some(checkpoint)) = (completed, &inventory.checkpoint) {
+            checkpoint.subscription_done(&sub.id);
+        }
+        progress::subscription_done();
+    }
+}
 
-    match args.format {
+/// Classifies the results as they arrive and writes the report
+async fn write_report(
+    rx: &mut Receiver<VMResult>,
+    config: &Config,
+    args: &ReportArgs,
+    deep: bool,
+    skipped: &Skipped,
+) -> Result<Summary, Box<dyn std::error::Error>> {
+    // Resources failing the run are reported as ending too
+    let today = chrono::Utc::now().date_naive();
+    let warn_within = match args.fail_if_eol_within {
+        Some(window) if window.end(today) > args.eol.warn_within.end(today) => window,
+        _ => args.eol.warn_within,
+    };
+    let detectors = eol_detection::registry(config, args.eol.options(deep));
+    let mut eol_data = load_eol_data(config, &args.eol, warn_within).await?;
+
+    let mut summary = Summary::default();
+    let unclassified = match args.format {
         OutputType::CSV => {
-            write_to_csv(&mut rx, args.out).await?;
+            write_to_csv(rx, &detectors, &mut eol_data, skipped, &mut summary, args).await?
         }
         OutputType::EXCEL => {
-            write_to_excel(&mut rx, args.out).await?;
+            write_to_excel(rx, &detectors, &mut eol_data, skipped, &mut summary, args).await?
+        }
+        _ => Unclassified::default(),
+    };
+    // Only used for suggestions, a run doesn't depend on it
+    let products = eol::all_products().await.unwrap_or_default();
+    unclassified.print(&products);
+    skipped.print();
+    warn_old_eol_data(args.eol.eol_max_age);
+    report_mismatches();
+    summary.print(skipped);
+    Ok(summary)
+}
+
+/// `--fail-on-eol`, `--fail-if-eol-within` and `--fail-on-unknown`, once the report is written
+fn fail_on_findings(args: &ReportArgs, summary: &Summary) {
+    if args.fail_on_eol && summary.eol() > 0 {
+        error!("{} resources are EOL", summary.eol());
+        std::process::exit(1);
+    }
+    if let Some(window) = args.fail_if_eol_within {
+        let end = window.end(chrono::Utc::now().date_naive());
+        let count = summary.eol() + summary.ending_before(end);
+        if count > 0 {
+            error!("{} resources are EOL or reach it before {}", count, end);
+            std::process::exit(1);
         }
-        _ => {}
+    }
+    if args.fail_on_unknown && summary.unknown() > 0 {
+        error!("{} resources have no EOL verdict", summary.unknown());
+        std::process::exit(2);
+    }
+}
+
+impl EolArgs {
+    fn options(&self, deep: bool) -> eol_detection::Options {
+        eol_detection::Options {
+            deep,
+            minor_versions: self.minor_versions,
+        }
+    }
+}
+
+/// Applies the EOL options and loads the custom and pinned products, the
+/// rest is fetched as resources need it. `warn_within` is the window the
+/// run reports as ending, at least `--warn-within`.
+async fn load_eol_data<'a>(
+    config: &'a Config,
+    args: &EolArgs,
+    warn_within: WarnWindow,
+) -> Result<LazyEolData<'a>, Box<dyn std::error::Error>> {
+    set_warn_within(warn_within);
+    eol::set_cache(eol::CacheOptions {
+        dir: eol::default_cache_dir(),
+        ttl: args.eol_cache_ttl,
+        refresh: args.refresh_eol,
+        offline: args.offline,
+    });
+    eol::set_endpoint(&args.eol_endpoint);
+    let custom_eol = match &args.eol_file {
+        Some(path) => load_eol_file(path)?,
+        None => EolData::new(),
+    };
+    check_mappings(config, &custom_eol).await;
+    // Products of the --eol-file take precedence over pinned ones
+    let custom_eol = match &args.eol_data {
+        Some(path) => {
+            let pinned = PinnedData::load(path)?;
+            Logger::new().info(format!(
+                "Using EOL data pinned on {} from {}",
+                pinned.fetched.date_naive(),
+                pinned.endpoint
+            ));
+            let mut data = pinned.eol_data()?;
+            data.extend(custom_eol);
+            data
+        }
+        None => custom_eol,
     };
+    Ok(LazyEolData::new(custom_eol, config))
+}
+
+/// Warns about mappings to products neither endoflife.date nor the
+/// --eol-file knows, typically a typo
+async fn check_mappings(config: &Config, custom_eol: &EolData) {
+    if config.mappings.is_empty() {
+        return;
+    }
+    let products = match eol::all_products().await {
+        Ok(products) => products,
+        Err(_) => return,
+    };
+    for mapping in &config.mappings {
+        if !products.contains(&mapping.product) && !custom_eol.contains_key(&mapping.product) {
+            warn!(
+                "Mapping to {} (publisher {}) names a product endoflife.date doesn't track",
+                mapping.product,
+                mapping.publisher.as_deref().unwrap_or("*")
+            );
+        }
+    }
+}
+
+fn warn_old_eol_data(max_age: Duration) {
+    let oldest = chrono::Local::now() - max_age;
+    for (product, provenance) in eol::provenance() {
+        if provenance.fetched < oldest {
+            warn!(
+                "EOL data of {} is from {} ({}), older than --eol-max-age",
+                product,
+                provenance.fetched.format("%Y-%m-%d"),
+                provenance.origin.as_str()
+            );
+        }
+    }
+}
+
+/// Keeps the state file of an interrupted scan for `--resume`
+fn finish_scan(state_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        warn!(
+            "The report is incomplete, continue the scan with --resume (state in {})",
+            state_path.display()
+        );
+        std::process::exit(130);
+    }
+    // The report is complete, there is nothing left to resume
+    _ = std::fs::remove_file(state_path);
 
     let mut log = Logger::new();
     log.success("Done!");
@@ -105,98 +1173,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn list_vms(
-    subscription_id: &String,
-    client: &azure_mgmt_compute::Client,
-    tx: &Mutex<Sender<VMResult>>,
-) {
-    let vms = client
-        .virtual_machines_client()
-        .list_all(subscription_id)
-        .into_stream();
-    vms.for_each_concurrent(10, |vms| async {
-        if let Ok(vms) = vms {
-            for vm in vms.value {
-                let properties = match vm.properties {
-                    Some(p) => p,
-                    None => {
-                        error!(
-                            "No properties found for: {}",
-                            vm.resource.id.unwrap_or_default()
-                        );
-                        continue;
-                    }
-                };
-                let storage_profile = match properties.storage_profile {
-                    Some(p) => p,
-                    None => {
-                        error!(
-                            "No storage profile found for: {}",
-                            vm.resource.id.unwrap_or_default()
-                        );
-                        continue;
-                    }
-                };
-                let image_info = {
-                    if let Some(r) = storage_profile.image_reference {
-                        (
-                            r.sku.unwrap_or_default(),
-                            r.publisher.unwrap_or_default(),
-                            r.offer.unwrap_or_default(),
-                            r.version.unwrap_or_default(),
-                            r.exact_version.unwrap_or_default(),
-                        )
-                    } else {
-                        (
-                            "".to_string(),
-                            "".to_string(),
-                            "".to_string(),
-                            "".to_string(),
-                            "".to_string(),
-                        )
-                    }
-                };
-                let os_disk = match storage_profile.os_disk {
-                    Some(p) => p,
-                    None => {
-                        error!(
-                            "No OS disk found for: {}",
-                            vm.resource.id.unwrap_or_default()
-                        );
-                        continue;
-                    }
-                };
-                let sku = image_info.0;
-
-                let resource_id = vm.resource.id.unwrap_or_default();
-                // info!("Found VM: {}", &resource_id);
-                let machine = VMResult {
-                    id: resource_id,
-                    subscription_id: subscription_id.clone(),
-                    publisher: image_info.1,
-                    offer: image_info.2,
-                    sku: sku.clone(),
-                    version: image_info.3,
-                    exact_version: image_info.4,
-                    os_type: os_disk.os_type,
-                };
-                let tx = tx.lock().await;
-                _ = tx.send(machine).await;
-            }
-        }
-    })
-    .await;
+fn fetched(provenance: &Provenance) -> String {
+    provenance.fetched.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Date of the older data a verdict fell back to, empty when current
+fn data_as_of(detection: &Detection) -> String {
+    detection
+        .data_as_of
+        .map_or(String::new(), |date| date.to_string())
+}
+
+fn created(vm: &VMResult) -> String {
+    vm.created.map_or(String::new(), |date| date.to_string())
 }
 
 async fn write_to_excel(
     rx: &mut Receiver<VMResult>,
-    file: PathBuf,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let ubuntu_eol = ubuntu::list().await?;
-    let centos_eol = centos::list().await?;
-    let windows_eol = windows::list().await?;
-    let redhat_eol = redhat::list().await?;
-
+    detectors: &[Box<dyn OsDetector>],
+    eol_data: &mut LazyEolData<'_>,
+    skipped: &Skipped,
+    summary: &mut Summary,
+    args: &ReportArgs,
+) -> Result<Unclassified, Box<dyn std::error::Error>> {
+    let rows = args.row_filter();
+    let file = args.out.clone();
     let workbook = Workbook::new_with_options(file.to_str().unwrap(), true, None, false)?;
     let mut sheet = workbook.add_worksheet(None)?;
 
@@ -238,63 +1239,71 @@ async fn write_to_excel(
     sheet.write_string(0, 9, "Version exact", header_format)?;
     sheet.write_string(0, 10, "Publisher", header_format)?;
     sheet.write_string(0, 11, "Resource ID", header_format)?;
+    sheet.write_string(0, 12, "EOL component", header_format)?;
+    sheet.write_string(0, 13, "Support tier", header_format)?;
+    sheet.write_string(0, 14, "EOL override", header_format)?;
+    sheet.write_string(0, 15, "Plan publisher", header_format)?;
+    sheet.write_string(0, 16, "Plan product", header_format)?;
+    sheet.write_string(0, 17, "Plan name", header_format)?;
+    sheet.write_string(0, 18, "License type", header_format)?;
+    sheet.write_string(0, 19, "Azure deprecation", header_format)?;
+    sheet.write_string(0, 20, "Gallery image", header_format)?;
+    sheet.write_string(0, 21, "Type", header_format)?;
+    sheet.write_string(0, 22, "Instances", header_format)?;
+    sheet.write_string(0, 23, "Scale set", header_format)?;
+    sheet.write_string(0, 24, "SQL Server", header_format)?;
+    sheet.write_string(0, 25, "SQL Server status", header_format)?;
+    sheet.write_string(0, 26, "Power state", header_format)?;
+    sheet.write_string(0, 27, "Tenant", header_format)?;
+    sheet.write_string(0, 28, "EOL data as of", header_format)?;
+    sheet.write_string(0, 29, "Location", header_format)?;
+    sheet.write_string(0, 30, "VM size", header_format)?;
+    sheet.write_string(0, 31, "Subscription name", header_format)?;
+    sheet.write_string(0, 32, "Created", header_format)?;
+    sheet.write_string(0, 33, "Support phase", header_format)?;
+    for (idx, name) in VMResult::tag_headers(&args.tag_columns).iter().enumerate() {
+        sheet.write_string(0, 34 + idx as u16, name, header_format)?;
+    }
 
+    let mut unclassified = Unclassified::default();
     let mut row_idx = 1;
     while let Some(vm) = rx.recv().await {
-        let version_info: (String, String) = {
-            if vm.offer.to_lowercase().contains("ubuntu") {
-                let version = ubuntu::parse_azure_version(&vm.sku);
-                let is_outdated = ubuntu::is_outdated(&vm, &ubuntu_eol);
-                (version.unwrap_or_default(), is_outdated)
-            } else if vm.offer.to_lowercase().contains("centos") {
-                let version = centos::parse_azure_version(&vm.sku);
-                let is_outdated = centos::is_outdated(&vm, &centos_eol);
-                (version.unwrap_or_default(), is_outdated)
-            } else if vm.offer.to_lowercase().contains("windows") {
-                let version = windows::parse_azure_version(&vm.sku);
-                let is_outdated = windows::is_outdated(&vm, &windows_eol);
-                (version.unwrap_or_default(), is_outdated)
-            } else if vm.offer.to_lowercase().contains("rhel") {
-                let version = redhat::parse_azure_version(&vm.sku);
-                let is_outdated = redhat::is_outdated(&vm, &redhat_eol);
-                (version.unwrap_or_default(), is_outdated)
-            } else {
-                (String::from(""), String::from("--"))
-            }
-        };
+        eol_data.prepare(&vm, detectors).await;
+        let detection = eol_detection::detect(&vm, detectors, eol_data.data());
+        summary.add(&vm, &detection);
+        if !detection.classified && !vm.custom_image {
+            unclassified.add(&vm);
+        }
+        if !rows.matches(&vm, &detection) {
+            continue;
+        }
 
         let deprecated_sytle = {
-            if version_info.1 == "EOL" {
+            if detection.status == "EOL" {
                 eol_style
-            } else if version_info.1 == "Supported" {
+            } else if detection.status == "Supported" {
                 green_style
-            } else if version_info.1.contains("Ending") {
-                unknown_style
             } else {
                 unknown_style
             }
         };
 
         let os_type = {
-            if let Some(os_type) = vm.os_type {
+            if let Some(os_type) = &vm.os_type {
                 format!("{:?}", os_type)
             } else {
                 String::from("--")
             }
         };
 
-        let vm_id = vm.id.clone();
-        let parts: Vec<&str> = vm_id.split("/").collect();
-        // println!("{:#?}", parts);
+        let resource_group = vm.resource_group();
+        let resource = vm.name();
 
-        let resource_group = parts[4];
-        let resource = parts.last().unwrap();
-
-        sheet.write_string(row_idx, 0, &version_info.0, None)?;
-        sheet.write_string(row_idx, 1, &version_info.1, deprecated_sytle)?;
+        sheet.write_string(row_idx, 0, &detection.version, None)?;
+        sheet.write_string(row_idx, 1, &detection.status, deprecated_sytle)?;
         sheet.write_string(row_idx, 2, resource_group, None)?;
         sheet.write_string(row_idx, 3, resource, None)?;
-        sheet.write_string(row_idx, 4, &os_type.as_str(), None)?;
+        sheet.write_string(row_idx, 4, os_type.as_str(), None)?;
         sheet.write_string(row_idx, 5, &vm.subscription_id, None)?;
         sheet.write_string(row_idx, 6, &vm.offer, None)?;
         sheet.write_string(row_idx, 7, &vm.sku, None)?;
@@ -302,49 +1311,111 @@ async fn write_to_excel(
         sheet.write_string(row_idx, 9, &vm.exact_version, None)?;
         sheet.write_string(row_idx, 10, &vm.publisher, None)?;
         sheet.write_string(row_idx, 11, &vm.id, None)?;
+        sheet.write_string(row_idx, 12, &detection.component, None)?;
+        sheet.write_string(row_idx, 13, &detection.support_tier, None)?;
+        sheet.write_string(row_idx, 14, &detection.source, None)?;
+        sheet.write_string(row_idx, 15, &vm.plan_publisher, None)?;
+        sheet.write_string(row_idx, 16, &vm.plan_product, None)?;
+        sheet.write_string(row_idx, 17, &vm.plan_name, None)?;
+        sheet.write_string(row_idx, 18, &vm.license_type, None)?;
+        sheet.write_string(row_idx, 19, &vm.azure_deprecation, None)?;
+        sheet.write_string(row_idx, 20, &vm.gallery_image, None)?;
+        sheet.write_string(row_idx, 21, &vm.resource_type, None)?;
+        sheet.write_number(row_idx, 22, vm.instances as f64, None)?;
+        sheet.write_string(row_idx, 23, &vm.scale_set, None)?;
+        sheet.write_string(row_idx, 24, &detection.sql_version, None)?;
+        sheet.write_string(row_idx, 25, &detection.sql_status, None)?;
+        sheet.write_string(row_idx, 26, &vm.power_state, None)?;
+        sheet.write_string(row_idx, 27, &vm.tenant_id, None)?;
+        sheet.write_string(row_idx, 28, &data_as_of(&detection), None)?;
+        sheet.write_string(row_idx, 29, &vm.location, None)?;
+        sheet.write_string(row_idx, 30, &vm.vm_size, None)?;
+        sheet.write_string(row_idx, 31, &vm.subscription_name, None)?;
+        sheet.write_string(row_idx, 32, &created(&vm), None)?;
+        sheet.write_string(row_idx, 33, &detection.phase, None)?;
+        for (idx, value) in vm.tag_fields(&args.tag_columns).iter().enumerate() {
+            sheet.write_string(row_idx, 34 + idx as u16, value, None)?;
+        }
 
         row_idx += 1;
+        progress::written();
+    }
+    progress::finish();
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        sheet.write_string(row_idx, 0, INCOMPLETE, eol_style)?;
+    }
+
+    let mut sheet = workbook.add_worksheet(Some("Unclassified images"))?;
+    sheet.write_string(0, 0, "Count", header_format)?;
+    sheet.write_string(0, 1, "Publisher", header_format)?;
+    sheet.write_string(0, 2, "Offer", header_format)?;
+    sheet.write_string(0, 3, "SKU", header_format)?;
+    for (idx, ((publisher, offer, sku), count)) in unclassified.images.iter().enumerate() {
+        let row = idx as u32 + 1;
+        sheet.write_number(row, 0, *count as f64, None)?;
+        sheet.write_string(row, 1, publisher, None)?;
+        sheet.write_string(row, 2, offer, None)?;
+        sheet.write_string(row, 3, sku, None)?;
+    }
+    let mut sheet = workbook.add_worksheet(Some("EOL data"))?;
+    sheet.write_string(0, 0, "Product", header_format)?;
+    sheet.write_string(0, 1, "Source", header_format)?;
+    sheet.write_string(0, 2, "Fetched", header_format)?;
+    for (idx, (product, provenance)) in eol::provenance().iter().enumerate() {
+        let row = idx as u32 + 1;
+        sheet.write_string(row, 0, product, None)?;
+        sheet.write_string(row, 1, provenance.origin.as_str(), None)?;
+        sheet.write_string(row, 2, &fetched(provenance), None)?;
+    }
+    let skipped = skipped.entries();
+    if !skipped.is_empty() {
+        let mut sheet = workbook.add_worksheet(Some("Skipped subscriptions"))?;
+        sheet.write_string(0, 0, "Subscription", header_format)?;
+        sheet.write_string(0, 1, "Reason", header_format)?;
+        for (idx, (id, reason)) in skipped.iter().enumerate() {
+            let row = idx as u32 + 1;
+            sheet.write_string(row, 0, id, None)?;
+            sheet.write_string(row, 1, reason, None)?;
+        }
     }
     workbook.close()?;
 
-    Ok(())
+    Ok(unclassified)
 }
 
 async fn write_to_csv(
     rx: &mut Receiver<VMResult>,
-    file: PathBuf,
-) -> Result<(), Box<dyn std::error::Error>> {
+    detectors: &[Box<dyn OsDetector>],
+    eol_data: &mut LazyEolData<'_>,
+    skipped: &Skipped,
+    summary: &mut Summary,
+    args: &ReportArgs,
+) -> Result<Unclassified, Box<dyn std::error::Error>> {
+    let rows = args.row_filter();
+    let file = args.out.clone();
+    let unclassified_file = file.with_extension("unclassified.csv");
+    let skipped_file = file.with_extension("skipped.csv");
+    let eol_file = file.with_extension("eol.csv");
     let file = File::create(file)?;
     let mut f = BufWriter::new(file);
-    f.write(VMResult::csv_header_line().as_bytes())?;
-
-    let ubuntu_eol = ubuntu::list().await?;
-    let centos_eol = centos::list().await?;
-    let windows_eol = windows::list().await?;
+    f.write_all(VMResult::csv_header_line(&args.tag_columns).as_bytes())?;
 
+    let mut unclassified = Unclassified::default();
     while let Some(vm) = rx.recv().await {
-        let version_info: (String, String) = {
-            if vm.offer.to_lowercase().contains("ubuntu") {
-                let version = ubuntu::parse_azure_version(&vm.sku);
-                let is_outdated = ubuntu::is_outdated(&vm, &ubuntu_eol);
-                (version.unwrap_or_default(), is_outdated)
-            } else if vm.offer.to_lowercase().contains("centos") {
-                let version = centos::parse_azure_version(&vm.sku);
-                let is_outdated = centos::is_outdated(&vm, &centos_eol);
-                (version.unwrap_or_default(), is_outdated)
-            } else if vm.offer.to_lowercase().contains("windows") {
-                let version = windows::parse_azure_version(&vm.sku);
-                let is_outdated = windows::is_outdated(&vm, &windows_eol);
-                (version.unwrap_or_default(), is_outdated)
-            } else {
-                (String::from(""), String::from("--"))
-            }
-        };
+        eol_data.prepare(&vm, detectors).await;
+        let detection = eol_detection::detect(&vm, detectors, eol_data.data());
+        summary.add(&vm, &detection);
+        if !detection.classified && !vm.custom_image {
+            unclassified.add(&vm);
+        }
+        if !rows.matches(&vm, &detection) {
+            continue;
+        }
 
         let line = format!(
-            "{};{};{};{:?};{};{};{};{};{};{}\n",
-            version_info.0,
-            version_info.1,
+            "{};{};{};{:?};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{};{}\n",
+            detection.version,
+            detection.status,
             vm.id,
             vm.os_type,
             vm.subscription_id,
@@ -352,10 +1423,126 @@ async fn write_to_csv(
             vm.offer,
             vm.sku,
             vm.version,
-            vm.exact_version
+            vm.exact_version,
+            detection.component,
+            detection.support_tier,
+            detection.source,
+            vm.plan_publisher,
+            vm.plan_product,
+            vm.plan_name,
+            vm.license_type,
+            vm.azure_deprecation,
+            vm.gallery_image,
+            vm.resource_type,
+            vm.instances,
+            vm.scale_set,
+            detection.sql_version,
+            detection.sql_status,
+            vm.power_state,
+            vm.tenant_id,
+            data_as_of(&detection),
+            vm.name(),
+            vm.resource_group(),
+            vm.location,
+            vm.vm_size,
+            vm.subscription_name,
+            created(&vm),
+            detection.phase,
+            vm.tag_fields(&args.tag_columns).join(";")
         );
-        f.write(line.as_bytes())?;
+        f.write_all(line.as_bytes())?;
+        progress::written();
+    }
+    progress::finish();
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        f.write_all(format!("{}\n", INCOMPLETE).as_bytes())?;
+    }
+    unclassified.write_csv(&unclassified_file)?;
+    let mut eol_csv = BufWriter::new(File::create(eol_file)?);
+    eol_csv.write_all(b"Product;Source;Fetched\n")?;
+    for (product, provenance) in eol::provenance() {
+        let line = format!(
+            "{};{};{}\n",
+            product,
+            provenance.origin.as_str(),
+            fetched(&provenance)
+        );
+        eol_csv.write_all(line.as_bytes())?;
+    }
+    if !skipped.entries().is_empty() {
+        skipped.write_csv(&skipped_file)?;
     }
 
-    Ok(())
+    Ok(unclassified)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_mappings, eol, parse_urn, with_env, Cli, Config, EolData};
+    use clap::CommandFactory;
+    use std::time::Duration;
+
+    #[test]
+    fn test_with_env() {
+        let command = with_env(Cli::command());
+        command.clone().debug_assert();
+        let report = command.find_subcommand("report").unwrap();
+        let env = |id: &str| {
+            report
+                .get_arguments()
+                .find(|a| a.get_id() == id)
+                .and_then(|a| a.get_env())
+                .map(|e| e.to_string_lossy().to_string())
+        };
+        assert_eq!(
+            env("eol_cache_ttl").as_deref(),
+            Some("AZINDEX_EOL_CACHE_TTL")
+        );
+        assert_eq!(env("out"), None);
+        let update = command
+            .find_subcommand("eol")
+            .and_then(|eol| eol.find_subcommand("update"))
+            .unwrap();
+        assert!(update.get_arguments().any(|a| a.get_env().is_some()));
+    }
+
+    #[test]
+    fn test_parse_urn() {
+        let vm =
+            parse_urn("Canonical:0001-com-ubuntu-server-bionic:18_04-lts-gen2:latest").unwrap();
+        assert_eq!(vm.publisher, "Canonical");
+        assert_eq!(vm.sku, "18_04-lts-gen2");
+        assert_eq!(vm.exact_version, "");
+        let vm = parse_urn("RedHat:RHEL:8_6:8.6.2022052401").unwrap();
+        assert_eq!(vm.exact_version, "8.6.2022052401");
+        assert!(parse_urn("RedHat:RHEL:8_6").is_err());
+        assert!(parse_urn("RedHat::8_6:latest").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_mappings_provenance() {
+        let dir = std::env::temp_dir().join(format!("azindex-eol-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("all.json"), r#"["ubuntu"]"#).unwrap();
+        eol::set_cache(eol::CacheOptions {
+            dir: Some(dir.clone()),
+            ttl: Duration::from_secs(3600),
+            refresh: false,
+            offline: true,
+        });
+        let config: Config = toml::from_str(
+            r#"
+            [[mappings]]
+            publisher = "contoso"
+            product = "ubuntu"
+            "#,
+        )
+        .unwrap();
+        check_mappings(&config, &EolData::new()).await;
+        // The product list isn't EOL data the report is based on
+        assert!(!eol::provenance()
+            .iter()
+            .any(|(product, _)| product == "all"));
+        _ = std::fs::remove_dir_all(&dir);
+    }
 }
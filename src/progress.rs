@@ -0,0 +1,89 @@
+use crate::logging::{enabled, is_json, Level, Logger};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// Subscriptions listed so far across tenants, and those finished
+static SUBSCRIPTIONS: AtomicUsize = AtomicUsize::new(0);
+static SUBSCRIPTIONS_DONE: AtomicUsize = AtomicUsize::new(0);
+// Resources sent to the report, after filtering and deduplication
+static FOUND: AtomicUsize = AtomicUsize::new(0);
+static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+// How often the counts are logged when stderr isn't a terminal
+const LOG_EVERY: Duration = Duration::from_secs(60);
+
+pub fn add_subscriptions(count: usize) {
+    SUBSCRIPTIONS.fetch_add(count, Ordering::Relaxed);
+}
+
+pub fn subscription_done() {
+    SUBSCRIPTIONS_DONE.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn found() {
+    FOUND.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn written() {
+    WRITTEN.fetch_add(1, Ordering::Relaxed);
+}
+
+fn counts() -> String {
+    format!(
+        "{}/{} subscriptions, {} resources found, {} rows written",
+        SUBSCRIPTIONS_DONE.load(Ordering::Relaxed),
+        SUBSCRIPTIONS.load(Ordering::Relaxed),
+        FOUND.load(Ordering::Relaxed),
+        WRITTEN.load(Ordering::Relaxed)
+    )
+}
+
+static TASK: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// Shows the counts of the running scan, redrawn on stderr while it is a
+/// terminal and logged every minute otherwise, e.g. under a scheduler
+pub fn start() {
+    if !enabled(Level::Normal) {
+        return;
+    }
+    // JSON logs are read by machines, they get the counts every minute
+    let terminal = std::io::stderr().is_terminal() && !is_json();
+    let task = tokio::spawn(async move {
+        let tick = match terminal {
+            true => Duration::from_millis(100),
+            false => LOG_EVERY,
+        };
+        let mut interval = tokio::time::interval(tick);
+        // The first tick completes immediately
+        interval.tick().await;
+        for frame in SPINNER.iter().cycle() {
+            interval.tick().await;
+            if terminal {
+                // Log lines start at the first column and overwrite it, the
+                // next tick draws it again below them
+                let mut stderr = std::io::stderr().lock();
+                _ = write!(stderr, "\x1b[2K{} {}\r", frame, counts());
+                _ = stderr.flush();
+            } else {
+                Logger::new().info(counts());
+            }
+        }
+    });
+    *TASK.lock().unwrap() = Some(task);
+}
+
+/// Stops showing the counts and clears the line, once every result is
+/// written
+pub fn finish() {
+    if let Some(task) = TASK.lock().unwrap().take() {
+        task.abort();
+        if std::io::stderr().is_terminal() && !is_json() {
+            _ = write!(std::io::stderr(), "\x1b[2K");
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::inventory::arm;
+use crate::logging::{error, warn, Logger};
+use azure_core::error::ErrorKind;
+use azure_mgmt_subscription::models::subscription::State;
+use futures::stream::StreamExt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A subscription visible to the credential
+#[derive(Debug, Clone, Default)]
+pub struct Subscription {
+    pub id: String,
+    pub name: String,
+    // Home tenant, differs from the one signed in to for subscriptions
+    // delegated through Azure Lighthouse
+    pub tenant_id: String,
+    // Enabled, Warned or PastDue
+    pub state: String,
+}
+
+/// `--subscription` and `--exclude-subscription`, both match the ID or the
+/// display name case-insensitively. Without includes every subscription is
+/// scanned.
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl SubscriptionFilter {
+    pub fn matches(&self, subscription: &Subscription) -> bool {
+        let named = |wanted: &String| {
+            wanted.eq_ignore_ascii_case(&subscription.id)
+                || wanted.eq_ignore_ascii_case(&subscription.name)
+        };
+        (self.include.is_empty() || self.include.iter().any(named))
+            && !self.exclude.iter().any(named)
+    }
+}
+
+/// Subscriptions that couldn't be scanned, or only partly, listed in the
+/// report so it doesn't silently have holes
+#[derive(Debug, Default)]
+pub struct Skipped {
+    // Subscription ID and why it was skipped, first failure only
+    entries: Mutex<Vec<(String, String)>>,
+}
+
+impl Skipped {
+    pub fn add(&self, subscription_id: &str, reason: String) {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.iter().any(|(id, _)| id == subscription_id) {
+            entries.push((subscription_id.to_string(), reason));
+        }
+    }
+
+    pub fn contains(&self, subscription_id: &str) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(id, _)| id == subscription_id)
+    }
+
+    pub fn entries(&self) -> Vec<(String, String)> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn print(&self) {
+        let entries = self.entries();
+        if entries.is_empty() {
+            return;
+        }
+        let mut log = Logger::new();
+        log.warn(format!("{} skipped subscriptions:", entries.len()));
+        for (id, reason) in &entries {
+            log.indent(1).log(format!("{}: {}", id, reason));
+        }
+    }
+
+    pub fn write_csv(&self, file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut f = BufWriter::new(File::create(file)?);
+        f.write_all(b"Subscription;Reason\n")?;
+        for (id, reason) in self.entries() {
+            f.write_all(format!("{};{}\n", id, reason).as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// `403 AuthorizationFailed` rather than the whole response body, for
+/// failures of the compute client and of plain ARM calls alike
+pub fn failure_reason(e: &(dyn std::error::Error + 'static)) -> String {
+    if let Some(e) = e.downcast_ref::<arm::ResponseError>() {
+        return format!("{} {}", e.status, e.code).trim_end().to_string();
+    }
+    match e.downcast_ref::<azure_core::Error>().map(|e| e.kind()) {
+        Some(ErrorKind::HttpResponse { status, error_code }) => format!(
+            "{} {}",
+            u16::from(*status),
+            error_code.as_deref().unwrap_or_default()
+        )
+        .trim_end()
+        .to_string(),
+        _ => e.to_string(),
+    }
+}
+
+/// Every subscription of the tenant the filter lets through. Disabled and
+/// deleted subscriptions can't be listed and are recorded as skipped.
+pub async fn list_subscriptions(
+    client: &azure_mgmt_subscription::Client,
+    filter: &SubscriptionFilter,
+    skipped: &Skipped,
+) -> Vec<Subscription> {
+    let mut subscriptions = Vec::new();
+    let mut pages = client.subscriptions_client().list().into_stream();
+    while let Some(page) = pages.next().await {
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Listing subscriptions failed: {}", e);
+                break;
+            }
+        };
+        for sub in page.value {
+            let state = sub.state.clone();
+            let subscription = Subscription {
+                id: sub.subscription_id.unwrap_or_default(),
+                name: sub.display_name.unwrap_or_default(),
+                tenant_id: sub.tenant_id.unwrap_or_default(),
+                state: state
+                    .as_ref()
+                    .map(|s| format!("{:?}", s))
+                    .unwrap_or_default(),
+            };
+            if !filter.matches(&subscription) {
+                continue;
+            }
+            match state {
+                Some(state @ (State::Disabled | State::Deleted)) => {
+                    skipped.add(&subscription.id, format!("Subscription is {:?}", state))
+                }
+                _ => subscriptions.push(subscription),
+            }
+        }
+    }
+    for wanted in &filter.include {
+        let found = subscriptions
+            .iter()
+            .any(|s| wanted.eq_ignore_ascii_case(&s.id) || wanted.eq_ignore_ascii_case(&s.name));
+        if !found {
+            warn!("Subscription {} not found or excluded", wanted);
+        }
+    }
+    subscriptions
+}
+
+#[cfg(test)]
+mod test {
+    use super::{failure_reason, Subscription, SubscriptionFilter};
+    use crate::inventory::arm::ResponseError;
+
+    fn subscription(id: &str, name: &str) -> Subscription {
+        Subscription {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let prod = subscription("3f2c1b1e-0000-4000-8000-000000000001", "Contoso Prod");
+        let dev = subscription("3f2c1b1e-0000-4000-8000-000000000002", "Contoso Dev");
+        let all = SubscriptionFilter::default();
+        assert!(all.matches(&prod) && all.matches(&dev));
+
+        let only_prod = SubscriptionFilter {
+            include: vec![String::from("contoso prod")],
+            exclude: vec![],
+        };
+        assert!(only_prod.matches(&prod));
+        assert!(!only_prod.matches(&dev));
+
+        let not_dev = SubscriptionFilter {
+            include: vec![],
+            exclude: vec![String::from("3F2C1B1E-0000-4000-8000-000000000002")],
+        };
+        assert!(not_dev.matches(&prod));
+        assert!(!not_dev.matches(&dev));
+    }
+
+    #[test]
+    fn test_failure_reason() {
+        let denied = ResponseError {
+            status: 403,
+            code: String::from("AuthorizationFailed"),
+            message: String::from("The client does not have authorization"),
+        };
+        assert_eq!(failure_reason(&denied), "403 AuthorizationFailed");
+        let io = std::io::Error::other("connection reset");
+        assert_eq!(failure_reason(&io), "connection reset");
+    }
+}
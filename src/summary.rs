@@ -0,0 +1,148 @@
+use crate::eol_detection::detector::Detection;
+use crate::logging::Logger;
+use crate::subscriptions::Skipped;
+use crate::VMResult;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Counts printed once the report is written, so the outcome of a run is
+/// known without opening it
+pub struct Summary {
+    started: Instant,
+    resources: usize,
+    // Scale sets count every instance
+    instances: i64,
+    statuses: BTreeMap<String, usize>,
+    systems: BTreeMap<String, usize>,
+    // EOL dates of the resources reported as ending
+    ending: BTreeMap<NaiveDate, usize>,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Summary {
+            started: Instant::now(),
+            resources: 0,
+            instances: 0,
+            statuses: BTreeMap::new(),
+            systems: BTreeMap::new(),
+            ending: BTreeMap::new(),
+        }
+    }
+}
+
+/// Product and version, or what the resource is for detectors without an
+/// endoflife.date product
+fn system(vm: &VMResult, detection: &Detection) -> String {
+    let name = match (detection.product.as_str(), vm.guest_os_name.as_str()) {
+        ("", "") if !detection.classified => return String::from("Unclassified"),
+        ("", "") => vm.resource_type.as_str(),
+        ("", guest) => guest,
+        (product, _) => product,
+    };
+    format!("{} {}", name, detection.version).trim().to_string()
+}
+
+/// 1h02m05s
+fn elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60),
+    }
+}
+
+impl Summary {
+    pub fn add(&mut self, vm: &VMResult, detection: &Detection) {
+        self.resources += 1;
+        self.instances += vm.instances.max(1);
+        *self
+            .statuses
+            .entry(detection.verdict().as_str().to_string())
+            .or_default() += 1;
+        *self.systems.entry(system(vm, detection)).or_default() += 1;
+        let date = detection.status.strip_prefix("Ending ");
+        if let Some(date) = date.and_then(|d| NaiveDate::from_str(d).ok()) {
+            *self.ending.entry(date).or_default() += 1;
+        }
+    }
+
+    pub fn eol(&self) -> usize {
+        self.statuses.get("EOL").copied().unwrap_or(0)
+    }
+
+    /// Resources reported as ending with an EOL date before `end`
+    pub fn ending_before(&self, end: NaiveDate) -> usize {
+        self.ending.range(..end).map(|(_, count)| count).sum()
+    }
+
+    /// Resources without a verdict, custom images included
+    pub fn unknown(&self) -> usize {
+        ["Unknown", "Custom image"]
+            .iter()
+            .filter_map(|status| self.statuses.get(*status))
+            .sum()
+    }
+
+    pub fn print(&self, skipped: &Skipped) {
+        let mut log = Logger::new();
+        log.info(format!(
+            "{} resources ({} instances) in {}",
+            self.resources,
+            self.instances,
+            elapsed(self.started.elapsed())
+        ));
+        for (status, count) in &self.statuses {
+            log.indent(1).log(format!("{}: {}", status, count));
+        }
+        log.info("By operating system:");
+        for (system, count) in &self.systems {
+            log.indent(1).log(format!("{}: {}", system, count));
+        }
+        let skipped = skipped.entries().len();
+        if skipped > 0 {
+            log.warn(format!("{} subscriptions skipped", skipped));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{elapsed, Summary};
+    use crate::eol_detection::detector::Detection;
+    use crate::VMResult;
+    use std::time::Duration;
+
+    #[test]
+    fn test_summary() {
+        let mut summary = Summary::default();
+        let vm = VMResult {
+            instances: 3,
+            ..Default::default()
+        };
+        let detection = |status: &str| Detection {
+            version: String::from("18.04"),
+            status: status.to_string(),
+            product: String::from("ubuntu"),
+            classified: true,
+            ..Default::default()
+        };
+        summary.add(&vm, &detection("EOL"));
+        summary.add(&vm, &detection("Ending 2026-05-31"));
+        summary.add(&vm, &Detection::default());
+        assert_eq!(summary.resources, 3);
+        assert_eq!(summary.instances, 9);
+        assert_eq!(summary.statuses["Ending"], 1);
+        assert_eq!(summary.statuses["Unknown"], 1);
+        assert_eq!((summary.eol(), summary.unknown()), (1, 1));
+        let date = |d: &str| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        assert_eq!(summary.ending_before(date("2026-06-01")), 1);
+        assert_eq!(summary.ending_before(date("2026-05-31")), 0);
+        assert_eq!(summary.systems["ubuntu 18.04"], 2);
+        assert_eq!(summary.systems["Unclassified"], 1);
+        assert_eq!(elapsed(Duration::from_secs(3725)), "1h02m05s");
+    }
+}
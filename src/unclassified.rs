@@ -0,0 +1,100 @@
+use crate::logging::Logger;
+use crate::VMResult;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Image references no detector matched, counted per publisher/offer/SKU
+#[derive(Debug, Default)]
+pub struct Unclassified {
+    pub images: BTreeMap<(String, String, String), usize>,
+}
+
+impl Unclassified {
+    pub fn add(&mut self, vm: &VMResult) {
+        let key = (
+            vm.publisher.to_lowercase(),
+            vm.offer.to_lowercase(),
+            vm.sku.to_lowercase(),
+        );
+        *self.images.entry(key).or_default() += 1;
+    }
+
+    /// Lists the images, with the endoflife.date product a mapping could
+    /// use where the offer names one of `products`
+    pub fn print(&self, products: &[String]) {
+        if self.images.is_empty() {
+            return;
+        }
+        let mut log = Logger::new();
+        log.warn(format!("{} unclassified images:", self.images.len()));
+        for ((publisher, offer, sku), count) in &self.images {
+            let suggestion = suggest_product(offer, products)
+                .map(|p| format!(" (endoflife.date tracks '{}', see --config mappings)", p))
+                .unwrap_or_default();
+            log.indent(1).log(format!(
+                "{}x {}:{}:{}{}",
+                count, publisher, offer, sku, suggestion
+            ));
+        }
+    }
+
+    pub fn write_csv(&self, file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut f = BufWriter::new(File::create(file)?);
+        f.write_all(b"Count;Publisher;Offer;SKU\n")?;
+        for ((publisher, offer, sku), count) in &self.images {
+            f.write_all(format!("{};{};{};{}\n", count, publisher, offer, sku).as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// The longest product whose name appears in an offer as whole words,
+/// `oracle-linux-9` names `oracle-linux`. The kernel (`linux`) is in
+/// nearly every offer and never suggested.
+fn suggest_product<'a>(offer: &str, products: &'a [String]) -> Option<&'a str> {
+    let words = |s: &str| -> Vec<String> {
+        s.to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    let offer = words(offer);
+    products
+        .iter()
+        .filter(|product| product.as_str() != "linux")
+        .filter(|product| {
+            let product = words(product);
+            !product.is_empty()
+                && offer
+                    .windows(product.len())
+                    .any(|w| w == product.as_slice())
+        })
+        .max_by_key(|product| product.len())
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod test {
+    use super::suggest_product;
+
+    #[test]
+    fn test_suggest_product() {
+        let products: Vec<String> = ["oracle-linux", "linux", "opensuse", "nginx"]
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(
+            suggest_product("Oracle-Linux-9", &products),
+            Some("oracle-linux")
+        );
+        assert_eq!(
+            suggest_product("nginx-plus-ubuntu", &products),
+            Some("nginx")
+        );
+        assert_eq!(suggest_product("vendor-linux", &products), None);
+        assert_eq!(suggest_product("opensuseleap", &products), None);
+    }
+}
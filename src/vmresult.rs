@@ -1,19 +1,158 @@
 use azure_mgmt_compute::models::os_disk::OsType;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VMResult {
     pub id: String,
+    // Virtual machine or Scale set
+    pub resource_type: String,
+    // Capacity of a scale set, 1 for a single VM
+    pub instances: i64,
+    // Flexible scale set an instance belongs to
+    pub scale_set: String,
+    pub location: String,
     pub subscription_id: String,
+    // Display name, empty in caches written before it was recorded
+    pub subscription_name: String,
     pub publisher: String,
     pub offer: String,
     pub sku: String,
     pub version: String,
     pub exact_version: String,
     pub os_type: Option<OsType>,
+    // No marketplace image reference, e.g. a specialized disk or custom image
+    pub custom_image: bool,
+    // Marketplace purchase plan, also present on VMs created from a copy of
+    // a paid or BYOS image
+    pub plan_publisher: String,
+    pub plan_product: String,
+    pub plan_name: String,
+    // Windows_Server (Hybrid Benefit), RHEL_BYOS, SLES_BYOS, empty for PAYG
+    pub license_type: String,
+    // KubernetesOfficial or AKSLongTermSupport, shown as the support tier of
+    // AKS clusters
+    pub support_plan: String,
+    // imageDeprecationStatus of the marketplace image version
+    pub azure_deprecation: String,
+    // Compute Gallery image version the VM was created from
+    pub gallery_image: String,
+    // Reported by the VM agent, only filled in with --deep
+    pub guest_os_name: String,
+    pub guest_os_version: String,
+    // End of support Azure publishes for the resource's own version, for
+    // services endoflife.date doesn't track
+    pub support_end: Option<NaiveDate>,
+    // SQL Server offer (SQL2019-WS2022) of a VM registered with the SQL IaaS
+    // Agent extension
+    pub sql_server: String,
+    // running, stopped, deallocated, only known for VMs
+    pub power_state: String,
+    // Resource tags, only fetched for VMs and scale sets
+    pub tags: HashMap<String, String>,
+    // Home tenant of the subscription
+    pub tenant_id: String,
+    // Standard_D4s_v5, only known for VMs and scale sets
+    pub vm_size: String,
+    // timeCreated of VMs and scale sets
+    pub created: Option<NaiveDate>,
 }
 
 impl VMResult {
-    pub fn csv_header_line() -> String {
-        String::from("Deprecated;Version (detected);ID;OS;Subscription;Publisher;Offer;SKU;Version;Exact version\n")
+    /// The VM, scale set or other resource name, the last segment of its ID
+    pub fn name(&self) -> &str {
+        self.id
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// The segment after `resourceGroups` in the ID, empty for IDs without
+    /// one
+    pub fn resource_group(&self) -> &str {
+        let mut segments = self.id.split('/');
+        segments
+            .by_ref()
+            .find(|s| s.eq_ignore_ascii_case("resourcegroups"));
+        segments.next().unwrap_or_default()
+    }
+
+    /// Headers of the tag columns: one per `--tag-column`, or a single Tags
+    /// column without them
+    pub fn tag_headers(tag_columns: &[String]) -> Vec<String> {
+        match tag_columns.is_empty() {
+            true => vec![String::from("Tags")],
+            false => tag_columns
+                .iter()
+                .map(|key| format!("Tag {}", key))
+                .collect(),
+        }
+    }
+
+    /// Values of the tag columns. Tag names are case-insensitive in Azure,
+    /// `;` would split the CSV field.
+    pub fn tag_fields(&self, tag_columns: &[String]) -> Vec<String> {
+        let fields = match tag_columns.is_empty() {
+            true => {
+                let tags: BTreeMap<_, _> = self.tags.iter().collect();
+                let tags: Vec<String> = tags
+                    .into_iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect();
+                vec![tags.join(", ")]
+            }
+            false => tag_columns
+                .iter()
+                .map(|wanted| {
+                    self.tags
+                        .iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case(wanted))
+                        .map(|(_, value)| value.clone())
+                        .unwrap_or_default()
+                })
+                .collect(),
+        };
+        fields.into_iter().map(|f| f.replace(';', ",")).collect()
+    }
+
+    pub fn csv_header_line(tag_columns: &[String]) -> String {
+        let header = String::from("Deprecated;Version (detected);ID;OS;Subscription;Publisher;Offer;SKU;Version;Exact version;EOL component;Support tier;EOL override;Plan publisher;Plan product;Plan name;License type;Azure deprecation;Gallery image;Type;Instances;Scale set;SQL Server;SQL Server status;Power state;Tenant;EOL data as of;Name;Resource group;Location;VM size;Subscription name;Created;Support phase");
+        format!("{};{}\n", header, Self::tag_headers(tag_columns).join(";"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VMResult;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_id_segments() {
+        let vm = VMResult {
+            id: String::from(
+                "/subscriptions/s/resourcegroups/RG-Web/providers/Microsoft.Compute/virtualMachines/web01/",
+            ),
+            ..Default::default()
+        };
+        assert_eq!(vm.name(), "web01");
+        assert_eq!(vm.resource_group(), "RG-Web");
+        assert_eq!(VMResult::default().resource_group(), "");
+    }
+
+    #[test]
+    fn test_tag_fields() {
+        let vm = VMResult {
+            tags: HashMap::from([
+                (String::from("Owner"), String::from("team-a;team-b")),
+                (String::from("env"), String::from("prod")),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(vm.tag_fields(&[]), vec!["Owner=team-a,team-b, env=prod"]);
+        let columns = [String::from("owner"), String::from("costcenter")];
+        assert_eq!(vm.tag_fields(&columns), vec!["team-a,team-b", ""]);
     }
 }